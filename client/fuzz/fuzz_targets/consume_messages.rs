@@ -0,0 +1,57 @@
+//! Messages decoded from the input are consumed by the message handler, emulated into
+//! a `RecordingEmulator`, and the connection is lost at the end.
+#![no_main]
+
+use jerry::configuration::args::LocalhostArgs;
+use jerry::configuration::{get_session_info_localhost, ClipboardRestore, ScreenResolution};
+use jerry::core::emulator::RecordingEmulator;
+use jerry::core::message_handler::ContextAwareMessageHandler;
+use jerry::core::JerryMessage;
+use jerry::proto_rs::ProtoInMsg;
+use jerry::serialization::compression::ClipboardCodec;
+use jerry::MessageConsumer;
+use libfuzzer_sys::fuzz_target;
+use protobuf::CodedInputStream;
+use std::sync::mpsc;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&mode, data)) = data.split_first() else {
+        return;
+    };
+    let args = LocalhostArgs {
+        width: 1920,
+        height: 1080,
+        guid: uuid::Uuid::nil(),
+        name: String::from("Fuzz"),
+        port: jerry::DEFAULT_PORT,
+        password: String::from("2002"),
+        reverse: false,
+    };
+    let mut params = get_session_info_localhost(&args).servers.remove(0);
+    params.clipboard_restore = ClipboardRestore::Keep;
+    if mode & 1 == 1 {
+        params.monitor = ScreenResolution::Dynamic;
+    }
+    let (transmitter, events) = mpsc::sync_channel(16);
+    let emulator = RecordingEmulator::with_cursor(400, 300);
+    let mut handler =
+        ContextAwareMessageHandler::with_emulator(transmitter, params, Box::new(emulator));
+
+    let mut codec = ClipboardCodec::new();
+    let mut input = CodedInputStream::from_bytes(data);
+    while let Ok(mut msg) = input.read_message::<ProtoInMsg>() {
+        if codec.decode(&mut msg).is_err() {
+            continue;
+        }
+        match JerryMessage::from(msg) {
+            // the system clipboard is left alone
+            JerryMessage::Clipboard(..) => {}
+            msg => _ = handler.consume(msg),
+        }
+        while events.try_recv().is_ok() {}
+        if handler.finished() {
+            break;
+        }
+    }
+    handler.disconnected();
+});
@@ -0,0 +1,34 @@
+//! Length delimited `MasterMessage`s as read from the server, down to a `JerryMessage`.
+#![no_main]
+
+use jerry::core::replay::{describe, MessageKind};
+use jerry::core::JerryMessage;
+use jerry::proto_rs::{ProtoInMsg, ProtoOutMsg};
+use jerry::serialization::compression::ClipboardCodec;
+use jerry::serialization::decode::{decode, DecodeOptions, Origin};
+use libfuzzer_sys::fuzz_target;
+use protobuf::CodedInputStream;
+
+fuzz_target!(|data: &[u8]| {
+    let mut codec = ClipboardCodec::new();
+    let mut input = CodedInputStream::from_bytes(data);
+    while let Ok(mut msg) = input.read_message::<ProtoInMsg>() {
+        _ = MessageKind::of(&msg);
+        if codec.decode(&mut msg).is_err() {
+            continue;
+        }
+        let msg = JerryMessage::from(msg);
+        _ = describe(&msg);
+        _ = ProtoInMsg::from(msg);
+    }
+    _ = CodedInputStream::from_bytes(data).read_message::<ProtoOutMsg>();
+
+    for origin in [Origin::Server, Origin::Client] {
+        let options = DecodeOptions {
+            origin,
+            key: None,
+            skip: None,
+        };
+        _ = decode(&mut data.to_vec(), &options, &mut std::io::sink());
+    }
+});
@@ -0,0 +1,28 @@
+//! Key codes sent by the server, mapped to `JKey` and to the codes of the platform.
+#![no_main]
+
+use jerry::emulation::{key_name, JKey};
+use libfuzzer_sys::fuzz_target;
+
+#[cfg(target_os = "linux")]
+fn platform_code(key: JKey) {
+    _ = jerry::emulation::linux_k::code_from_key(key);
+}
+#[cfg(target_os = "macos")]
+fn platform_code(key: JKey) {
+    _ = jerry::emulation::mac_k::code_from_key(key);
+}
+/// Windows emulates the virtual key codes as they are
+#[cfg(target_os = "windows")]
+fn platform_code(_key: JKey) {}
+
+fuzz_target!(|codes: &[u8]| {
+    for chunk in codes.chunks(4) {
+        let mut code = [0u8; 4];
+        code[..chunk.len()].copy_from_slice(chunk);
+        _ = key_name(u32::from_le_bytes(code));
+    }
+    for &code in codes {
+        platform_code(JKey::from(code));
+    }
+});
@@ -1,10 +1,89 @@
+use crate::core::replay::MessageKind;
+use crate::serialization::decode::Origin;
 use crate::DEFAULT_PORT;
 use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 use uuid::Uuid;
 
 #[derive(Subcommand, Clone, Debug)]
 pub enum Commands {
     Localhost(LocalhostArgs),
+    /// Replay a recorded macro
+    Play(PlayArgs),
+    /// Replay the messages of a capture (see --capture)
+    Replay(ReplayArgs),
+    /// Print the messages of a dumped protocol stream
+    Decode(DecodeArgs),
+    /// Send a request to the control socket of a running client (see --control)
+    Ctl(CtlArgs),
+}
+#[derive(Args, Clone, Debug)]
+pub struct PlayArgs {
+    /// Name of the macro (macros/<name>.toml)
+    #[arg(value_parser = macro_name)]
+    pub name: String,
+    /// Replay speed multiplier
+    #[arg(long, short, default_value_t = 1.0)]
+    pub speed: f64,
+}
+#[derive(Args, Clone, Debug)]
+pub struct ReplayArgs {
+    /// Capture file
+    pub file: PathBuf,
+    /// Replay speed multiplier
+    #[arg(long, short, default_value_t = 1.0)]
+    pub speed: f64,
+    /// Print the messages and the emulator calls with key names instead of emulating them
+    #[arg(long, short = 'n', default_value_t = false)]
+    pub dry_run: bool,
+    /// Replay only these kinds of messages, sessions are always replayed
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub only: Vec<MessageKind>,
+}
+#[derive(Args, Clone, Debug)]
+pub struct DecodeArgs {
+    /// Side that wrote the stream
+    #[arg(value_enum)]
+    pub origin: Origin,
+    /// Length delimited messages or a raw TCP payload, standard input if omitted or -
+    pub file: Option<PathBuf>,
+    /// ChaCha20 key of the stream (64 hex digits), the payload is decrypted
+    #[arg(long, value_parser = parse_hex::<32>, requires = "nonce")]
+    pub key: Option<[u8; 32]>,
+    /// ChaCha20 nonce of the stream (24 hex digits)
+    #[arg(long, value_parser = parse_hex::<12>, requires = "key")]
+    pub nonce: Option<[u8; 12]>,
+    /// Bytes to skip at the start, the key exchange by default if the stream is decrypted
+    #[arg(long)]
+    pub skip: Option<usize>,
+}
+
+fn macro_name(name: &str) -> Result<String, String> {
+    crate::core::macros::check_name(name).map(|_| name.to_owned())
+}
+
+fn parse_hex<const N: usize>(hex: &str) -> Result<[u8; N], String> {
+    let digits = hex.trim();
+    if digits.len() != 2 * N || !digits.is_ascii() {
+        return Err(format!("expected {} hex digits", 2 * N));
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[2 * i..2 * i + 2], 16)
+            .map_err(|_| format!("invalid hex digits {}", &digits[2 * i..2 * i + 2]))?;
+    }
+    Ok(bytes)
+}
+#[derive(Args, Clone, Debug)]
+pub struct CtlArgs {
+    /// status, disconnect, reconnect, switch, pause, resume or subscribe
+    pub method: String,
+    /// Parameters as a JSON object, e.g. '{"server": "office"}'
+    pub params: Option<String>,
+    /// Control socket, $XDG_RUNTIME_DIR/jerry_client.sock by default,
+    /// or jerry_client-<uid>.sock in the temporary directory
+    #[arg(long)]
+    pub socket: Option<PathBuf>,
 }
 #[derive(Args, Clone, Debug)]
 pub struct LocalhostArgs {
@@ -22,6 +101,9 @@ pub struct LocalhostArgs {
     pub port: u16,
     #[arg(default_value = "2002")]
     pub password: String,
+    /// Listen on the port and wait for the server to connect
+    #[arg(long, default_value_t = false)]
+    pub reverse: bool,
 }
 
 #[derive(Parser, Clone, Debug)]
@@ -33,4 +115,20 @@ pub struct Cli {
     pub visualizer: bool,
     #[arg(long, short, default_value_t = false)]
     pub emulate: bool,
+    /// Record received input into the macro with the given name
+    #[arg(long, short, value_parser = macro_name)]
+    pub record: Option<String>,
+    /// Write the decrypted traffic of every connection into the file
+    #[arg(long)]
+    pub capture: Option<PathBuf>,
+    /// Run as a service without a terminal: no prompts, SIGTERM or SIGINT stops the client
+    #[arg(long, short, default_value_t = false, conflicts_with = "visualizer")]
+    pub daemon: bool,
+    /// Log into the file instead of the standard error
+    #[arg(long, requires = "daemon")]
+    pub log_file: Option<PathBuf>,
+    /// Accept JSON-RPC requests on the Unix socket, $XDG_RUNTIME_DIR/jerry_client.sock by default,
+    /// or jerry_client-<uid>.sock in the temporary directory
+    #[arg(long, value_name = "SOCKET")]
+    pub control: Option<Option<PathBuf>>,
 }
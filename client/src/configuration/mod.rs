@@ -1,31 +1,51 @@
 pub mod args;
 mod provider;
 use self::provider::ServerConfig;
+use crate::connection::discovery::{self, DiscoveredServer};
+use crate::connection::ServerAddress;
+use crate::core::clipboard::ClipboardFilter;
 use crate::{configuration, DisplayMode, CONFIGURATION_FILE};
 pub use args::Cli;
 use dialoguer::{console::Term, theme::ColorfulTheme, Input, Select};
 pub use provider::ConfigProvider;
-use std::{io::ErrorKind, net::Ipv4Addr, str::FromStr};
+pub use provider::{ClipboardDirection, ClipboardFormat, ClipboardPolicy, ClipboardRestore};
+pub use provider::{Hooks, ReconnectAction, ReconnectPolicy};
+use std::net::IpAddr;
+use std::{io::ErrorKind, time::Duration};
 use tracing::{self, error, info};
 
-pub fn get_session_info_localhost(args: &args::LocalhostArgs) -> SessionParams {
-    SessionParams {
+const DISCOVERY_TIMEOUT: Duration = Duration::from_millis(700);
+
+pub fn get_session_info_localhost(args: &args::LocalhostArgs) -> ServerGroup {
+    let params = SessionParams {
         client_name: args.name.clone(),
         client_guid: args.guid.to_string(),
-        port: args.port,
         monitor: ScreenResolution::Static(MonitorSize {
             width: args.width,
             height: args.height,
         }),
+        server_name: String::from("localhost"),
         server_password: args.password.clone(),
 
-        ip: Ipv4Addr::LOCALHOST,
+        address: ServerAddress {
+            host: String::from("127.0.0.1"),
+            port: args.port,
+        },
+        reverse: args.reverse,
         display_mode: DisplayMode::CurrentState,
         emulate_events: false,
+        clipboard_restore: ClipboardRestore::default(),
+        clipboard_filter: ClipboardFilter::default(),
+        reconnect: ReconnectPolicy::default(),
+    };
+    ServerGroup {
+        servers: vec![params],
+        failback: false,
+        hooks: Hooks::default(),
     }
 }
 
-pub fn get_session_info(cli: Cli) -> Option<SessionParams> {
+pub fn get_session_info(cli: Cli) -> Option<ServerGroup> {
     let mut provider = match configuration::ConfigProvider::new(CONFIGURATION_FILE) {
         Ok(cp) => cp,
         Err(_e) => {
@@ -34,7 +54,8 @@ pub fn get_session_info(cli: Cli) -> Option<SessionParams> {
         }
     };
 
-    if !provider.connect_without_confirmation() {
+    // without a terminal the server selected last is connected to
+    if !provider.connect_without_confirmation() && !cli.daemon {
         if let Err(_e) = crate::configuration::update_configuration_using_prompt(&mut provider) {
             // "User did not select a server to connect to;
             return None;
@@ -45,16 +66,66 @@ pub fn get_session_info(cli: Cli) -> Option<SessionParams> {
             .expect("Error saving configuration");
     }
 
-    let client = provider.get_init_info_for_last_server().unwrap();
+    let Some(previous) = provider.get_previous().map(str::to_owned) else {
+        error!("No server selected, set connection.previous in the configuration");
+        return None;
+    };
+    let (names, failback) = match provider.get_group(&previous) {
+        Some(group) => (group.servers.clone(), group.failback),
+        None => (vec![previous], false),
+    };
 
-    let server_specific = provider
-        .get_last_connection_info()
-        .expect("Set last connection in toml");
+    let mut servers = Vec::new();
+    for name in names {
+        let Some(server_specific) = provider.get_server_info(&name) else {
+            error!("Server '{}' is not in the configuration", name);
+            return None;
+        };
+        if cli.daemon {
+            // the standard output ends up in the journal
+            info!(
+                "Server '{}': {}:{}",
+                server_specific.name, server_specific.host, server_specific.port
+            );
+        } else {
+            let shown = ServerConfig {
+                password: String::from("********"),
+                ..server_specific.clone()
+            };
+            println!(
+                "\n========================\n Connecting to server : \n{}\n========================\n",
+                toml::to_string_pretty(&shown).expect("Serialization failed")
+            );
+        }
+        servers.push(get_server_params(&provider, &cli, server_specific)?);
+    }
+    Some(ServerGroup {
+        servers,
+        failback,
+        hooks: provider.get_hooks(),
+    })
+}
 
-    println!(
-        "\n========================\n Connecting to server : \n{}\n========================\n",
-        toml::to_string_pretty(&server_specific).expect("Serialization failed")
-    );
+fn get_server_params(
+    provider: &ConfigProvider,
+    cli: &Cli,
+    server_specific: ServerConfig,
+) -> Option<SessionParams> {
+    let address = match ServerAddress::parse(&server_specific.host, server_specific.port) {
+        Ok(address) => address,
+        Err(e) => {
+            error!("Server '{}': {}", server_specific.name, e);
+            return None;
+        }
+    };
+    let policy = server_specific.clipboard_policy.unwrap_or_default();
+    let clipboard_filter = match ClipboardFilter::new(&policy) {
+        Ok(filter) => filter,
+        Err(e) => {
+            error!("Server '{}': {}", server_specific.name, e);
+            return None;
+        }
+    };
 
     let display_mode = match cli.visualizer {
         true => DisplayMode::CurrentState,
@@ -62,37 +133,84 @@ pub fn get_session_info(cli: Cli) -> Option<SessionParams> {
     };
 
     Some(SessionParams {
-        client_name: client.name,
-        client_guid: server_specific.guid.unwrap_or(client.default_guid),
+        client_name: provider.get_client_name().to_owned(),
+        client_guid: server_specific
+            .guid
+            .expect("The default guid is filled in by the provider"),
         monitor: ScreenResolution::Dynamic,
 
+        server_name: server_specific.name,
         server_password: server_specific.password,
-        ip: server_specific.ip,
-        port: server_specific.port,
+        emulate_events: !address.is_loopback() | cli.emulate,
+        address,
+        reverse: server_specific.reverse,
 
-        emulate_events: !server_specific.ip.is_loopback() | cli.emulate,
         display_mode,
+        clipboard_restore: server_specific.clipboard.unwrap_or_default(),
+        clipboard_filter,
+        reconnect: provider.get_reconnect_policy(),
     })
 }
 
 pub fn update_configuration_using_prompt(cp: &mut ConfigProvider) -> std::io::Result<()> {
     let servers = cp.get_servers();
+    let groups = cp.get_groups();
+    let group_items: Vec<String> = groups.iter().map(|g| format!("[group] {}", g)).collect();
 
-    let last = cp
-        .get_last_connection_info()
-        .map_or(String::new(), |l| l.name);
+    let last = cp.get_previous().unwrap_or_default();
 
-    let last_index = servers.iter().position(|s| s.eq(&last)).unwrap_or(0);
+    let last_index = servers
+        .iter()
+        .chain(groups.iter())
+        .position(|s| s.eq(last))
+        .unwrap_or(0);
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Select a server and press [ENTER]. Press [ESC]/[Q] to exit.")
-        .default(last_index)
-        .items(&servers[..])
-        .item("[*] Create new record")
-        //.items(&selections[..])
-        .interact_on_opt(&Term::stderr())?;
+    // once servers are saved the network is only searched on request
+    let mut discovered = match servers.is_empty() {
+        true => Some(discover_new_servers(cp)),
+        false => None,
+    };
+    let (selection, discovered) = loop {
+        let found = discovered.clone().unwrap_or_default();
+        let discovered_items: Vec<String> = found.iter().map(|d| format!("[LAN] {}", d)).collect();
+        let theme = ColorfulTheme::default();
+        let mut select = Select::with_theme(&theme);
+        select
+            .with_prompt("Select a server and press [ENTER]. Press [ESC]/[Q] to exit.")
+            .default(last_index)
+            .items(&servers[..])
+            .items(&group_items[..])
+            .items(&discovered_items[..]);
+        if discovered.is_none() {
+            select.item("[*] Search the local network");
+        }
+        let selection = select
+            .item("[*] Create new record")
+            .interact_on_opt(&Term::stderr())?;
+        let search_item = servers.len() + groups.len();
+        match selection {
+            Some(index) if discovered.is_none() && index == search_item => {
+                discovered = Some(discover_new_servers(cp));
+            }
+            Some(index) if discovered.is_none() && index > search_item => {
+                break (Some(index - 1), found)
+            }
+            selection => break (selection, found),
+        }
+    };
+    let listed = servers.len() + groups.len();
     match selection {
-        Some(index) if index == servers.len() => {
+        Some(index) if index >= servers.len() && index < listed => {
+            cp.update_last(&groups[index - servers.len()])
+        }
+        Some(index) if index >= listed && index < listed + discovered.len() => {
+            let new_server = prompt_discovered_server(cp, &discovered[index - listed]);
+            if cp.add_server(&new_server).is_err() {
+                return Err(std::io::Error::new(ErrorKind::InvalidInput, "XXX"));
+            }
+            cp.update_last(&new_server.name);
+        }
+        Some(index) if index == listed + discovered.len() => {
             //Add new Server
             let new_server = prompt_new_server(cp);
             if cp.add_server(&new_server).is_err() {
@@ -112,73 +230,113 @@ pub fn update_configuration_using_prompt(cp: &mut ConfigProvider) -> std::io::Re
     Result::Ok(())
 }
 
+/// Servers announcing on the local network that are not saved yet.
+fn discover_new_servers(cp: &ConfigProvider) -> Vec<DiscoveredServer> {
+    println!("Searching for Jerry servers on the local network...");
+    discovery::discover_lan(DISCOVERY_TIMEOUT)
+        .unwrap_or_else(|e| {
+            info!("LAN discovery failed: {}", e);
+            Vec::new()
+        })
+        .into_iter()
+        .filter(|d| !cp.contains_address(IpAddr::V4(d.ip), d.port))
+        .collect()
+}
+
+fn prompt_discovered_server(cp: &ConfigProvider, server: &DiscoveredServer) -> ServerConfig {
+    let name = prompt_name(cp, &server.name);
+    let password = prompt_password();
+    ServerConfig {
+        name,
+        host: server.ip.to_string(),
+        port: server.port,
+        reverse: false,
+        password,
+        guid: None,
+        clipboard: None,
+        clipboard_policy: None,
+    }
+}
+
 fn prompt_new_server(cp: &ConfigProvider) -> ServerConfig {
-    let name = prompt_name(cp);
-    let ip = prompt_ipv4(false);
-    let port = Input::<u16>::with_theme(&ColorfulTheme::default())
-        .with_prompt("Port:\n")
-        .with_initial_text(cp.get_default_port().to_string())
-        .interact_text()
-        .unwrap();
-    let password = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Password:\n")
-        .interact_text()
-        .unwrap();
+    let name = prompt_name(cp, "");
+    let entered = prompt_host(false, cp.get_default_port());
+    // validated by the prompt
+    let (host, port) = ServerAddress::split(&entered).unwrap_or((entered, None));
+    let port = match port {
+        Some(port) => port,
+        None => Input::<u16>::with_theme(&ColorfulTheme::default())
+            .with_prompt("Port:\n")
+            .with_initial_text(cp.get_default_port().to_string())
+            .interact_text()
+            .unwrap(),
+    };
+    let password = prompt_password();
     ServerConfig {
         name,
-        ip,
+        host,
         port,
+        reverse: false,
         password,
         guid: None,
+        clipboard: None,
+        clipboard_policy: None,
     }
 }
 
-fn prompt_ipv4(recursive: bool) -> Ipv4Addr {
+/// Returns the address exactly as entered, it is resolved on every connection attempt.
+fn prompt_host(recursive: bool, default_port: u16) -> String {
     let prompt_val = if recursive {
-        String::from("IP address, e.g. 192.168.1.66\n")
+        String::from("Address, e.g. 192.168.1.66, fd00::66, jerry.local or jerry.local:8888\n")
     } else {
-        String::from("IP address:\n")
+        String::from("Address (hostname or IP):\n")
     };
-    let ip_res = prompt_ip_par(prompt_val);
-    match ip_res {
-        Some(ip) => ip,
-        None => prompt_ipv4(true),
+    let host_res = prompt_host_par(prompt_val, default_port);
+    match host_res {
+        Some(host) => host,
+        None => prompt_host(true, default_port),
     }
 }
-fn prompt_ip_par(prompt: String) -> Option<Ipv4Addr> {
-    let a = Input::<String>::with_theme(&ColorfulTheme::default())
+fn prompt_host_par(prompt: String, default_port: u16) -> Option<String> {
+    Input::<String>::with_theme(&ColorfulTheme::default())
         .with_prompt(prompt)
         .validate_with({
             move |input: &String| -> Result<(), String> {
-                match Ipv4Addr::from_str(input) {
-                    Ok(_) => Ok(()),
-                    Err(e) => Err(e.to_string()),
-                }
+                ServerAddress::parse(input, default_port).map(|_| ())
             }
         })
-        .interact_text();
-    a.map(|ip| Ipv4Addr::from_str(&ip).unwrap()).ok()
+        .interact_text()
+        .map(|host| host.trim().to_owned())
+        .ok()
+}
+
+fn prompt_password() -> String {
+    Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Password:\n")
+        .interact_text()
+        .unwrap()
 }
 
-fn prompt_name(cp: &ConfigProvider) -> String {
+fn prompt_name(cp: &ConfigProvider, initial: &str) -> String {
     let name_res = Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Name:\n")
+        .with_initial_text(initial)
         .validate_with({
-            let servers_used = cp.get_servers().join(", ");
+            let servers_used = [cp.get_servers(), cp.get_groups()].concat().join(", ");
             let error = std::format!(
                 "Input error: server name must be unique. The following values are already in use: {:?}.",
                 servers_used
             );
             move |input: &String| -> Result<(), String> {
-                match cp.get_server(input) {
-                    None => Ok(()),
-                    Some(_) => Err(error.clone()),
+                match cp.get_server(input).is_some() || cp.get_group(input).is_some() {
+                    false => Ok(()),
+                    true => Err(error.clone()),
                 }
             }
         })
         .interact_text();
     match name_res {
-        Err(_) => prompt_name(cp),
+        Err(_) => prompt_name(cp, initial),
         Ok(name) => name,
     }
 }
@@ -188,10 +346,31 @@ pub struct SessionParams {
     pub client_guid: String,
     pub server_password: String,
     pub monitor: ScreenResolution,
-    pub ip: Ipv4Addr,
-    pub port: u16,
+    pub server_name: String,
+    pub address: ServerAddress,
+    /// Wait for the server to connect instead of connecting to it
+    pub reverse: bool,
     pub display_mode: DisplayMode,
     pub emulate_events: bool,
+    pub clipboard_restore: ClipboardRestore,
+    pub clipboard_filter: ClipboardFilter,
+    pub reconnect: ReconnectPolicy,
+}
+
+/// Servers in the order in which they are tried, the first one is the primary.
+#[derive(Clone, Debug)]
+pub struct ServerGroup {
+    pub servers: Vec<SessionParams>,
+    /// Return to the primary server as soon as it is reachable again
+    pub failback: bool,
+    /// Commands run on the events of the session
+    pub hooks: Hooks,
+}
+
+impl ServerGroup {
+    pub fn primary(&self) -> &SessionParams {
+        &self.servers[0]
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -1,8 +1,10 @@
+use crate::connection::ServerAddress;
+use crate::hooks::HookEvent;
 use eyre::Context;
 use eyre::{eyre, Result};
 use std::fs::File;
 use std::io::prelude::*;
-use std::net::Ipv4Addr;
+use std::net::IpAddr;
 use sysinfo::{System, SystemExt};
 use tracing::info;
 use uuid::Uuid;
@@ -22,6 +24,7 @@ impl ConfigProvider {
         configuration
             .validate()
             .wrap_err("Configuration file is not valid!")?;
+        Self::check_groups(&configuration).wrap_err("Configuration file is not valid!")?;
 
         Ok(Self {
             config: configuration,
@@ -31,9 +34,46 @@ impl ConfigProvider {
                 #----------------------
                 # [[servers]]           
                 # name       : required    must be unique
-                # ip         : required    
+                # host       : required    hostname, IPv4 or IPv6 address, optionally with :port
                 # port       : required    
+                # reverse    : optional    true = listen on port, the server connects to this client
+                #                          and proves it knows the password (needs a server able to
+                #                          dial out, the Jerry server cannot yet)
                 # password   : required
+                # clipboard  : optional    keep | restore | merge
+                #
+                # [servers.clipboard_policy]   optional
+                # direction  : optional    in | out | both | none
+                # max_size   : optional    bytes
+                # formats    : optional    ['text', 'file']
+                # deny       : optional    list of regular expressions
+                #
+                # [[groups]]                   servers tried in order, the first one is the primary
+                # name       : required    must be unique, select it instead of a server
+                # servers    : required    ['primary', 'backup']
+                # failback   : optional    return to the primary as soon as it is reachable
+                #
+                # [connection.reconnect]       optional
+                # initial_delay_ms        : optional    delay before the first retry
+                # max_delay_ms            : optional    upper bound of the exponential backoff
+                # multiplier              : optional    >= 1.0
+                # jitter                  : optional    0.0 - 1.0, fraction of the delay
+                # max_attempts            : optional    unlimited if missing
+                # on_connection_error     : optional    retry | stop | prompt
+                # on_key_exchange_failed  : optional    retry | stop | prompt
+                # on_handshake_failed     : optional    retry | stop | prompt
+                # on_read_error           : optional    retry | stop | prompt
+                #
+                # [hooks]                      optional, shell commands run on the events
+                # timeout_ms              : optional    a command running longer is killed
+                # connected               : optional    the handshake succeeded
+                # handshake_failed        : optional
+                # session_begin           : optional    the client receives the input of the server
+                # session_end             : optional    also when the connection is lost during a session
+                # clipboard_received      : optional
+                # disconnected            : optional    the connection was closed after the handshake
+                # environment: JERRY_EVENT, JERRY_SERVER, JERRY_STATE, JERRY_DESCRIPTION,
+                #              JERRY_CLIPBOARD_LENGTH
                 #----------------------",
             ),
         })
@@ -42,32 +82,36 @@ impl ConfigProvider {
     pub fn get_default_port(&self) -> u16 {
         self.config.connection.port
     }
-    pub fn get_init_info_for_last_server(&self) -> Option<ClientInfo> {
-        match self.get_server(self.config.connection.previous.as_ref().unwrap()) {
-            None => None,
-            Some(server) => {
-                let mut info = self.config.client.clone();
-                if let Some(guid) = server.guid.as_ref() {
-                    info.default_guid = guid.clone();
+    pub fn get_client_name(&self) -> &str {
+        &self.config.client.name
+    }
+    /// Name of the last selected server or group
+    pub fn get_previous(&self) -> Option<&str> {
+        self.config.connection.previous.as_deref()
+    }
+    /// Server configuration with the default guid filled in
+    pub fn get_server_info(&self, name: &str) -> Option<ServerConfig> {
+        match self.get_server(name) {
+            Some(s) => {
+                let mut s_clone = s.clone();
+                if s_clone.guid.is_none() {
+                    s_clone.guid = Some(String::from(&self.config.client.default_guid));
                 }
-                Some(info)
+                Some(s_clone)
             }
+            None => None,
         }
     }
-    pub fn get_last_connection_info(&self) -> Option<ServerConfig> {
-        if let Some(ls) = self.config.connection.previous.as_ref() {
-            match self.get_server(ls) {
-                Some(s) => {
-                    let mut s_clone = s.clone();
-                    if s_clone.guid.is_none() {
-                        s_clone.guid = Some(String::from(&self.config.client.default_guid));
-                    }
-                    Some(s_clone)
-                }
-                None => None,
-            }
-        } else {
-            None
+    pub fn get_group(&self, name: &str) -> Option<&GroupConfig> {
+        self.config
+            .groups
+            .as_ref()
+            .and_then(|groups| groups.iter().find(|g| g.name == name))
+    }
+    pub fn get_groups(&self) -> Vec<String> {
+        match &self.config.groups {
+            None => Vec::new(),
+            Some(groups) => groups.iter().map(|g| g.name.clone()).collect(),
         }
     }
     pub fn get_server(&self, name: &str) -> Option<&ServerConfig> {
@@ -84,6 +128,15 @@ impl ConfigProvider {
         }
     }
 
+    /// A saved server leads to `ip`:`port`, whatever form its host is saved in.
+    pub fn contains_address(&self, ip: IpAddr, port: u16) -> bool {
+        self.config.servers.as_ref().is_some_and(|servers| {
+            servers.iter().any(|s| {
+                ServerAddress::parse(&s.host, s.port).is_ok_and(|address| address.matches(ip, port))
+            })
+        })
+    }
+
     pub fn get_servers(&self) -> Vec<String> {
         match &self.config.servers {
             None => Vec::new(),
@@ -97,6 +150,12 @@ impl ConfigProvider {
         file.write_all(toml_str.as_bytes())?;
         Ok(())
     }
+    pub fn get_reconnect_policy(&self) -> ReconnectPolicy {
+        self.config.connection.reconnect.clone()
+    }
+    pub fn get_hooks(&self) -> Hooks {
+        self.config.hooks.clone().unwrap_or_default()
+    }
     pub fn connect_without_confirmation(&self) -> bool {
         !self.config.connection.confirm
     }
@@ -105,18 +164,12 @@ impl ConfigProvider {
     }
 
     pub fn add_server(&mut self, server: &ServerConfig) -> Result<()> {
-        self.add_new_server(server.ip, server.port, &server.name, &server.password)?;
+        self.add_new_server(&server.host, server.port, &server.name, &server.password)?;
         self.update_server(&server.name, server.guid.as_deref())?;
         Ok(())
     }
 
-    fn add_new_server(
-        &mut self,
-        ip: Ipv4Addr,
-        port: u16,
-        name: &str,
-        password: &str,
-    ) -> Result<()> {
+    fn add_new_server(&mut self, host: &str, port: u16, name: &str, password: &str) -> Result<()> {
         if self.config.servers.is_none() {
             self.config.servers = Some(Vec::new());
         }
@@ -124,10 +177,13 @@ impl ConfigProvider {
         //ok unwrap
         self.config.servers.as_mut().unwrap().push(ServerConfig {
             name: String::from(name),
-            ip,
+            host: String::from(host),
             port,
+            reverse: false,
             password: String::from(password),
             guid: None,
+            clipboard: None,
+            clipboard_policy: None,
         });
 
         self.config.connection.previous = Some(String::from(name));
@@ -165,6 +221,7 @@ impl ConfigProvider {
             .unwrap() //checked
             .iter()
             .any(|s| s.name == name)
+            || self.get_group(name).is_some()
         {
             return Err(eyre!(
                 "There is already a server with name '{}' in the configuration",
@@ -174,6 +231,33 @@ impl ConfigProvider {
         Ok(())
     }
 
+    fn check_groups(config: &Config) -> Result<()> {
+        let servers = config.servers.as_deref().unwrap_or_default();
+        for group in config.groups.as_deref().unwrap_or_default() {
+            if group.servers.is_empty() {
+                return Err(eyre!("Group '{}' has no servers", group.name));
+            }
+            if servers.iter().any(|s| s.name == group.name) {
+                return Err(eyre!(
+                    "Group '{}' has the same name as a server",
+                    group.name
+                ));
+            }
+            if let Some(missing) = group
+                .servers
+                .iter()
+                .find(|name| !servers.iter().any(|s| &s.name == *name))
+            {
+                return Err(eyre!(
+                    "Group '{}' refers to an unknown server '{}'",
+                    group.name,
+                    missing
+                ));
+            }
+        }
+        Ok(())
+    }
+
     fn read_from_file(path: &str) -> Result<String, std::io::Error> {
         let mut file = File::open(path)?;
         let mut contents = String::new();
@@ -210,15 +294,18 @@ impl ConfigProvider {
                 port: crate::DEFAULT_PORT,
                 previous: None,
                 confirm: true,
+                reconnect: ReconnectPolicy::default(),
             },
             servers: Some(ss),
+            groups: None,
+            hooks: None,
         }
     }
 }
 
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Serialize, Validate, Deserialize, Debug)]
 pub struct Config {
@@ -228,6 +315,12 @@ pub struct Config {
     connection: Connection,
     #[validate]
     servers: Option<Vec<ServerConfig>>,
+    #[validate]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<GroupConfig>>,
+    #[validate]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hooks: Option<Hooks>,
 }
 
 #[derive(Serialize, Deserialize, Validate, Debug, Clone)]
@@ -244,20 +337,203 @@ pub struct Connection {
     port: u16,
     previous: Option<String>,
     confirm: bool,
+    #[serde(default)]
+    #[validate]
+    reconnect: ReconnectPolicy,
+}
+
+/// How the client reconnects after the connection to the server has failed or was lost.
+#[derive(Serialize, Deserialize, Validate, Debug, Clone)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    #[validate(range(min = 1.0))]
+    pub multiplier: f64,
+    /// The delay is randomly shortened or extended by up to this fraction
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub jitter: f64,
+    /// Failed attempts in a row before giving up, unlimited if `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    pub on_connection_error: ReconnectAction,
+    pub on_key_exchange_failed: ReconnectAction,
+    pub on_handshake_failed: ReconnectAction,
+    pub on_read_error: ReconnectAction,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 1_000,
+            max_delay_ms: 30_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: None,
+            on_connection_error: ReconnectAction::Retry,
+            on_key_exchange_failed: ReconnectAction::Stop,
+            // most likely a wrong password, retrying does not help
+            on_handshake_failed: ReconnectAction::Prompt,
+            on_read_error: ReconnectAction::Retry,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReconnectAction {
+    Retry,
+    Stop,
+    /// Ask the user whether to retry
+    Prompt,
 }
 
 #[derive(Serialize, Validate, Deserialize, Debug, Clone)]
 pub struct ServerConfig {
     #[validate(length(min = 1))]
     pub name: String,
-    pub ip: Ipv4Addr,
+    /// Hostname, IPv4 or IPv6 address, a port given here overrides `port`
+    #[serde(alias = "ip")]
+    #[validate(length(min = 1))]
+    pub host: String,
     #[validate(range(min = 1024, max = 65535))]
     pub port: u16,
+    /// The client listens on `port` and accepts a single connection from `host` that
+    /// proves it knows `password`. Depends on a server able to dial out, which the
+    /// C# server is not yet.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reverse: bool,
     #[validate(length(min = 4))]
     pub password: String,
     //#[validate(Uuid)]
     //#[validate(custom = "validate_guid")]
     pub guid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clipboard: Option<ClipboardRestore>,
+    #[validate]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clipboard_policy: Option<ClipboardPolicy>,
+}
+
+/// Servers used for failover, tried in the listed order.
+#[derive(Serialize, Validate, Deserialize, Debug, Clone)]
+pub struct GroupConfig {
+    #[validate(length(min = 1))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub servers: Vec<String>,
+    /// Return to the first server of the list as soon as it is reachable again
+    #[serde(default)]
+    pub failback: bool,
+}
+
+/// Commands run by the shell on events of the session, see `crate::hooks`.
+#[derive(Serialize, Deserialize, Validate, Debug, Clone)]
+#[serde(default)]
+pub struct Hooks {
+    /// A command still running after this time is killed
+    #[validate(range(min = 1))]
+    pub timeout_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handshake_failed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_begin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clipboard_received: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disconnected: Option<String>,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 10_000,
+            connected: None,
+            handshake_failed: None,
+            session_begin: None,
+            session_end: None,
+            clipboard_received: None,
+            disconnected: None,
+        }
+    }
+}
+
+impl Hooks {
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::Connected => self.connected.as_deref(),
+            HookEvent::HandshakeFailed => self.handshake_failed.as_deref(),
+            HookEvent::SessionBegin => self.session_begin.as_deref(),
+            HookEvent::SessionEnd => self.session_end.as_deref(),
+            HookEvent::ClipboardReceived => self.clipboard_received.as_deref(),
+            HookEvent::Disconnected => self.disconnected.as_deref(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        HookEvent::ALL.iter().all(|e| self.command(*e).is_none())
+    }
+}
+
+/// Filter applied to every clipboard transfer between the server and this client.
+#[derive(Serialize, Deserialize, Validate, Debug, Clone, Default)]
+pub struct ClipboardPolicy {
+    #[serde(default)]
+    pub direction: ClipboardDirection,
+    /// Maximum content length in bytes
+    pub max_size: Option<usize>,
+    /// Allowed formats, all formats are allowed if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formats: Vec<ClipboardFormat>,
+    /// Content matching any of these regular expressions is never transferred
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[validate(custom = "validate_regex_list")]
+    pub deny: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardDirection {
+    /// Server to client only
+    In,
+    /// Client to server only
+    Out,
+    #[default]
+    Both,
+    None,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardFormat {
+    Text,
+    File,
+}
+
+fn validate_regex_list(patterns: &[String]) -> Result<(), ValidationError> {
+    match patterns.iter().all(|p| regex::Regex::new(p).is_ok()) {
+        true => Ok(()),
+        false => Err(ValidationError::new("invalid regular expression")),
+    }
+}
+
+/// What happens to the local clipboard when a session ends
+/// (`SessionEnd` or a connection lost while the client is active).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardRestore {
+    /// Leave the content copied during the session in the clipboard
+    Keep,
+    /// Put back the content captured at `SessionBegin`
+    #[default]
+    Restore,
+    /// Put back the content captured at `SessionBegin` and keep both it and the content
+    /// of the session in the clipboard history
+    Merge,
 }
 
 // fn validate_guid(input: &str) -> Result<(), ValidationError> {
@@ -266,3 +542,53 @@ pub struct ServerConfig {
 //     }
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(groups: &str) -> Config {
+        let toml = format!(
+            r#"
+            [client]
+            name = "desk"
+            default_guid = "4a9c3c5e-8f8e-4a8e-9b8a-0c3e2f0d1a11"
+
+            [connection]
+            port = 8888
+            confirm = false
+
+            [[servers]]
+            name = "office"
+            host = "10.0.0.2"
+            port = 8888
+            password = "secret"
+            {}
+            "#,
+            groups
+        );
+        ConfigProvider::deserialize(&toml).unwrap()
+    }
+
+    fn check(groups: &str) -> Result<()> {
+        ConfigProvider::check_groups(&config(groups))
+    }
+
+    #[test]
+    fn groups_refer_to_servers() {
+        check("[[groups]]\nname = \"all\"\nservers = [\"office\"]").unwrap();
+
+        let e = check("[[groups]]\nname = \"all\"\nservers = []").unwrap_err();
+        assert_eq!(e.to_string(), "Group 'all' has no servers");
+        let e = check("[[groups]]\nname = \"all\"\nservers = [\"office\", \"home\"]").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Group 'all' refers to an unknown server 'home'"
+        );
+        let e = check("[[groups]]\nname = \"office\"\nservers = [\"office\"]").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Group 'office' has the same name as a server"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+
+/// Server address as entered by the user: hostname, IPv4 or IPv6 literal,
+/// optionally followed by a port (`host:port`, `[v6]:port`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerAddress {
+    pub host: String,
+    pub port: u16,
+}
+
+impl ServerAddress {
+    /// `default_port` is used when the input does not contain a port.
+    pub fn parse(input: &str, default_port: u16) -> Result<Self, String> {
+        let (host, port) = Self::split(input)?;
+        Ok(Self::new(host, port.unwrap_or(default_port)))
+    }
+
+    /// Validated host and the port, if the input contains one.
+    pub fn split(input: &str) -> Result<(String, Option<u16>), String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(String::from("Address must not be empty"));
+        }
+        if input.chars().any(char::is_whitespace) {
+            return Err(String::from("Address must not contain whitespace"));
+        }
+        // bare IPv6 literal, e.g. fe80::1
+        if let Ok(ip) = input.parse::<Ipv6Addr>() {
+            return Ok((ip.to_string(), None));
+        }
+        // [v6] or [v6]:port
+        if let Some(rest) = input.strip_prefix('[') {
+            let (ip, port) = match rest.split_once(']') {
+                Some((ip, "")) => (ip, None),
+                Some((ip, port)) => (ip, Some(Self::parse_port(port.strip_prefix(':'))?)),
+                None => return Err(String::from("Missing ']' in IPv6 address")),
+            };
+            let ip = ip
+                .parse::<Ipv6Addr>()
+                .map_err(|e| format!("Invalid IPv6 address: {}", e))?;
+            return Ok((ip.to_string(), port));
+        }
+        let (host, port) = match input.split_once(':') {
+            Some((host, port)) => (host, Some(Self::parse_port(Some(port))?)),
+            None => (input, None),
+        };
+        let valid_host = host.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+        match valid_host {
+            true => Ok((host.to_owned(), port)),
+            false => Err(format!("Invalid hostname '{}'", host)),
+        }
+    }
+
+    fn new(host: String, port: u16) -> Self {
+        Self { host, port }
+    }
+
+    fn parse_port(port: Option<&str>) -> Result<u16, String> {
+        port.and_then(|p| p.parse::<u16>().ok())
+            .filter(|p| *p != 0)
+            .ok_or_else(|| String::from("Invalid port"))
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        match self.host.parse::<IpAddr>() {
+            Ok(ip) => ip.is_loopback(),
+            Err(_) => self.host.eq_ignore_ascii_case("localhost"),
+        }
+    }
+
+    /// The address leads to `ip`:`port`, a hostname is resolved for the comparison.
+    pub fn matches(&self, ip: IpAddr, port: u16) -> bool {
+        if self.port != port {
+            return false;
+        }
+        match self.host.parse::<IpAddr>() {
+            Ok(host) => host == ip,
+            Err(_) => (self.host.as_str(), port)
+                .to_socket_addrs()
+                .is_ok_and(|mut addresses| addresses.any(|a| a.ip() == ip)),
+        }
+    }
+
+    /// Resolves the host, IPv6 and IPv4 addresses are interleaved (IPv6 first)
+    /// in the order in which connections should be attempted (RFC 8305).
+    pub async fn resolve(&self) -> std::io::Result<Vec<SocketAddr>> {
+        let (v6, v4): (Vec<SocketAddr>, Vec<SocketAddr>) =
+            tokio::net::lookup_host((self.host.as_str(), self.port))
+                .await?
+                .partition(|a| a.is_ipv6());
+        let mut v6 = v6.into_iter();
+        let mut v4 = v4.into_iter();
+        let mut ordered = Vec::new();
+        loop {
+            match (v6.next(), v4.next()) {
+                (None, None) => break,
+                (a, b) => ordered.extend(a.into_iter().chain(b)),
+            }
+        }
+        Ok(ordered)
+    }
+}
+
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.host.parse::<Ipv6Addr>() {
+            Ok(_) => write!(f, "[{}]:{}", self.host, self.port),
+            Err(_) => write!(f, "{}:{}", self.host, self.port),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<ServerAddress, String> {
+        ServerAddress::parse(input, 8888)
+    }
+
+    #[test]
+    fn hosts_and_ports() {
+        assert_eq!(
+            parse(" jerry.local "),
+            Ok(ServerAddress::new(String::from("jerry.local"), 8888))
+        );
+        assert_eq!(
+            parse("192.168.1.66:9000"),
+            Ok(ServerAddress::new(String::from("192.168.1.66"), 9000))
+        );
+        assert_eq!(
+            parse("jerry-pc:9000"),
+            Ok(ServerAddress::new(String::from("jerry-pc"), 9000))
+        );
+    }
+
+    #[test]
+    fn matching_addresses() {
+        let office = IpAddr::from([10, 0, 0, 2]);
+        assert!(parse("10.0.0.2").unwrap().matches(office, 8888));
+        assert!(parse("10.0.0.2:9000").unwrap().matches(office, 9000));
+        assert!(!parse("10.0.0.2:9000").unwrap().matches(office, 8888));
+        assert!(!parse("10.0.0.3").unwrap().matches(office, 8888));
+        let localhost = IpAddr::from([127, 0, 0, 1]);
+        assert!(parse("localhost").unwrap().matches(localhost, 8888));
+    }
+
+    #[test]
+    fn ipv6() {
+        assert_eq!(
+            parse("fe80::1"),
+            Ok(ServerAddress::new(String::from("fe80::1"), 8888))
+        );
+        assert_eq!(
+            parse("[fd00::66]"),
+            Ok(ServerAddress::new(String::from("fd00::66"), 8888))
+        );
+        let address = parse("[fd00:0::66]:9000").unwrap();
+        assert_eq!(address, ServerAddress::new(String::from("fd00::66"), 9000));
+        assert_eq!(address.to_string(), "[fd00::66]:9000");
+        assert!(parse("[fd00::66:9000").is_err());
+        assert!(parse("[jerry.local]:9000").is_err());
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(parse("  "), Err(String::from("Address must not be empty")));
+        assert_eq!(
+            parse("jerry local"),
+            Err(String::from("Address must not contain whitespace"))
+        );
+        assert_eq!(parse("jerry.local:0"), Err(String::from("Invalid port")));
+        assert_eq!(
+            parse("jerry.local:70000"),
+            Err(String::from("Invalid port"))
+        );
+        assert_eq!(parse("jerry.local:"), Err(String::from("Invalid port")));
+        assert_eq!(parse(":9000"), Err(String::from("Invalid hostname ''")));
+        assert_eq!(
+            parse("jerry..local"),
+            Err(String::from("Invalid hostname 'jerry..local'"))
+        );
+    }
+
+    #[test]
+    fn only_given_ports_are_returned() {
+        assert_eq!(
+            ServerAddress::split("jerry.local"),
+            Ok((String::from("jerry.local"), None))
+        );
+        assert_eq!(
+            ServerAddress::split("[::1]:9000"),
+            Ok((String::from("::1"), Some(9000)))
+        );
+    }
+}
@@ -1,90 +1,133 @@
+use super::ServerAddress;
 use std::io::{Error, ErrorKind};
-use std::net::{IpAddr, Ipv4Addr};
-use std::net::{SocketAddr, TcpStream};
-use std::thread::sleep;
-use std::time::{Duration, Instant};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
 use tap::tap::*;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, warn};
+
+/// Delay between two connection attempts to different addresses of the same host (RFC 8305)
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Single connection attempt (outbound or reverse), retries are driven by the `ConnectionWorker` reconnection policy.
 pub struct Connection {
-    ip: Ipv4Addr,
-    port: u16,
+    address: ServerAddress,
     timeout: Duration,
-    reconnection_interval: Duration,
-    automatic_reconnection: bool,
-    on_error: Option<fn(ErrorKind) -> Option<ErrorKind>>,
 }
 
 impl Connection {
-    pub fn new(ip: Ipv4Addr, port: u16) -> Self {
+    pub fn new(address: ServerAddress) -> Self {
         let timeout = Duration::MAX;
-        let repeated_rest = Duration::from_secs(5);
-        Connection {
-            ip,
-            port,
-            timeout,
-            reconnection_interval: repeated_rest,
-            automatic_reconnection: false,
-            on_error: None,
-        }
+        Connection { address, timeout }
     }
     pub fn set_timeout(&mut self, timeout_sec: u64) -> &mut Self {
         self.timeout = Duration::from_secs(timeout_sec);
-        self.reconnection_interval = std::cmp::min(self.timeout, self.reconnection_interval);
         self
     }
-    pub fn enable_automatic_reconnection(&mut self, interval: u64) -> &mut Self {
-        self.automatic_reconnection = true;
-        self.reconnection_interval = Duration::from_secs(interval);
-        self.timeout = std::cmp::max(self.timeout, self.reconnection_interval);
-        self
+
+    pub async fn connect(&self) -> Result<TcpStream, Error> {
+        self._resolve_and_connect()
+            .await
+            .tap_err(|e| warn!("{}", e))
     }
-    pub fn events_on_error(&mut self, on_err: fn(ErrorKind) -> Option<ErrorKind>) -> &mut Self {
-        self.on_error = Some(on_err);
-        self
+    /// Listens on the port of the address until the host connects (reverse mode).
+    ///
+    /// The listening socket is closed once the connection is accepted, so only a single
+    /// server is connected at a time. Connections from other hosts are dropped, unless
+    /// the host is an unspecified address (`0.0.0.0`, `::`); the peer then still has to
+    /// pass `peer_auth::verify_peer`. A hostname is listened for on IPv6 as soon as it
+    /// resolves to an IPv6 address, IPv4 peers reach a dual-stack socket as well.
+    /// Fails with `ErrorKind::Interrupted` as soon as `cancel` is cancelled.
+    pub async fn accept(&self, cancel: &CancellationToken) -> Result<TcpStream, Error> {
+        let ipv6 = match self.address.host.parse::<IpAddr>() {
+            Ok(ip) => ip.is_ipv6(),
+            Err(_) => self.address.resolve().await?.iter().any(|a| a.is_ipv6()),
+        };
+        let unspecified = match ipv6 {
+            true => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            false => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        };
+        let listener = TcpListener::bind((unspecified, self.address.port)).await?;
+        debug!("Listening on {:?}", listener.local_addr());
+        loop {
+            let (stream, peer) = tokio::select! {
+                _ = cancel.cancelled() => return Err(Error::from(ErrorKind::Interrupted)),
+                accepted = listener.accept() => accepted?,
+            };
+            if self.is_expected_peer(peer.ip()).await {
+                debug!("Accepted connection from {}", peer);
+                return Ok(stream);
+            }
+            warn!(
+                "Connection from {} rejected, waiting for {}",
+                peer, self.address.host
+            );
+        }
     }
-
-    pub fn connect(&mut self) -> Result<TcpStream, Error> {
-        match self.automatic_reconnection {
-            true => self._reconnection_loop(),
-            false => self._connect(),
+    async fn is_expected_peer(&self, peer: IpAddr) -> bool {
+        let peer = peer.to_canonical();
+        match self.address.host.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => true,
+            Ok(ip) => ip.to_canonical() == peer,
+            Err(_) => self
+                .address
+                .resolve()
+                .await
+                .is_ok_and(|addresses| addresses.iter().any(|a| a.ip().to_canonical() == peer)),
         }
     }
-    fn _connect(&mut self) -> Result<TcpStream, Error> {
-        let soc = SocketAddr::new(IpAddr::V4(self.ip), self.port);
-        TcpStream::connect_timeout(&soc, self.timeout).tap_err(|e| warn!("{}", e))
+    /// Connects and closes the connection right away, failures are not logged.
+    pub async fn is_reachable(&self) -> bool {
+        self._resolve_and_connect().await.is_ok()
     }
-    fn _reconnection_loop(&mut self) -> Result<TcpStream, Error> {
-        let soc = SocketAddr::new(IpAddr::V4(self.ip), self.port);
-        let start = Instant::now();
-        let mut last: Error;
+    /// The host is resolved again on every attempt, addresses may change (DHCP).
+    async fn _resolve_and_connect(&self) -> Result<TcpStream, Error> {
+        let addresses = self.address.resolve().await?;
+        debug!("Server: {} -> {:?}", self.address, addresses);
+        connect_happy_eyeballs(addresses, self.timeout).await
+    }
+}
 
-        loop {
-            debug!("Server: {}", soc);
-            match TcpStream::connect_timeout(&soc, self.timeout) {
-                //TcpStream::connect(soc )
-                Ok(stream) => {
-                    debug!("Connected to {}", soc);
-                    return Ok(stream);
-                }
-                Err(e) => {
-                    if let Some(fn_err) = self.on_error {
-                        let new_error = fn_err(e.kind());
-                        debug!("Connection error {:?}", e);
-                        if let Some(er_kind) = new_error {
-                            return Err(Error::from(er_kind));
-                        }
-                    } else {
-                        debug!("Connection error {:?}", e);
-                    }
-                    last = e;
+/// Starts a connection attempt to each address, `CONNECTION_ATTEMPT_DELAY` apart,
+/// without waiting for the previous attempts to fail. The first established connection wins,
+/// the remaining attempts are aborted.
+async fn connect_happy_eyeballs(
+    addresses: Vec<SocketAddr>,
+    timeout: Duration,
+) -> Result<TcpStream, Error> {
+    if addresses.is_empty() {
+        return Err(Error::new(
+            ErrorKind::AddrNotAvailable,
+            "Host name resolved to no address",
+        ));
+    }
+    let mut attempts = JoinSet::new();
+    for (i, address) in addresses.into_iter().enumerate() {
+        attempts.spawn(async move {
+            tokio::time::sleep(CONNECTION_ATTEMPT_DELAY * i as u32).await;
+            let result = match tokio::time::timeout(timeout, TcpStream::connect(address)).await {
+                Ok(result) => result,
+                Err(_) => Err(Error::from(ErrorKind::TimedOut)),
+            };
+            (address, result)
+        });
+    }
 
-                    sleep(self.reconnection_interval);
-                }
+    let mut last = Error::from(ErrorKind::NotConnected);
+    while let Some(attempt) = attempts.join_next().await {
+        match attempt {
+            Ok((address, Ok(stream))) => {
+                debug!("Connected to {}", address);
+                return Ok(stream);
             }
-            if start.elapsed() > self.timeout {
-                break;
+            Ok((address, Err(e))) => {
+                debug!("Connection to {} failed: {}", address, e);
+                last = e;
             }
+            Err(e) => last = Error::other(e),
         }
-        Err(last)
     }
+    Err(last)
 }
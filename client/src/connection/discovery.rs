@@ -0,0 +1,179 @@
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+//  Client                                   Server
+//    │  "JERRY_DISCOVER/1"  (UDP broadcast)   │
+//    │ ─────────────────────────────────────► │
+//    │  "JERRY_ANNOUNCE/1\n                   │
+//    │   name=<name>\n                        │
+//    │   port=<tcp port>\n                    │
+//    │   fingerprint=<fingerprint>"           │
+//    │ ◄───────────────────────────────────── │
+//
+// The server address is the source address of the announcement.
+
+pub const DISCOVERY_PORT: u16 = 8889;
+const QUERY: &[u8] = b"JERRY_DISCOVER/1";
+const ANNOUNCEMENT: &str = "JERRY_ANNOUNCE/1";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveredServer {
+    pub name: String,
+    pub ip: Ipv4Addr,
+    pub port: u16,
+    pub fingerprint: String,
+}
+
+impl fmt::Display for DiscoveredServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}:{}) [{}]",
+            self.name, self.ip, self.port, self.fingerprint
+        )
+    }
+}
+
+/// Broadcasts a discovery query to the local network and collects the announcements
+/// received within the timeout.
+pub fn discover_lan(timeout: Duration) -> std::io::Result<Vec<DiscoveredServer>> {
+    discover(
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), DISCOVERY_PORT),
+        timeout,
+    )
+}
+
+pub fn discover(target: SocketAddr, timeout: Duration) -> std::io::Result<Vec<DiscoveredServer>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    socket.send_to(QUERY, target)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut servers: Vec<DiscoveredServer> = Vec::new();
+    let mut buffer = [0u8; 512];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        match socket.recv_from(&mut buffer) {
+            Ok((len, from)) => match parse_announcement(&buffer[..len], from) {
+                Some(server)
+                    if !servers
+                        .iter()
+                        .any(|s| s.ip == server.ip && s.port == server.port) =>
+                {
+                    debug!("Discovered server {}", server);
+                    servers.push(server);
+                }
+                Some(_) => {}
+                None => debug!("Invalid announcement from {}", from),
+            },
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(servers)
+}
+
+fn parse_announcement(data: &[u8], from: SocketAddr) -> Option<DiscoveredServer> {
+    let IpAddr::V4(ip) = from.ip() else {
+        return None;
+    };
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.lines();
+    if lines.next()? != ANNOUNCEMENT {
+        return None;
+    }
+    let (mut name, mut port, mut fingerprint) = (None, None, String::new());
+    for line in lines {
+        match line.split_once('=') {
+            Some(("name", value)) if !value.is_empty() => name = Some(value.to_owned()),
+            Some(("port", value)) => port = value.parse::<u16>().ok(),
+            Some(("fingerprint", value)) => fingerprint = value.to_owned(),
+            _ => {}
+        }
+    }
+    Some(DiscoveredServer {
+        name: name?,
+        ip,
+        port: port?,
+        fingerprint,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::JoinHandle;
+
+    /// Stand-in for a Jerry server answering discovery queries.
+    struct Announcer {
+        socket: UdpSocket,
+        reply: String,
+    }
+
+    impl Announcer {
+        fn bind(name: &str, port: u16, fingerprint: &str) -> Self {
+            let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            socket
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .unwrap();
+            let reply = format!(
+                "{}\nname={}\nport={}\nfingerprint={}",
+                ANNOUNCEMENT, name, port, fingerprint
+            );
+            Self { socket, reply }
+        }
+
+        fn address(&self) -> SocketAddr {
+            self.socket.local_addr().unwrap()
+        }
+
+        fn answer_once(self) -> JoinHandle<()> {
+            std::thread::spawn(move || {
+                let mut buffer = [0u8; 64];
+                if let Ok((len, from)) = self.socket.recv_from(&mut buffer) {
+                    if &buffer[..len] == QUERY {
+                        // answered twice, duplicates are reported once
+                        self.socket.send_to(self.reply.as_bytes(), from).unwrap();
+                        self.socket.send_to(self.reply.as_bytes(), from).unwrap();
+                    }
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn discovers_announcing_server() {
+        let announcer = Announcer::bind("office", 8888, "ab:cd:ef");
+        let address = announcer.address();
+        let handle = announcer.answer_once();
+
+        let servers = discover(address, Duration::from_millis(500)).unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(
+            servers,
+            vec![DiscoveredServer {
+                name: String::from("office"),
+                ip: Ipv4Addr::LOCALHOST,
+                port: 8888,
+                fingerprint: String::from("ab:cd:ef"),
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_announcements() {
+        let from = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), DISCOVERY_PORT);
+        assert!(parse_announcement(b"JERRY_ANNOUNCE/1\nport=8888", from).is_none());
+        assert!(parse_announcement(b"JERRY_ANNOUNCE/1\nname=a\nport=99999", from).is_none());
+        assert!(parse_announcement(b"HELLO\nname=a\nport=8888", from).is_none());
+        assert!(parse_announcement(&[0xff, 0xfe], from).is_none());
+    }
+}
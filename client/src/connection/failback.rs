@@ -0,0 +1,51 @@
+use super::conn::Connection;
+use super::ServerAddress;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT_SEC: u64 = 2;
+
+/// Watches the primary server while the client is connected to a backup.
+///
+/// As soon as the primary accepts a TCP connection, the session with the backup
+/// is cancelled so that the connection worker can switch back.
+pub struct FailbackProbe {
+    stop: CancellationToken,
+    task: JoinHandle<bool>,
+}
+
+impl FailbackProbe {
+    pub fn start(primary: ServerAddress, session: CancellationToken) -> Self {
+        let stop = CancellationToken::new();
+        let stopped = stop.clone();
+        let task = tokio::spawn(async move {
+            let mut connection = Connection::new(primary);
+            connection.set_timeout(PROBE_TIMEOUT_SEC);
+            loop {
+                tokio::select! {
+                    _ = stopped.cancelled() => return false,
+                    _ = tokio::time::sleep(PROBE_INTERVAL) => {}
+                }
+                let reachable = tokio::select! {
+                    _ = stopped.cancelled() => return false,
+                    reachable = connection.is_reachable() => reachable,
+                };
+                if reachable {
+                    debug!("Primary server is reachable, closing the backup session");
+                    session.cancel();
+                    return true;
+                }
+            }
+        });
+        Self { stop, task }
+    }
+
+    /// Stops probing, returns true if the session was closed because the primary is back.
+    pub async fn stop(self) -> bool {
+        self.stop.cancel();
+        self.task.await.unwrap_or(false)
+    }
+}
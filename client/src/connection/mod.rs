@@ -1,23 +1,86 @@
-use crate::configuration::SessionParams;
-use crate::core::message_handler::ContextAwareMessageHandler;
-use crate::core::Command;
-use std::error::Error;
-use std::io::{ErrorKind, Read, Write};
-use std::sync::mpsc::Sender;
+use crate::configuration::{ServerGroup, SessionParams};
+use crate::core::{Command, ConsumerFactory, GoodbyeCause, JerryResponse};
+use crate::error::{ConnectionError, CryptoError, ErrorSource, Rejection, TransportError};
+use crate::proto_rs::ProtoOutMsg;
+use crate::serialization::capture::Capture;
+use std::io::ErrorKind;
+use std::sync::mpsc::{SyncSender, TrySendError};
 use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
+pub mod address;
 mod conn;
+pub mod discovery;
+mod failback;
+mod pipeline;
+pub mod queue;
+pub mod reconnect;
+mod shutdown;
+pub mod stats;
+pub use address::ServerAddress;
+use failback::FailbackProbe;
+use pipeline::Pipeline;
+use reconnect::{Backoff, Decision, Failure, UserDecision};
+pub use shutdown::ShutdownSignal;
+use stats::LinkStats;
 #[derive(PartialEq, Debug)]
 pub enum ConnectionState {
     None,
     Establishing,
-    ConnectionError(String),
+    Listening(u16),
+    ConnectionError(TransportError),
     Connected,
     ConnectedSecured,
-    KeyExchangeFailed(String),
-    HandshakeFailed(String),
+    KeyExchangeFailed(CryptoError),
+    HandshakeFailed(Rejection),
     HandshakeSuccess(String),
-    ReadError(String),
+    /// The session ended after the connection was secured
+    ReadError(ConnectionError),
+    Reconnecting {
+        attempt: u32,
+        max_attempts: Option<u32>,
+        remaining: Duration,
+    },
+    /// The reconnection policy asks the user whether to retry after the error
+    AwaitingDecision(ConnectionError),
+    Stopped(ConnectionError),
+    /// Disconnected by a `ControlRequest`, waiting for the request to connect again
+    Disconnected,
+}
+
+/// Requests of a controller, e.g. the control socket, to the connection worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlRequest {
+    /// Ends the connection and waits for `Reconnect` or `Switch`
+    Disconnect,
+    /// Connects again right away, ending the current connection
+    Reconnect,
+    /// Connects to the server of the group with the name
+    Switch(String),
+}
+
+impl ConnectionState {
+    /// Short name of the state for scripts, e.g. `handshake_success`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConnectionState::None => "none",
+            ConnectionState::Establishing => "establishing",
+            ConnectionState::Listening(_) => "listening",
+            ConnectionState::ConnectionError(_) => "connection_error",
+            ConnectionState::Connected => "connected",
+            ConnectionState::ConnectedSecured => "connected_secured",
+            ConnectionState::KeyExchangeFailed(_) => "key_exchange_failed",
+            ConnectionState::HandshakeFailed(_) => "handshake_failed",
+            ConnectionState::HandshakeSuccess(_) => "handshake_success",
+            ConnectionState::ReadError(_) => "read_error",
+            ConnectionState::Reconnecting { .. } => "reconnecting",
+            ConnectionState::AwaitingDecision(_) => "awaiting_decision",
+            ConnectionState::Stopped(_) => "stopped",
+            ConnectionState::Disconnected => "disconnected",
+        }
+    }
 }
 
 use std::fmt;
@@ -26,130 +89,442 @@ impl fmt::Display for ConnectionState {
         match self {
             ConnectionState::None => write!(f, "None"),
             ConnectionState::Establishing => write!(f, "Establishing"),
+            ConnectionState::Listening(port) => {
+                write!(f, "Waiting for the server to connect on port {}", port)
+            }
             ConnectionState::Connected => write!(f, "Connected"),
             ConnectionState::ConnectedSecured => write!(f, "Encrypted communication established"),
-            ConnectionState::ConnectionError(_) => write!(f, "Connection error"),
-            ConnectionState::ReadError(_s) => write!(f, "Read error"),
-            ConnectionState::HandshakeFailed(s) => write!(f, "Handshake failed ({})", s),
+            ConnectionState::ConnectionError(e) => write!(f, "Connection error ({})", e),
+            ConnectionState::ReadError(e) => write!(f, "Connection lost ({})", e),
+            ConnectionState::HandshakeFailed(rejection) => {
+                write!(f, "Handshake failed ({})", rejection)
+            }
             ConnectionState::HandshakeSuccess(s) => write!(f, "Handshake succeeded ({})", s),
-            ConnectionState::KeyExchangeFailed(_) => write!(f, "Key exchange failed",),
+            ConnectionState::KeyExchangeFailed(e) => write!(f, "{}", e),
+            ConnectionState::Reconnecting {
+                attempt,
+                max_attempts,
+                remaining,
+            } => {
+                let seconds = remaining.as_secs_f32().ceil();
+                match max_attempts {
+                    Some(max) => write!(f, "Reconnecting in {}s ({}/{})", seconds, attempt, max),
+                    None => write!(f, "Reconnecting in {}s (attempt {})", seconds, attempt),
+                }
+            }
+            ConnectionState::AwaitingDecision(s) => write!(f, "{} - retry? [y/n]", s),
+            ConnectionState::Stopped(s) => write!(f, "Reconnection stopped ({})", s),
+            ConnectionState::Disconnected => write!(f, "Disconnected on request"),
         }
     }
 }
 
-pub struct ConnectionWorker {
-    transmitter: Sender<Command>,
-    info: SessionParams,
-    cycle_count: u32,
+/// Connects to the servers of the group in turn and runs the sessions,
+/// following the reconnection policy of the primary server.
+pub(crate) struct ConnectionWorker {
+    transmitter: SyncSender<Command>,
+    group: ServerGroup,
+    /// Index of the active server in the group
+    current: usize,
+    decisions: Receiver<UserDecision>,
+    controls: Option<Receiver<ControlRequest>>,
+    backoff: Backoff,
+    shutdown: ShutdownSignal,
+    /// Cancelled once the shutdown is requested
+    cancel: CancellationToken,
+    consumer: ConsumerFactory,
+    capture: Option<Capture>,
+}
+
+enum SessionEnd {
+    Failed(ConnectionError),
+    /// The primary server is reachable again
+    Failback,
+    /// Ended by a `ControlRequest`
+    Interrupted,
 }
 
-const HEARTBEAT_TIMEOUT: Duration = Duration::from_millis(2_500);
+const COUNTDOWN_TICK: Duration = Duration::from_secs(1);
+/// A server that stops answering during the key exchange would block the connection
+const KEY_EXCHANGE_TIMEOUT: Duration = Duration::from_secs(5);
 
 impl ConnectionWorker {
-    pub fn new(transmitter: Sender<Command>, info: SessionParams) -> Self {
+    pub fn new(
+        transmitter: SyncSender<Command>,
+        group: ServerGroup,
+        decisions: Receiver<UserDecision>,
+        controls: Option<Receiver<ControlRequest>>,
+        shutdown: ShutdownSignal,
+        consumer: ConsumerFactory,
+        capture: Option<Capture>,
+    ) -> Self {
+        let backoff = Backoff::new(group.primary().reconnect.clone());
+        let cancel = shutdown.token();
         ConnectionWorker {
             transmitter,
-            info,
-            cycle_count: 0,
+            group,
+            current: 0,
+            decisions,
+            controls,
+            backoff,
+            shutdown,
+            cancel,
+            consumer,
+            capture,
         }
     }
-    pub fn run(&mut self) {
-        let result = self.loop_connection_read();
-        if let Err(err) = result {
-            eprintln!("Connection worker error: {}", err);
-        }
-        let _ = self.transmitter.send(Command::Halt);
+    pub async fn run(mut self) {
+        // without a controller nothing is ever received
+        let mut controls = self
+            .controls
+            .take()
+            .unwrap_or_else(|| tokio::sync::mpsc::channel(1).1);
+        self.loop_connection_read(&mut controls).await;
+        let _ = self.send(Command::Halt);
     }
 
-    fn loop_connection_read(&mut self) -> Result<(), Box<dyn Error>> {
-        'listen: loop {
-            if !self.try_send_state(ConnectionState::Establishing) {
-                break 'listen;
-            }
-            if self.cycle_count != 0 {
-                std::thread::sleep(Duration::from_secs(1));
+    async fn loop_connection_read(&mut self, controls: &mut Receiver<ControlRequest>) {
+        self.select_server(0);
+        loop {
+            // a control request cancels the attempt, which still ends gracefully
+            let attempt = self.cancel.child_token();
+            let mut request = None;
+            let end = {
+                let session = self.connect_and_listen(attempt.clone());
+                tokio::pin!(session);
+                loop {
+                    tokio::select! {
+                        end = &mut session => break end,
+                        Some(received) = controls.recv(), if request.is_none() => {
+                            request = Some(received);
+                            attempt.cancel();
+                        }
+                    }
+                }
+            };
+            let error = match (end, request) {
+                (None, _) => break,
+                (Some(_), Some(request)) => match self.follow(request, controls).await {
+                    true => continue,
+                    false => break,
+                },
+                (Some(SessionEnd::Failback), None) => {
+                    self.select_server(0);
+                    continue;
+                }
+                (Some(SessionEnd::Interrupted), None) => continue,
+                (Some(SessionEnd::Failed(error)), None) => error,
+            };
+            let failure = Failure::from(&error);
+            let decision = self.backoff.on_failure(failure);
+            debug!("{:?} ({}): {:?}", failure, error, decision);
+            let proceed = match decision {
+                // the next server of the group is tried right away
+                Decision::Retry(_) if self.current + 1 < self.group.servers.len() => {
+                    self.select_server(self.current + 1);
+                    true
+                }
+                Decision::Retry(delay) => {
+                    self.select_server(0);
+                    self.count_down(delay, controls).await
+                }
+                Decision::Prompt => self.prompt(error, controls).await,
+                Decision::Stop => {
+                    let _ = self.try_send_state(ConnectionState::Stopped(error));
+                    false
+                }
+            };
+            if !proceed {
+                break;
             }
-            self.cycle_count += 1;
+        }
+    }
 
-            let connection_res = conn::Connection::new(self.info.ip, self.info.port)
-                .enable_automatic_reconnection(1)
-                .set_timeout(5)
-                .events_on_error(|err_kind| {
-                    if err_kind == ErrorKind::ConnectionAborted {
-                        return Some(err_kind);
+    /// Prepares the next connection as requested, waits for the request to connect
+    /// again after a disconnection. Returns `false` if the shutdown was requested meanwhile.
+    async fn follow(
+        &mut self,
+        mut request: ControlRequest,
+        controls: &mut Receiver<ControlRequest>,
+    ) -> bool {
+        loop {
+            match request {
+                ControlRequest::Reconnect => {
+                    self.backoff.reset();
+                    return true;
+                }
+                ControlRequest::Switch(name) => {
+                    match self
+                        .group
+                        .servers
+                        .iter()
+                        .position(|s| s.server_name == name)
+                    {
+                        Some(index) => {
+                            self.backoff.reset();
+                            self.select_server(index);
+                            return true;
+                        }
+                        None => warn!("Server '{}' is not in the group", name),
                     }
-                    None
-                })
-                .connect();
-
-            if let Err(e) = connection_res {
-                match self.try_send_state(ConnectionState::ConnectionError(e.to_string())) {
-                    true => continue 'listen,
-                    false => break 'listen,
                 }
+                ControlRequest::Disconnect => {}
             }
-            let mut stream = connection_res.unwrap();
+            if !self.try_send_state(ConnectionState::Disconnected) {
+                return false;
+            }
+            request = tokio::select! {
+                _ = self.cancel.cancelled() => return false,
+                received = controls.recv() => match received {
+                    Some(received) => received,
+                    // nobody can ask to connect again
+                    None => {
+                        self.cancel.cancelled().await;
+                        return false;
+                    }
+                },
+            };
+        }
+    }
+
+    fn server(&self) -> &SessionParams {
+        &self.group.servers[self.current]
+    }
+
+    fn select_server(&mut self, index: usize) {
+        self.current = index;
+        let server = format!("{} ({})", self.server().server_name, self.server().address);
+        _ = self.send(Command::ActiveServer(server));
+    }
+
+    /// Returns the reason the session ended, `None` if the receiver is gone
+    /// or the shutdown was requested. `cancel` is a child of the shutdown token.
+    async fn connect_and_listen(&mut self, cancel: CancellationToken) -> Option<SessionEnd> {
+        let state = match self.server().reverse {
+            true => ConnectionState::Listening(self.server().address.port),
+            false => ConnectionState::Establishing,
+        };
+        if !self.try_send_state(state) {
+            return None;
+        }
+        let mut connection = conn::Connection::new(self.server().address.clone());
+        let connection_res = match self.server().reverse {
+            true => connection.accept(&cancel).await,
+            false => tokio::select! {
+                _ = cancel.cancelled() => Err(ErrorKind::Interrupted.into()),
+                connected = connection.set_timeout(5).connect() => connected,
+            },
+        };
 
-            if stream.set_nodelay(true).is_err() {
-                warn!("Nagle's algorithm is enabled");
+        let mut stream = match connection_res {
+            Ok(stream) => stream,
+            Err(e) if e.kind() == ErrorKind::Interrupted => return self.interrupted(),
+            Err(e) => {
+                let error = TransportError::Connect(e.into());
+                return self
+                    .try_send_state(ConnectionState::ConnectionError(error.clone()))
+                    .then(|| SessionEnd::Failed(error.into()));
             }
-            if let Err(e) = stream.set_read_timeout(Some(HEARTBEAT_TIMEOUT)) {
-                self.try_send_state(ConnectionState::ConnectionError(e.to_string()));
-                break 'listen;
+        };
+
+        if stream.set_nodelay(true).is_err() {
+            warn!("Nagle's algorithm is enabled");
+        }
+        if !self.try_send_state(ConnectionState::Connected) {
+            return None;
+        }
+
+        // a server dialing in has to prove that it knows the password before the
+        // client info, which carries it, is sent
+        let reverse = self
+            .server()
+            .reverse
+            .then(|| self.server().server_password.clone());
+        let secured = async {
+            let keys = crate::security::key_exchange::get_secrets_chacha(&mut stream).await?;
+            if let Some(password) = reverse {
+                crate::security::peer_auth::verify_peer(&mut stream, &password, &keys).await?;
             }
-            if !self.try_send_state(ConnectionState::Connected) {
-                break 'listen;
+            Ok(keys)
+        };
+        let exchanged = tokio::select! {
+            _ = cancel.cancelled() => return self.interrupted(),
+            keys = tokio::time::timeout(KEY_EXCHANGE_TIMEOUT, secured) => keys.unwrap_or_else(|_| {
+                let timeout = std::io::Error::from(ErrorKind::TimedOut);
+                Err(CryptoError::KeyExchange(ErrorSource::new(timeout)))
+            }),
+        };
+
+        let keys = match exchanged {
+            Ok(keys) => keys,
+            Err(error) => {
+                return self
+                    .try_send_state(ConnectionState::KeyExchangeFailed(error.clone()))
+                    .then(|| SessionEnd::Failed(error.into()));
             }
+        };
+
+        let session_token = cancel.child_token();
+        let primary = self.group.primary();
+        let probe = (self.group.failback && self.current != 0 && !primary.reverse)
+            .then(|| FailbackProbe::start(primary.address.clone(), session_token.clone()));
 
-            let keys_option = crate::security::key_exchange::get_secrets_chacha(&mut stream);
+        let stats = LinkStats::new(self.transmitter.clone());
+        let pipeline = Pipeline::new(stream, Some(keys).filter(|_| crate::ENCRYPT), stats)
+            .with_capture(self.capture.clone());
 
-            if keys_option.is_none() {
-                // Exit
-                let _ = self.try_send_state(ConnectionState::KeyExchangeFailed("".into()));
-                break 'listen;
+        if !self.try_send_state(ConnectionState::ConnectedSecured) {
+            return None;
+        }
+
+        let outcome = pipeline
+            .run(
+                self.consumer.clone(),
+                self.transmitter.clone(),
+                self.server().clone(),
+                session_token,
+            )
+            .await;
+        let failback = match probe {
+            Some(probe) => probe.stop().await,
+            None => false,
+        };
+        let interrupted = self.shutdown.requested().is_some() || cancel.is_cancelled();
+        let goodbye = match self.shutdown.requested() {
+            Some(cause) => Some((cause, Self::goodbye_description(cause))),
+            None if interrupted => Some((
+                GoodbyeCause::USER_EXIT,
+                String::from("The client was asked to disconnect"),
+            )),
+            // the backup server would keep routing the input to this client
+            None if failback => Some((
+                GoodbyeCause::USER_EXIT,
+                String::from("The client returns to the primary server"),
+            )),
+            None => None,
+        };
+        if let (Some((cause, description)), Some(mut writer)) = (goodbye, outcome.writer) {
+            let goodbye = ProtoOutMsg::from(JerryResponse::Goodbye(cause, description));
+            if let Some(capture) = self.capture.as_ref() {
+                capture.sent(&goodbye);
+            }
+            match writer.write_message(&goodbye).await {
+                Ok(_) => info!("Goodbye sent to the server"),
+                Err(e) => warn!("Goodbye not sent: {}", e),
             }
+            _ = writer.shutdown().await;
+        }
+        if interrupted {
+            return self.interrupted();
+        }
+        match outcome.handshake {
+            Some(Ok(())) => self.backoff.reset(),
+            Some(Err(rejection)) => {
+                return Some(SessionEnd::Failed(rejection.into()));
+            }
+            None => {}
+        }
+        if failback {
+            info!("Primary server is available again");
+            return Some(SessionEnd::Failback);
+        }
+        match outcome.error {
+            Some(e) => {
+                warn!("{}", e);
+                info!("Disconnected");
+                self.try_send_state(ConnectionState::ReadError(e.clone()))
+                    .then(|| SessionEnd::Failed(e))
+            }
+            // the message handler has no one to report to
+            None => None,
+        }
+    }
 
-            let (master, slave) = keys_option.unwrap();
-            use crate::security::{Decryptor, Encryptor};
+    /// A cancelled attempt ends the worker only if the shutdown was requested.
+    fn interrupted(&self) -> Option<SessionEnd> {
+        (!self.cancel.is_cancelled()).then_some(SessionEnd::Interrupted)
+    }
 
-            let out = match stream.try_clone() {
-                Ok(stream) => stream,
-                Err(_) => continue 'listen,
+    /// Waits before the next attempt and reports the remaining time every second.
+    async fn count_down(
+        &mut self,
+        delay: Duration,
+        controls: &mut Receiver<ControlRequest>,
+    ) -> bool {
+        let deadline = Instant::now() + delay;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return true;
+            }
+            let state = ConnectionState::Reconnecting {
+                attempt: self.backoff.attempt(),
+                max_attempts: self.backoff.max_attempts(),
+                remaining,
             };
-            let _ = out.set_nodelay(true);
-
-            let (mut in_stream, mut out_stream): (Box<dyn Read>, Box<dyn Write>) =
-                match crate::ENCRYPT {
-                    true => {
-                        let dec = Decryptor::new(stream, master);
-                        let enc = Encryptor::new(out, slave);
-                        (Box::new(dec), Box::new(enc))
-                    }
-                    false => (Box::new(stream), Box::new(out)),
-                };
-            let mut listener =
-                crate::serialization::ProtoSerDe::new(&mut in_stream, &mut out_stream);
-
-            if !self.try_send_state(ConnectionState::ConnectedSecured) {
-                break 'listen;
+            if !self.try_send_state(state) {
+                return false;
             }
+            // align the following ticks to whole seconds
+            let fraction = Duration::from_nanos(remaining.subsec_nanos() as u64);
+            let tick = match fraction.is_zero() {
+                true => COUNTDOWN_TICK,
+                false => fraction,
+            };
+            tokio::select! {
+                _ = self.cancel.cancelled() => return false,
+                Some(request) = controls.recv() => return self.follow(request, controls).await,
+                _ = tokio::time::sleep(tick) => {}
+            }
+        }
+    }
 
-            let msg_handler: ContextAwareMessageHandler =
-                ContextAwareMessageHandler::new(self.transmitter.clone(), self.info.clone());
-
-            if let Err(e) = listener.listen_loop(Box::new(msg_handler)) {
-                warn!("{}", e);
-                if !self.try_send_state(ConnectionState::ReadError(e.to_string())) {
-                    break 'listen;
-                }
+    /// Waits until the user decides whether to retry, or a controller asks to connect.
+    async fn prompt(
+        &mut self,
+        error: ConnectionError,
+        controls: &mut Receiver<ControlRequest>,
+    ) -> bool {
+        // answers given before the question was asked
+        while self.decisions.try_recv().is_ok() {}
+        if !self.try_send_state(ConnectionState::AwaitingDecision(error)) {
+            return false;
+        }
+        let decision = tokio::select! {
+            _ = self.cancel.cancelled() => None,
+            decision = self.decisions.recv() => decision,
+            Some(request) = controls.recv() => return self.follow(request, controls).await,
+        };
+        match decision {
+            Some(UserDecision::Retry) => {
+                self.backoff.reset();
+                true
             }
-            info!("Disconnected");
+            Some(UserDecision::Stop) | None => false,
+        }
+    }
+
+    fn goodbye_description(cause: GoodbyeCause) -> String {
+        match cause {
+            GoodbyeCause::USER_EXIT => String::from("The user closed the client"),
+            GoodbyeCause::TERMINATED => String::from("The client was terminated"),
         }
-        Ok(())
     }
 
     fn try_send_state(&self, state: ConnectionState) -> bool {
-        let result = self.transmitter.send(Command::ConnectionResult(state));
+        self.send(Command::ConnectionResult(state))
+    }
+
+    /// Waits for room in the views queue without stalling the other tasks of the runtime,
+    /// `Session::run` refuses the current-thread runtimes `block_in_place` panics on.
+    fn send(&self, command: Command) -> bool {
+        let result = match self.transmitter.try_send(command) {
+            Err(TrySendError::Full(command)) => {
+                tokio::task::block_in_place(|| self.transmitter.send(command))
+                    .map_err(|e| e.to_string())
+            }
+            result => result.map_err(|e| e.to_string()),
+        };
         match result {
             Ok(_) => true,
             Err(error) => {
@@ -0,0 +1,275 @@
+use super::queue::{self, QueueReceiver, QueueSender};
+use super::stats::LinkStats;
+use crate::configuration::SessionParams;
+use crate::core::{Command, ConsumerFactory, JerryMessage};
+use crate::error::{ConnectionError, ErrorSource, Rejection, TransportError};
+use crate::proto_rs::proto_in::MasterMessage_oneof_action as MsgType;
+use crate::proto_rs::{ProtoInMsg, ProtoOutMsg};
+use crate::security::{ChaChaKey, Decryptor, Encryptor};
+use crate::serialization::capture::Capture;
+use crate::serialization::compression::ClipboardCodec;
+use crate::serialization::ping::{self, Pinger};
+use crate::serialization::{FrameReader, FrameWriter};
+use std::sync::mpsc::SyncSender;
+use std::time::Duration;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+/// Decoded messages waiting for the emulator
+const INPUT_QUEUE: usize = 256;
+/// Messages waiting to be written to the server
+const OUTPUT_QUEUE: usize = 64;
+/// The server sends a heartbeat every second, a server silent for longer is gone
+/// even if the connection was never closed.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One connection to the server, split into three tasks joined by bounded channels:
+///
+/// * the reader decodes messages and answers heartbeats and pings,
+/// * the emulation thread feeds the messages to the `MessageConsumer`,
+///   mouse moves are merged while it is behind (see `queue::channel`),
+/// * the writer encodes the responses and sends them to the server.
+///
+/// The consumer reports to the views through the `Command` channel.
+pub struct Pipeline {
+    reader: FrameReader<OwnedReadHalf>,
+    writer: FrameWriter<OwnedWriteHalf>,
+    stats: LinkStats,
+    capture: Option<Capture>,
+}
+
+pub struct PipelineOutcome {
+    /// Error of the reader or the writer, `None` if the session was cancelled
+    /// or the message handler has finished.
+    pub error: Option<ConnectionError>,
+    /// Outcome of the handshake, `Err` holds the reason of the rejection
+    pub handshake: Option<Result<(), Rejection>>,
+    /// The connection can still be used to say goodbye
+    pub writer: Option<FrameWriter<OwnedWriteHalf>>,
+}
+
+impl Pipeline {
+    /// Without keys the messages are sent unencrypted.
+    pub fn new(stream: TcpStream, keys: Option<(ChaChaKey, ChaChaKey)>, stats: LinkStats) -> Self {
+        let (bytes_in, bytes_out) = stats.counters();
+        let (decryptor, encryptor) = match keys {
+            Some((master, slave)) => (Some(Decryptor::new(master)), Some(Encryptor::new(slave))),
+            None => (None, None),
+        };
+        let (read_half, write_half) = stream.into_split();
+        Self {
+            reader: FrameReader::new(read_half, decryptor).with_counter(bytes_in),
+            writer: FrameWriter::new(write_half, encryptor).with_counter(bytes_out),
+            stats,
+            capture: None,
+        }
+    }
+
+    /// Every message is written to `capture` as it is received or sent.
+    pub fn with_capture(mut self, capture: Option<Capture>) -> Self {
+        if let Some(capture) = capture.as_ref() {
+            capture.connected();
+        }
+        self.capture = capture;
+        self
+    }
+
+    /// Runs until the connection fails, the message handler finishes or `cancel` is cancelled.
+    pub async fn run(
+        self,
+        consumer: ConsumerFactory,
+        transmitter: SyncSender<Command>,
+        params: SessionParams,
+        cancel: CancellationToken,
+    ) -> PipelineOutcome {
+        // any of the tasks ends the whole session
+        let session = cancel.child_token();
+        let codec = ClipboardCodec::new();
+        let (input_tx, input_rx) = queue::channel(INPUT_QUEUE);
+        let (output_tx, output_rx) = mpsc::channel(OUTPUT_QUEUE);
+
+        let emulation = spawn_emulation(
+            consumer,
+            transmitter,
+            params,
+            input_rx,
+            output_tx.clone(),
+            session.clone(),
+        );
+        let writing = tokio::spawn(write_loop(
+            self.writer,
+            codec.clone(),
+            self.capture.clone(),
+            output_rx,
+            session.clone(),
+        ));
+        let mut stats = self.stats;
+        stats.watch_queue(input_tx.monitor());
+        let reading = tokio::spawn(read_loop(
+            self.reader,
+            codec,
+            self.capture,
+            stats,
+            input_tx,
+            output_tx,
+            session,
+        ));
+
+        let read_result = reading
+            .await
+            .unwrap_or_else(|e| Err(ConnectionError::Internal(ErrorSource::new(e))));
+        let handshake = emulation.await.unwrap_or_else(|_| {
+            error!("Emulation thread panicked");
+            None
+        });
+        let (write_result, writer) = match writing.await {
+            Ok((result, writer)) => (result, Some(writer)),
+            Err(e) => (Err(ConnectionError::Internal(ErrorSource::new(e))), None),
+        };
+        PipelineOutcome {
+            error: read_result.err().or(write_result.err()),
+            handshake,
+            writer,
+        }
+    }
+}
+
+async fn read_loop(
+    mut reader: FrameReader<OwnedReadHalf>,
+    mut codec: ClipboardCodec,
+    capture: Option<Capture>,
+    mut stats: LinkStats,
+    input: QueueSender,
+    output: mpsc::Sender<ProtoOutMsg>,
+    session: CancellationToken,
+) -> Result<(), ConnectionError> {
+    let mut pinger = Pinger::new();
+    loop {
+        let mut proto_in = tokio::select! {
+            _ = session.cancelled() => return Ok(()),
+            read = tokio::time::timeout(HEARTBEAT_TIMEOUT, reader.read_message::<ProtoInMsg>()) => {
+                read.map_err(|_| TransportError::Timeout(HEARTBEAT_TIMEOUT))??
+            }
+        };
+        stats.message_received();
+        if let Some(capture) = capture.as_ref() {
+            capture.received(&proto_in);
+        }
+        // ping/pong is handled here, the message handler never sees it
+        let forward = match proto_in.action.as_ref() {
+            Some(MsgType::pong(pong)) => {
+                if let Some(rtt) = pinger.rtt(pong) {
+                    stats.rtt_sample(rtt);
+                }
+                false
+            }
+            Some(MsgType::heartbeat(heartbeat)) if !heartbeat.one_way => {
+                let mut proto_out = ProtoOutMsg::new();
+                proto_out.set_pong(ping::echo(heartbeat.timestamp));
+                if !send(&output, proto_out, &session).await {
+                    return Ok(());
+                }
+                true
+            }
+            Some(MsgType::handshake(echo)) => {
+                pinger.enable(echo.ping);
+                stats.ping_supported(echo.ping);
+                true
+            }
+            _ => true,
+        };
+        if forward {
+            match codec.decode(&mut proto_in) {
+                Err(e) => warn!("Message discarded: {}", e),
+                Ok(()) => {
+                    let pushed = tokio::select! {
+                        _ = session.cancelled() => return Ok(()),
+                        pushed = input.push(JerryMessage::from(proto_in)) => pushed,
+                    };
+                    // the message handler has finished
+                    if pushed.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        if let Some(ping) = pinger.due() {
+            let mut proto_out = ProtoOutMsg::new();
+            proto_out.set_ping(ping);
+            if !send(&output, proto_out, &session).await {
+                return Ok(());
+            }
+        }
+        stats.tick();
+    }
+}
+
+/// Waits for room in the queue, false once the session is over.
+async fn send<T>(queue: &mpsc::Sender<T>, item: T, session: &CancellationToken) -> bool {
+    tokio::select! {
+        _ = session.cancelled() => false,
+        sent = queue.send(item) => sent.is_ok(),
+    }
+}
+
+/// Returns the writer once every sender is gone, so that the caller can still say goodbye.
+async fn write_loop(
+    mut writer: FrameWriter<OwnedWriteHalf>,
+    codec: ClipboardCodec,
+    capture: Option<Capture>,
+    mut output: mpsc::Receiver<ProtoOutMsg>,
+    session: CancellationToken,
+) -> (Result<(), ConnectionError>, FrameWriter<OwnedWriteHalf>) {
+    while let Some(mut proto_out) = output.recv().await {
+        if let Err(e) = codec.encode(&mut proto_out) {
+            warn!("Clipboard sent uncompressed: {}", e);
+        }
+        if let Some(capture) = capture.as_ref() {
+            capture.sent(&proto_out);
+        }
+        if let Err(e) = writer.write_message(&proto_out).await {
+            session.cancel();
+            return (Err(e), writer);
+        }
+    }
+    (Ok(()), writer)
+}
+
+/// The emulator calls block, so the consumer gets a thread of its own
+/// and slow calls never hold up the reader.
+/// Resolves to the outcome of the handshake once the thread is done.
+fn spawn_emulation(
+    consumer: ConsumerFactory,
+    transmitter: SyncSender<Command>,
+    params: SessionParams,
+    input: QueueReceiver,
+    output: mpsc::Sender<ProtoOutMsg>,
+    session: CancellationToken,
+) -> oneshot::Receiver<Option<Result<(), Rejection>>> {
+    let (done_tx, done_rx) = oneshot::channel();
+    std::thread::spawn(move || {
+        use thread_priority::*;
+        _ = set_current_thread_priority(ThreadPriority::Max);
+        let mut handler = consumer(transmitter, params);
+        while let Some(msg) = input.pop() {
+            if session.is_cancelled() {
+                break;
+            }
+            if let Some(response) = handler.consume(msg) {
+                if output.blocking_send(ProtoOutMsg::from(response)).is_err() {
+                    break;
+                }
+            }
+            if handler.finished() {
+                break;
+            }
+        }
+        session.cancel();
+        handler.disconnected();
+        _ = done_tx.send(handler.handshake().cloned());
+    });
+    done_rx
+}
@@ -0,0 +1,286 @@
+use crate::core::JerryMessage;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use tokio::sync::Notify;
+
+/// Bounded queue between the reader task and the emulation thread.
+///
+/// A mouse move is merged into the move at the tail of the queue, which is only
+/// there if the emulator has fallen behind. Relative moves are summed, absolute moves
+/// are replaced by the latest position. Moves are never merged across another message,
+/// so keys, buttons and wheel events keep their exact order.
+pub fn channel(capacity: usize) -> (QueueSender, QueueReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(QueueState::default()),
+        capacity: capacity.max(1),
+        available: Condvar::new(),
+        space: Notify::new(),
+    });
+    (
+        QueueSender {
+            shared: shared.clone(),
+        },
+        QueueReceiver { shared },
+    )
+}
+
+/// The other side of the queue is gone.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Closed;
+
+/// Queue metrics since the previous `QueueMonitor::take`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueueMetrics {
+    /// Messages waiting for the emulator
+    pub depth: usize,
+    pub peak: usize,
+    /// Mouse moves merged into the previous one
+    pub merged: u64,
+}
+
+struct Shared {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    /// Signals the emulation thread that a message was queued
+    available: Condvar,
+    /// Signals the reader task that a message was taken
+    space: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    messages: VecDeque<JerryMessage>,
+    /// Mode announced by the last `SessionBegin` that was queued
+    relative: bool,
+    closed: bool,
+    peak: usize,
+    merged: u64,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn close(&self) {
+        self.lock().closed = true;
+        self.available.notify_all();
+        // stores a permit if the reader task is not waiting yet
+        self.space.notify_one();
+    }
+}
+
+impl QueueState {
+    fn try_merge(&mut self, msg: &JerryMessage) -> bool {
+        let relative = self.relative;
+        match (self.messages.back_mut(), msg) {
+            (Some(JerryMessage::MouseMove(x, y)), JerryMessage::MouseMove(dx, dy)) => {
+                match relative {
+                    true => {
+                        *x = x.saturating_add(*dx);
+                        *y = y.saturating_add(*dy);
+                    }
+                    false => (*x, *y) = (*dx, *dy),
+                }
+                self.merged += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Producer side, used by the reader task. Closes the queue when dropped.
+pub struct QueueSender {
+    shared: Arc<Shared>,
+}
+
+impl QueueSender {
+    /// Waits for room in the queue unless the message can be merged.
+    pub async fn push(&self, msg: JerryMessage) -> Result<(), Closed> {
+        loop {
+            {
+                let mut state = self.shared.lock();
+                if state.closed {
+                    return Err(Closed);
+                }
+                if state.try_merge(&msg) {
+                    return Ok(());
+                }
+                if state.messages.len() < self.shared.capacity {
+                    if let JerryMessage::SessionBegin { relative_move } = msg {
+                        state.relative = relative_move;
+                    }
+                    state.messages.push_back(msg);
+                    state.peak = state.peak.max(state.messages.len());
+                    drop(state);
+                    self.shared.available.notify_one();
+                    return Ok(());
+                }
+            }
+            self.shared.space.notified().await;
+        }
+    }
+
+    pub fn monitor(&self) -> QueueMonitor {
+        QueueMonitor {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for QueueSender {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+/// Consumer side, used by the emulation thread. Closes the queue when dropped.
+pub struct QueueReceiver {
+    shared: Arc<Shared>,
+}
+
+impl QueueReceiver {
+    /// Blocks until a message is queued, `None` once the queue is closed and empty.
+    pub fn pop(&self) -> Option<JerryMessage> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(msg) = state.messages.pop_front() {
+                drop(state);
+                self.shared.space.notify_one();
+                return Some(msg);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .shared
+                .available
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
+
+impl Drop for QueueReceiver {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+/// Reads the metrics without keeping the queue open.
+pub struct QueueMonitor {
+    shared: Arc<Shared>,
+}
+
+impl QueueMonitor {
+    /// Resets the peak and the merge counter.
+    pub fn take(&self) -> QueueMetrics {
+        let mut state = self.shared.lock();
+        let depth = state.messages.len();
+        QueueMetrics {
+            depth,
+            peak: std::mem::replace(&mut state.peak, depth),
+            merged: std::mem::take(&mut state.merged),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Button, State};
+
+    fn drain(receiver: &QueueReceiver) -> Vec<String> {
+        let mut messages = Vec::new();
+        while let Some(msg) = receiver.pop() {
+            messages.push(format!("{:?}", msg));
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn merges_moves_without_reordering_other_events() {
+        let (sender, receiver) = channel(16);
+        let monitor = sender.monitor();
+        let messages = [
+            JerryMessage::SessionBegin {
+                relative_move: true,
+            },
+            JerryMessage::MouseMove(1, 1),
+            JerryMessage::MouseMove(2, -3),
+            JerryMessage::MouseClick(Button::LEFT, State::PRESSED),
+            JerryMessage::MouseMove(5, 5),
+            JerryMessage::Key(30, State::PRESSED),
+            JerryMessage::MouseMove(1, 0),
+            JerryMessage::MouseMove(1, 0),
+            JerryMessage::MouseMove(1, 0),
+        ];
+        for msg in messages {
+            sender.push(msg).await.unwrap();
+        }
+        assert_eq!(
+            monitor.take(),
+            QueueMetrics {
+                depth: 6,
+                peak: 6,
+                merged: 3
+            }
+        );
+        drop(sender);
+        assert_eq!(
+            drain(&receiver),
+            vec![
+                "SessionBegin { relative_move: true }",
+                "MouseMove(3, -2)",
+                "MouseClick(LEFT, PRESSED)",
+                "MouseMove(5, 5)",
+                "Key(30, PRESSED)",
+                "MouseMove(3, 0)",
+            ]
+        );
+        // the peak of an interval starts at the depth left by the previous one
+        assert_eq!(
+            monitor.take(),
+            QueueMetrics {
+                depth: 0,
+                peak: 6,
+                merged: 0
+            }
+        );
+        assert_eq!(monitor.take().peak, 0);
+    }
+
+    #[tokio::test]
+    async fn absolute_moves_keep_the_latest_position() {
+        let (sender, receiver) = channel(16);
+        for msg in [
+            JerryMessage::MouseMove(10, 10),
+            JerryMessage::MouseMove(20, 30),
+        ] {
+            sender.push(msg).await.unwrap();
+        }
+        drop(sender);
+        assert_eq!(drain(&receiver), vec!["MouseMove(20, 30)"]);
+    }
+
+    #[tokio::test]
+    async fn full_queue_waits_for_the_emulator() {
+        let (sender, receiver) = channel(1);
+        sender.push(JerryMessage::Heartbeat).await.unwrap();
+        // a move cannot be merged into a heartbeat
+        let pending = tokio::spawn(async move {
+            sender.push(JerryMessage::MouseMove(1, 1)).await.unwrap();
+            sender
+        });
+        tokio::task::yield_now().await;
+        assert!(!pending.is_finished());
+
+        assert!(matches!(receiver.pop(), Some(JerryMessage::Heartbeat)));
+        let sender = pending.await.unwrap();
+        drop(receiver);
+        assert_eq!(sender.push(JerryMessage::SessionEnd).await, Err(Closed));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::configuration::{ReconnectAction, ReconnectPolicy};
+use crate::error::{ConnectionError, TransportError};
+use rand::Rng;
+use std::time::Duration;
+
+/// Why the previous connection attempt or session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Connection,
+    KeyExchange,
+    Handshake,
+    Read,
+}
+
+impl From<&ConnectionError> for Failure {
+    fn from(error: &ConnectionError) -> Self {
+        match error {
+            ConnectionError::Transport(TransportError::Connect(_)) => Failure::Connection,
+            ConnectionError::Crypto(_) => Failure::KeyExchange,
+            ConnectionError::Handshake(_) => Failure::Handshake,
+            ConnectionError::Transport(_)
+            | ConnectionError::Protocol(_)
+            | ConnectionError::Internal(_) => Failure::Read,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Retry(Duration),
+    Stop,
+    Prompt,
+}
+
+/// Answer of the user to `ConnectionState::AwaitingDecision`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserDecision {
+    Retry,
+    Stop,
+}
+
+/// Exponential backoff with jitter driven by the `ReconnectPolicy`.
+pub struct Backoff {
+    policy: ReconnectPolicy,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(policy: ReconnectPolicy) -> Self {
+        Self { policy, attempt: 0 }
+    }
+
+    /// Number of failed attempts in a row
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.policy.max_attempts
+    }
+
+    /// Called once the client has been accepted by the server.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    pub fn on_failure(&mut self, failure: Failure) -> Decision {
+        let action = match failure {
+            Failure::Connection => self.policy.on_connection_error,
+            Failure::KeyExchange => self.policy.on_key_exchange_failed,
+            Failure::Handshake => self.policy.on_handshake_failed,
+            Failure::Read => self.policy.on_read_error,
+        };
+        match action {
+            ReconnectAction::Stop => Decision::Stop,
+            ReconnectAction::Prompt => Decision::Prompt,
+            ReconnectAction::Retry => {
+                self.attempt += 1;
+                match self.policy.max_attempts {
+                    Some(max) if self.attempt > max => Decision::Stop,
+                    _ => Decision::Retry(self.delay(rand::thread_rng().gen_range(-1.0..=1.0))),
+                }
+            }
+        }
+    }
+
+    /// `spread` in -1.0..=1.0 selects the jitter applied to the delay of the current attempt.
+    fn delay(&self, spread: f64) -> Duration {
+        let exponent = self.attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = (self.policy.initial_delay_ms as f64 * self.policy.multiplier.powi(exponent))
+            .min(self.policy.max_delay_ms as f64);
+        let jittered = base * (1.0 + self.policy.jitter * spread);
+        Duration::from_millis(jittered.max(0.0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay_ms: 100,
+            max_delay_ms: 1_000,
+            multiplier: 2.0,
+            jitter: 0.5,
+            max_attempts: Some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn delay_grows_exponentially_up_to_the_limit() {
+        let mut backoff = Backoff::new(policy());
+        let mut delays = Vec::new();
+        for attempt in 1..=6 {
+            backoff.attempt = attempt;
+            delays.push(backoff.delay(0.0).as_millis());
+        }
+        assert_eq!(delays, vec![100, 200, 400, 800, 1_000, 1_000]);
+
+        backoff.attempt = 2;
+        assert_eq!(backoff.delay(-1.0), Duration::from_millis(100));
+        assert_eq!(backoff.delay(1.0), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn stops_after_max_attempts_until_reset() {
+        let mut backoff = Backoff::new(policy());
+        for _ in 0..3 {
+            assert!(matches!(
+                backoff.on_failure(Failure::Connection),
+                Decision::Retry(_)
+            ));
+        }
+        assert_eq!(backoff.on_failure(Failure::Read), Decision::Stop);
+
+        backoff.reset();
+        assert!(matches!(
+            backoff.on_failure(Failure::Read),
+            Decision::Retry(_)
+        ));
+        assert_eq!(backoff.on_failure(Failure::KeyExchange), Decision::Stop);
+        assert_eq!(backoff.on_failure(Failure::Handshake), Decision::Prompt);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::core::GoodbyeCause;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Lets other threads (views, signal handler) end the session gracefully.
+///
+/// Requesting a shutdown cancels the token every task of the connection layer
+/// listens to. The connection worker then sends a `Goodbye` and shuts the connection
+/// down cleanly. The C# server does not read the `Goodbye`, it notices the closed
+/// connection on its next heartbeat and only then gives the control back.
+#[derive(Clone, Default)]
+pub struct ShutdownSignal {
+    cause: Arc<Mutex<Option<GoodbyeCause>>>,
+    token: CancellationToken,
+}
+
+impl ShutdownSignal {
+    /// Only the first request is taken into account.
+    pub fn request(&self, cause: GoodbyeCause) {
+        let Ok(mut current) = self.cause.lock() else {
+            return;
+        };
+        if current.is_none() {
+            *current = Some(cause);
+            self.token.cancel();
+        }
+    }
+
+    pub fn requested(&self) -> Option<GoodbyeCause> {
+        self.cause.lock().ok().and_then(|cause| *cause)
+    }
+
+    /// Cancelled once the shutdown is requested
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
@@ -0,0 +1,205 @@
+use super::queue::{QueueMetrics, QueueMonitor};
+use crate::core::Command;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Number of RTT samples the rolling average is computed from
+const RTT_WINDOW: usize = 16;
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Connection quality over the last report interval.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LinkSnapshot {
+    /// The server answers pings, RTT and jitter are only measured if it does.
+    /// The C# server does not yet, it sends one way heartbeats only.
+    pub ping: bool,
+    /// Rolling average of the round-trip time
+    pub rtt: Option<Duration>,
+    /// Smoothed variation of the round-trip time (RFC 3550)
+    pub jitter: Option<Duration>,
+    pub messages_per_sec: f64,
+    pub bytes_in_per_sec: f64,
+    pub bytes_out_per_sec: f64,
+    /// Messages waiting for the emulator
+    pub queue: QueueMetrics,
+}
+
+impl LinkSnapshot {
+    /// RTT and jitter, or why they are not measured.
+    pub fn latency(&self) -> String {
+        if !self.ping {
+            return String::from("RTT n/a (the server does not answer pings)");
+        }
+        let ms = |d: Option<Duration>| match d {
+            Some(d) => format!("{:.1} ms", d.as_secs_f64() * 1000.0),
+            None => String::from("n/a"),
+        };
+        format!("RTT {}, jitter {}", ms(self.rtt), ms(self.jitter))
+    }
+}
+
+impl fmt::Display for LinkSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}, {:.0} msg/s, in {:.1} kB/s, out {:.1} kB/s, queue {} (peak {}, {} moves merged)",
+            self.latency(),
+            self.messages_per_sec,
+            self.bytes_in_per_sec / 1000.0,
+            self.bytes_out_per_sec / 1000.0,
+            self.queue.depth,
+            self.queue.peak,
+            self.queue.merged
+        )
+    }
+}
+
+/// Collects the statistics of one session and reports them to the views.
+pub struct LinkStats {
+    transmitter: SyncSender<Command>,
+    ping: bool,
+    rtt: VecDeque<Duration>,
+    jitter: Option<f64>,
+    messages: u64,
+    bytes_in: ByteCounter,
+    bytes_out: ByteCounter,
+    queue: Option<QueueMonitor>,
+    last_report: Instant,
+}
+
+impl LinkStats {
+    pub fn new(transmitter: SyncSender<Command>) -> Self {
+        Self {
+            transmitter,
+            ping: false,
+            rtt: VecDeque::with_capacity(RTT_WINDOW),
+            jitter: None,
+            messages: 0,
+            bytes_in: ByteCounter::default(),
+            bytes_out: ByteCounter::default(),
+            queue: None,
+            last_report: Instant::now(),
+        }
+    }
+
+    /// Counters of the received and sent bytes, shared with the reader and writer tasks.
+    pub fn counters(&self) -> (ByteCounter, ByteCounter) {
+        (self.bytes_in.clone(), self.bytes_out.clone())
+    }
+
+    pub fn watch_queue(&mut self, monitor: QueueMonitor) {
+        self.queue = Some(monitor);
+    }
+
+    /// The server announced in the handshake whether it answers pings.
+    pub fn ping_supported(&mut self, ping: bool) {
+        self.ping = ping;
+    }
+
+    pub fn message_received(&mut self) {
+        self.messages += 1;
+    }
+
+    pub fn rtt_sample(&mut self, rtt: Duration) {
+        if let Some(previous) = self.rtt.back() {
+            let difference = (rtt.as_secs_f64() - previous.as_secs_f64()).abs();
+            let jitter = self.jitter.unwrap_or(0.0);
+            self.jitter = Some(jitter + (difference - jitter) / 16.0);
+        }
+        if self.rtt.len() == RTT_WINDOW {
+            self.rtt.pop_front();
+        }
+        self.rtt.push_back(rtt);
+    }
+
+    /// Resets the message and byte counters.
+    pub fn snapshot(&mut self) -> LinkSnapshot {
+        let elapsed = self.last_report.elapsed().as_secs_f64().max(f64::EPSILON);
+        self.last_report = Instant::now();
+        let rtt = match self.rtt.len() {
+            0 => None,
+            n => Some(self.rtt.iter().sum::<Duration>() / n as u32),
+        };
+        LinkSnapshot {
+            ping: self.ping,
+            rtt,
+            jitter: self.jitter.map(Duration::from_secs_f64),
+            messages_per_sec: std::mem::take(&mut self.messages) as f64 / elapsed,
+            bytes_in_per_sec: self.bytes_in.take() as f64 / elapsed,
+            bytes_out_per_sec: self.bytes_out.take() as f64 / elapsed,
+            queue: self
+                .queue
+                .as_ref()
+                .map(QueueMonitor::take)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Sends the snapshot to the views once per `REPORT_INTERVAL`.
+    ///
+    /// A snapshot is dropped rather than waited for when the views are busy.
+    pub fn tick(&mut self) {
+        if self.last_report.elapsed() >= REPORT_INTERVAL {
+            let snapshot = self.snapshot();
+            _ = self.transmitter.try_send(Command::LinkStats(snapshot));
+        }
+    }
+}
+
+/// Number of bytes passing through one direction of the connection.
+#[derive(Clone, Default)]
+pub struct ByteCounter(Arc<AtomicU64>);
+
+impl ByteCounter {
+    pub fn add(&self, bytes: usize) {
+        self.0.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn take(&self) -> u64 {
+        self.0.swap(0, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_rtt_and_jitter() {
+        let (tx, _rx) = std::sync::mpsc::sync_channel(1);
+        let mut stats = LinkStats::new(tx);
+        assert_eq!(stats.snapshot().rtt, None);
+        assert!(stats.snapshot().latency().contains("does not answer pings"));
+        stats.ping_supported(true);
+
+        for ms in [10, 30, 10, 30] {
+            stats.rtt_sample(Duration::from_millis(ms));
+        }
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.rtt, Some(Duration::from_millis(20)));
+        // 20 ms differences smoothed with gain 1/16
+        let jitter = snapshot.jitter.unwrap().as_secs_f64() * 1000.0;
+        assert!((jitter - 3.52).abs() < 0.01, "jitter {}", jitter);
+        assert_eq!(snapshot.latency(), "RTT 20.0 ms, jitter 3.5 ms");
+    }
+
+    #[test]
+    fn counts_bytes_in_both_directions() {
+        let (tx, _rx) = std::sync::mpsc::sync_channel(1);
+        let mut stats = LinkStats::new(tx);
+        let (bytes_in, bytes_out) = stats.counters();
+        bytes_in.add(100);
+        bytes_out.add(100);
+        bytes_out.add(3);
+
+        stats.last_report = Instant::now() - Duration::from_secs(1);
+        let snapshot = stats.snapshot();
+        assert!((snapshot.bytes_in_per_sec - 100.0).abs() < 1.0);
+        assert!((snapshot.bytes_out_per_sec - 103.0).abs() < 1.0);
+        assert_eq!(stats.snapshot().bytes_in_per_sec, 0.0);
+    }
+}
@@ -0,0 +1,245 @@
+//! Local control of a running client: status, reconnection, pause of the emulation
+//! and a stream of events, for scripts and status bars.
+//!
+//! The `ControlServer` listens on a Unix domain socket for newline delimited
+//! JSON-RPC 2.0 requests, see `rpc` for the methods:
+//!
+//! ```text
+//! $ echo '{"jsonrpc":"2.0","id":1,"method":"status"}' | socat - UNIX-CONNECT:$XDG_RUNTIME_DIR/jerry_client.sock
+//! {"jsonrpc":"2.0","id":1,"result":{"state":"handshake_success","description":"Handshake succeeded ()","active":false,"server":"office (10.0.0.2:8888)","paused":false}}
+//! ```
+//!
+//! The `Controller` follows the events of the session (`Controller::observe`)
+//! and passes the requests to the connection worker.
+pub mod rpc;
+#[cfg(unix)]
+mod server;
+
+use crate::connection::{ConnectionState, ControlRequest};
+use crate::core::clipboard::Transfer;
+use crate::core::message_handler::EmulationPause;
+use crate::core::JerryMessage;
+use crate::state::Command;
+use serde_derive::Serialize;
+#[cfg(unix)]
+pub use server::ControlServer;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio::sync::{broadcast, mpsc};
+
+/// Events kept for a subscriber that does not read them
+const EVENT_BACKLOG: usize = 256;
+
+/// `jerry_client.sock` in the runtime directory of the user.
+pub fn default_socket_path() -> PathBuf {
+    socket_path(std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from))
+}
+
+/// Without a runtime directory the socket goes to the shared temporary directory,
+/// named after the user so clients of other users do not take or remove it.
+fn socket_path(runtime_dir: Option<PathBuf>) -> PathBuf {
+    match runtime_dir {
+        Some(dir) => dir.join("jerry_client.sock"),
+        None => std::env::temp_dir().join(format!("jerry_client-{}.sock", user_id())),
+    }
+}
+
+#[cfg(unix)]
+fn user_id() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }
+}
+
+#[cfg(not(unix))]
+fn user_id() -> u32 {
+    std::process::id()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    /// `ConnectionState::name`
+    pub state: &'static str,
+    /// The state as shown in the log
+    pub description: String,
+    /// A session is active, the input of the server is emulated
+    pub active: bool,
+    /// Name and address of the server
+    pub server: Option<String>,
+    /// The emulation is paused
+    pub paused: bool,
+}
+
+/// Notification sent to the subscribers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Connection {
+        state: &'static str,
+        description: String,
+    },
+    Server {
+        server: String,
+    },
+    Session {
+        active: bool,
+    },
+    Clipboard {
+        /// `incoming` from the server or `outgoing`
+        direction: &'static str,
+        length: usize,
+    },
+    Emulation {
+        paused: bool,
+    },
+}
+
+/// Why a control request was not passed on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlError {
+    /// The server is not in the group of the session
+    UnknownServer(String),
+    /// The connection worker has stopped
+    Stopped,
+    /// Earlier requests are still waiting for the connection worker
+    Busy,
+}
+
+pub struct Controller {
+    status: Mutex<Status>,
+    events: broadcast::Sender<Event>,
+    requests: mpsc::Sender<ControlRequest>,
+    pause: EmulationPause,
+    /// Names of the servers of the group
+    servers: Vec<String>,
+}
+
+impl Controller {
+    /// `requests` are received by the session, see `SessionBuilder::controls`.
+    pub fn new(
+        servers: Vec<String>,
+        requests: mpsc::Sender<ControlRequest>,
+        pause: EmulationPause,
+    ) -> Self {
+        let status = Status {
+            state: ConnectionState::None.name(),
+            description: ConnectionState::None.to_string(),
+            active: false,
+            server: None,
+            paused: pause.is_paused(),
+        };
+        Self {
+            status: Mutex::new(status),
+            events: broadcast::channel(EVENT_BACKLOG).0,
+            requests,
+            pause,
+            servers,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        let mut status = self.lock().clone();
+        status.paused = self.pause.is_paused();
+        status
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Updates the status from an event of the session and notifies the subscribers.
+    pub fn observe(&self, command: &Command) {
+        let event = {
+            let mut status = self.lock();
+            match command {
+                Command::ConnectionResult(state) => {
+                    // the session ends with the connection
+                    status.active = false;
+                    status.state = state.name();
+                    status.description = state.to_string();
+                    Event::Connection {
+                        state: status.state,
+                        description: status.description.clone(),
+                    }
+                }
+                Command::ActiveServer(server) => {
+                    status.server = Some(server.clone());
+                    Event::Server {
+                        server: server.clone(),
+                    }
+                }
+                Command::Message(JerryMessage::SessionBegin { .. }) => {
+                    status.active = true;
+                    Event::Session { active: true }
+                }
+                Command::Message(JerryMessage::SessionEnd) => {
+                    status.active = false;
+                    Event::Session { active: false }
+                }
+                Command::ClipboardTransfer(transfer, content) => Event::Clipboard {
+                    direction: match transfer {
+                        Transfer::Incoming => "incoming",
+                        Transfer::Outgoing => "outgoing",
+                    },
+                    length: content.len(),
+                },
+                _ => return,
+            }
+        };
+        // nobody may have subscribed
+        _ = self.events.send(event);
+    }
+
+    pub fn request(&self, request: ControlRequest) -> Result<(), ControlError> {
+        if let ControlRequest::Switch(name) = &request {
+            if !self.servers.contains(name) {
+                return Err(ControlError::UnknownServer(name.clone()));
+            }
+        }
+        self.requests.try_send(request).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => ControlError::Busy,
+            mpsc::error::TrySendError::Closed(_) => ControlError::Stopped,
+        })
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        if self.pause.is_paused() != paused {
+            self.pause.set(paused);
+            _ = self.events.send(Event::Emulation { paused });
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Status> {
+        // the fields are only assigned, a poisoned status is still usable
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+use std::fmt;
+impl fmt::Display for ControlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ControlError::UnknownServer(name) => write!(f, "Server '{}' is not in the group", name),
+            ControlError::Stopped => write!(f, "The connection has stopped"),
+            ControlError::Busy => write!(f, "Too many requests"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn socket_path_is_per_user() {
+        assert_eq!(
+            socket_path(Some(PathBuf::from("/run/user/1000"))),
+            PathBuf::from("/run/user/1000/jerry_client.sock")
+        );
+        let shared = socket_path(None);
+        assert_eq!(shared.parent(), Some(std::env::temp_dir().as_path()));
+        assert_eq!(
+            shared.file_name().unwrap().to_str().unwrap(),
+            format!("jerry_client-{}.sock", user_id())
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! JSON-RPC 2.0 methods of the control socket, one request or response per line.
+//!
+//! | method       | params               | result                                  |
+//! |--------------|----------------------|-----------------------------------------|
+//! | `status`     |                      | `Status`                                |
+//! | `disconnect` |                      | `true`, the client waits for `reconnect` |
+//! | `reconnect`  |                      | `true`                                  |
+//! | `switch`     | `{"server": name}`   | `true`, `name` of a server of the group |
+//! | `pause`      | `{"paused": bool}`   | `true`, `paused` is `true` if omitted   |
+//! | `resume`     |                      | `true`                                  |
+//! | `subscribe`  |                      | `true`, followed by `event` notifications |
+//!
+//! An event is sent as `{"jsonrpc":"2.0","method":"event","params":{"event":"session","active":true}}`.
+use super::{ControlError, Controller, Event};
+use crate::connection::ControlRequest;
+use serde_json::{json, Value};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The request was valid but could not be passed to the connection worker
+pub const REQUEST_FAILED: i64 = -32000;
+
+/// What to send back for a line received.
+#[derive(Debug, PartialEq)]
+pub struct Reply {
+    /// `None` for a notification, a request without an id
+    pub line: Option<String>,
+    /// The connection receives the events from now on
+    pub subscribe: bool,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<ControlError> for RpcError {
+    fn from(e: ControlError) -> Self {
+        match e {
+            ControlError::UnknownServer(_) => RpcError::new(INVALID_PARAMS, e.to_string()),
+            ControlError::Stopped | ControlError::Busy => {
+                RpcError::new(REQUEST_FAILED, e.to_string())
+            }
+        }
+    }
+}
+
+pub fn reply(controller: &Controller, line: &str) -> Reply {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return error_reply(Value::Null, RpcError::new(PARSE_ERROR, e.to_string())),
+    };
+    let id = request.get("id").cloned();
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => {
+            let error = RpcError::new(INVALID_REQUEST, "Expected a JSON-RPC 2.0 request");
+            return error_reply(id.unwrap_or(Value::Null), error);
+        }
+    };
+    let params = request.get("params").unwrap_or(&Value::Null);
+    let result = call(controller, method, params);
+    let Some(id) = id else {
+        return Reply {
+            line: None,
+            subscribe: method == "subscribe" && result.is_ok(),
+        };
+    };
+    match result {
+        Ok(result) => Reply {
+            line: Some(json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string()),
+            subscribe: method == "subscribe",
+        },
+        Err(error) => error_reply(id, error),
+    }
+}
+
+pub fn notification(event: &Event) -> String {
+    json!({"jsonrpc": "2.0", "method": "event", "params": event}).to_string()
+}
+
+fn call(controller: &Controller, method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "status" => Ok(json!(controller.status())),
+        "disconnect" => request(controller, ControlRequest::Disconnect),
+        "reconnect" => request(controller, ControlRequest::Reconnect),
+        "switch" => match params.get("server").and_then(Value::as_str) {
+            Some(server) => request(controller, ControlRequest::Switch(server.to_owned())),
+            None => Err(RpcError::new(INVALID_PARAMS, "Expected {\"server\": name}")),
+        },
+        "pause" => {
+            let paused = match params.get("paused") {
+                None => true,
+                Some(paused) => paused
+                    .as_bool()
+                    .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Expected {\"paused\": bool}"))?,
+            };
+            controller.set_paused(paused);
+            Ok(Value::Bool(true))
+        }
+        "resume" => {
+            controller.set_paused(false);
+            Ok(Value::Bool(true))
+        }
+        "subscribe" => Ok(Value::Bool(true)),
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method '{}'", method),
+        )),
+    }
+}
+
+fn request(controller: &Controller, request: ControlRequest) -> Result<Value, RpcError> {
+    controller.request(request)?;
+    Ok(Value::Bool(true))
+}
+
+fn error_reply(id: Value, error: RpcError) -> Reply {
+    let error = json!({"code": error.code, "message": error.message});
+    Reply {
+        line: Some(json!({"jsonrpc": "2.0", "id": id, "error": error}).to_string()),
+        subscribe: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionState;
+    use crate::core::message_handler::EmulationPause;
+    use crate::core::JerryMessage;
+    use crate::state::Command;
+    use tokio::sync::mpsc;
+
+    fn controller() -> (Controller, mpsc::Receiver<ControlRequest>) {
+        let (requests, received) = mpsc::channel(4);
+        let servers = vec![String::from("office"), String::from("home")];
+        let controller = Controller::new(servers, requests, EmulationPause::default());
+        (controller, received)
+    }
+
+    fn call(controller: &Controller, request: &str) -> Value {
+        let reply = reply(controller, request);
+        serde_json::from_str(&reply.line.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn status_follows_the_session() {
+        let (controller, _received) = controller();
+        controller.observe(&Command::ActiveServer(String::from(
+            "office (10.0.0.2:8888)",
+        )));
+        controller.observe(&Command::ConnectionResult(
+            ConnectionState::HandshakeSuccess(String::new()),
+        ));
+        controller.observe(&Command::Message(JerryMessage::SessionBegin {
+            relative_move: false,
+        }));
+
+        let response = call(&controller, r#"{"jsonrpc":"2.0","id":7,"method":"status"}"#);
+        assert_eq!(response["id"], 7);
+        let status = &response["result"];
+        assert_eq!(status["state"], "handshake_success");
+        assert_eq!(status["active"], true);
+        assert_eq!(status["server"], "office (10.0.0.2:8888)");
+        assert_eq!(status["paused"], false);
+
+        controller.observe(&Command::ConnectionResult(ConnectionState::Disconnected));
+        let response = call(&controller, r#"{"jsonrpc":"2.0","id":8,"method":"status"}"#);
+        assert_eq!(response["result"]["active"], false);
+    }
+
+    #[test]
+    fn requests_are_passed_to_the_worker() {
+        let (controller, mut received) = controller();
+        let response = call(
+            &controller,
+            r#"{"jsonrpc":"2.0","id":1,"method":"switch","params":{"server":"home"}}"#,
+        );
+        assert_eq!(response["result"], true);
+        assert_eq!(
+            received.try_recv(),
+            Ok(ControlRequest::Switch(String::from("home")))
+        );
+
+        let response = call(
+            &controller,
+            r#"{"jsonrpc":"2.0","id":2,"method":"switch","params":{"server":"lab"}}"#,
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        // a notification is not answered
+        let reply = reply(&controller, r#"{"jsonrpc":"2.0","method":"disconnect"}"#);
+        assert_eq!(reply.line, None);
+        assert_eq!(received.try_recv(), Ok(ControlRequest::Disconnect));
+    }
+
+    #[test]
+    fn pause_is_notified_to_the_subscribers() {
+        let (controller, _received) = controller();
+        let reply = reply(
+            &controller,
+            r#"{"jsonrpc":"2.0","id":1,"method":"subscribe"}"#,
+        );
+        assert!(reply.subscribe);
+        let mut events = controller.subscribe();
+
+        call(&controller, r#"{"jsonrpc":"2.0","id":2,"method":"pause"}"#);
+        assert!(controller.status().paused);
+        let event = events.try_recv().unwrap();
+        assert_eq!(
+            notification(&event),
+            r#"{"jsonrpc":"2.0","method":"event","params":{"event":"emulation","paused":true}}"#
+        );
+        call(&controller, r#"{"jsonrpc":"2.0","id":3,"method":"resume"}"#);
+        assert!(!controller.status().paused);
+    }
+
+    #[test]
+    fn malformed_requests() {
+        let (controller, _received) = controller();
+        let response = call(&controller, "{");
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        let response = call(&controller, r#"{"id":1,"method":"status"}"#);
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        let response = call(
+            &controller,
+            r#"{"jsonrpc":"2.0","id":1,"method":"restart"}"#,
+        );
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        let response = call(
+            &controller,
+            r#"{"jsonrpc":"2.0","id":1,"method":"pause","params":{"paused":1}}"#,
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,122 @@
+use super::{rpc, Controller, Event};
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener as StdUnixListener, UnixStream as StdUnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Serves the `Controller` on a Unix domain socket, only to the user running the client.
+pub struct ControlServer {
+    listener: StdUnixListener,
+    path: PathBuf,
+    controller: Arc<Controller>,
+}
+
+impl ControlServer {
+    /// The socket left behind by a client that did not exit cleanly is replaced,
+    /// the one of a running client is not.
+    pub fn bind(path: &Path, controller: Arc<Controller>) -> io::Result<Self> {
+        if path.exists() {
+            if StdUnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is used by another client", path.display()),
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        let listener = StdUnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            path: path.to_owned(),
+            controller,
+        })
+    }
+
+    /// Accepts connections until `cancel` is cancelled, then removes the socket.
+    pub async fn run(self, cancel: CancellationToken) {
+        match UnixListener::from_std(self.listener) {
+            Ok(listener) => {
+                info!("Control socket: {}", self.path.display());
+                loop {
+                    let stream = tokio::select! {
+                        _ = cancel.cancelled() => break,
+                        accepted = listener.accept() => match accepted {
+                            Ok((stream, _)) => stream,
+                            Err(e) => {
+                                warn!("Control connection not accepted: {}", e);
+                                continue;
+                            }
+                        },
+                    };
+                    tokio::spawn(serve(stream, self.controller.clone(), cancel.clone()));
+                }
+            }
+            Err(e) => warn!("Control socket not served: {}", e),
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            debug!("Control socket not removed: {}", e);
+        }
+    }
+}
+
+/// Answers the requests of one connection and, once it subscribed, sends the events.
+async fn serve(stream: UnixStream, controller: Arc<Controller>, cancel: CancellationToken) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut events = None;
+    loop {
+        let line = tokio::select! {
+            _ = cancel.cancelled() => break,
+            received = lines.next_line() => match received {
+                Ok(Some(line)) => {
+                    let reply = rpc::reply(&controller, &line);
+                    if reply.subscribe && events.is_none() {
+                        events = Some(controller.subscribe());
+                    }
+                    match reply.line {
+                        Some(line) => line,
+                        None => continue,
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    debug!("Control connection closed: {}", e);
+                    break;
+                }
+            },
+            event = next_event(&mut events) => match event {
+                Some(event) => rpc::notification(&event),
+                None => break,
+            },
+        };
+        if writer
+            .write_all(format!("{}\n", line).as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Never completes before the connection subscribed, `None` once the session is gone.
+async fn next_event(events: &mut Option<broadcast::Receiver<Event>>) -> Option<Event> {
+    let Some(receiver) = events.as_mut() else {
+        return std::future::pending().await;
+    };
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return Some(event),
+            Err(RecvError::Lagged(missed)) => debug!("{} events missed by a subscriber", missed),
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
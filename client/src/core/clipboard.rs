@@ -0,0 +1,327 @@
+use crate::configuration::{ClipboardDirection, ClipboardFormat, ClipboardPolicy};
+use regex::Regex;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tracing::error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    /// Server -> client (`JerryMessage::Clipboard`)
+    Incoming,
+    /// Client -> server (`Request::CLIPBOARD`)
+    Outgoing,
+}
+
+/// Text clipboard of this computer.
+pub trait SystemClipboard: Send {
+    fn get_text(&mut self) -> Option<String>;
+    fn set_text(&mut self, content: String) -> Result<(), arboard::Error>;
+}
+
+/// The platform clipboard, opened for every access like the other applications do.
+pub struct PlatformClipboard;
+
+impl SystemClipboard for PlatformClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        match arboard::Clipboard::new() {
+            Ok(mut clipboard) => clipboard.get_text().ok(),
+            Err(e) => {
+                error!("Clipboard::new() failed {:?}", e);
+                None
+            }
+        }
+    }
+    fn set_text(&mut self, content: String) -> Result<(), arboard::Error> {
+        arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(content))
+    }
+}
+
+/// Clipboard kept in memory, clones share the content. Used to test the message handler.
+#[derive(Clone, Default)]
+pub struct MemoryClipboard(Arc<Mutex<Option<String>>>);
+
+impl MemoryClipboard {
+    pub fn content(&self) -> Option<String> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl SystemClipboard for MemoryClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.content()
+    }
+    fn set_text(&mut self, content: String) -> Result<(), arboard::Error> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(content);
+        Ok(())
+    }
+}
+
+/// Contents `ClipboardRestore::Merge` keeps in the clipboard history at the end of a
+/// session, the oldest first. The clipboard itself gets the previous content back.
+pub fn merge(previous: &str, current: &str) -> Vec<String> {
+    let mut kept: Vec<String> = Vec::new();
+    for content in [previous, current] {
+        if !content.is_empty() && !kept.iter().any(|k| k == content) {
+            kept.push(content.to_owned());
+        }
+    }
+    kept
+}
+
+/// Compiled form of [`ClipboardPolicy`].
+#[derive(Clone, Debug, Default)]
+pub struct ClipboardFilter {
+    direction: ClipboardDirection,
+    max_size: Option<usize>,
+    formats: Vec<ClipboardFormat>,
+    deny: Vec<Regex>,
+}
+
+impl ClipboardFilter {
+    /// Fails on an invalid deny rule rather than letting the content it targets through.
+    pub fn new(policy: &ClipboardPolicy) -> Result<Self, regex::Error> {
+        Ok(Self {
+            direction: policy.direction,
+            max_size: policy.max_size,
+            formats: policy.formats.clone(),
+            deny: policy
+                .deny
+                .iter()
+                .map(|p| Regex::new(p))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Returns the reason why the transfer is blocked.
+    pub fn check(&self, transfer: Transfer, content: &str, file: bool) -> Result<(), String> {
+        let allowed_direction = match (self.direction, transfer) {
+            (ClipboardDirection::Both, _) => true,
+            (ClipboardDirection::In, Transfer::Incoming) => true,
+            (ClipboardDirection::Out, Transfer::Outgoing) => true,
+            (_, _) => false,
+        };
+        if !allowed_direction {
+            return Err(format!(
+                "Clipboard direction {:?} is not allowed",
+                self.direction
+            ));
+        }
+        if let Some(max_size) = self.max_size {
+            if content.len() > max_size {
+                return Err(format!(
+                    "Clipboard content exceeds the size limit ({} > {} bytes)",
+                    content.len(),
+                    max_size
+                ));
+            }
+        }
+        let format = match file {
+            true => ClipboardFormat::File,
+            false => ClipboardFormat::Text,
+        };
+        if !self.formats.is_empty() && !self.formats.contains(&format) {
+            return Err(format!("Clipboard format {:?} is not allowed", format));
+        }
+        if let Some(rule) = self.deny.iter().find(|r| r.is_match(content)) {
+            return Err(format!(
+                "Clipboard content matches deny rule '{}'",
+                rule.as_str()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    /// `None` for a content kept by `ClipboardRestore::Merge`
+    pub transfer: Option<Transfer>,
+    pub content: String,
+    pub time: Instant,
+}
+
+impl HistoryEntry {
+    /// Single line preview with sensitive looking parts masked.
+    pub fn preview(&self, max_chars: usize) -> String {
+        let redacted = redact(&self.content);
+        let mut line: String = redacted
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .take(max_chars)
+            .collect();
+        if redacted.chars().count() > max_chars {
+            line.push('…');
+        }
+        line
+    }
+}
+
+/// Bounded list of clipboard transfers, the most recent entry first.
+pub struct ClipboardHistory {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+}
+
+impl ClipboardHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Consecutive transfers of the same content are stored only once,
+    /// returns `false` for such duplicates.
+    pub fn push(&mut self, transfer: Option<Transfer>, content: String) -> bool {
+        if self.entries.front().is_some_and(|e| e.content == content) {
+            return false;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_back();
+        }
+        self.entries.push_front(HistoryEntry {
+            transfer,
+            content,
+            time: Instant::now(),
+        });
+        true
+    }
+
+    /// Adds the content unless it is in the history already.
+    pub fn keep(&mut self, content: String) -> bool {
+        if self.entries.iter().any(|e| e.content == content) {
+            return false;
+        }
+        self.push(None, content)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&HistoryEntry> {
+        self.entries.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+const REDACTED: &str = "[redacted]";
+
+fn redact(content: &str) -> String {
+    static RULES: OnceLock<Vec<Regex>> = OnceLock::new();
+    let rules = RULES.get_or_init(|| {
+        [
+            // private keys, certificates
+            r"(?s)-----BEGIN [A-Z ]+-----.*?(-----END [A-Z ]+-----|$)",
+            // payment card numbers
+            r"\b(?:\d[ -]?){12,18}\d\b",
+            // password=..., token: ...
+            r"(?i)(password|passwd|secret|token)\s*[:=]\s*\S+",
+        ]
+        .iter()
+        .filter_map(|r| Regex::new(r).ok())
+        .collect()
+    });
+    rules.iter().fold(content.to_owned(), |text, rule| {
+        rule.replace_all(&text, REDACTED).into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_content() {
+        assert_eq!(merge("before", "server"), vec!["before", "server"]);
+        assert_eq!(merge("before", ""), vec!["before"]);
+        assert_eq!(merge("before", "before"), vec!["before"]);
+        assert_eq!(merge("", "server"), vec!["server"]);
+        assert!(merge("", "").is_empty());
+    }
+
+    #[test]
+    fn kept_content_is_added_once() {
+        let mut history = ClipboardHistory::new(3);
+        history.push(Some(Transfer::Incoming), String::from("server"));
+        history.push(Some(Transfer::Outgoing), String::from("client"));
+        assert!(!history.keep(String::from("server")));
+        assert!(history.keep(String::from("before")));
+        let contents: Vec<&str> = history.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(contents, vec!["before", "client", "server"]);
+        assert_eq!(history.get(0).unwrap().transfer, None);
+    }
+
+    fn filter(policy: ClipboardPolicy) -> ClipboardFilter {
+        ClipboardFilter::new(&policy).unwrap()
+    }
+
+    #[test]
+    fn direction() {
+        let incoming_only = filter(ClipboardPolicy {
+            direction: ClipboardDirection::In,
+            ..ClipboardPolicy::default()
+        });
+        assert!(incoming_only.check(Transfer::Incoming, "a", false).is_ok());
+        assert!(incoming_only.check(Transfer::Outgoing, "a", false).is_err());
+
+        let none = filter(ClipboardPolicy {
+            direction: ClipboardDirection::None,
+            ..ClipboardPolicy::default()
+        });
+        assert!(none.check(Transfer::Incoming, "a", false).is_err());
+        assert!(none.check(Transfer::Outgoing, "a", false).is_err());
+        assert!(ClipboardFilter::default()
+            .check(Transfer::Outgoing, "a", false)
+            .is_ok());
+    }
+
+    #[test]
+    fn format_and_size() {
+        let text_only = filter(ClipboardPolicy {
+            formats: vec![ClipboardFormat::Text],
+            max_size: Some(4),
+            ..ClipboardPolicy::default()
+        });
+        assert!(text_only.check(Transfer::Incoming, "text", false).is_ok());
+        assert_eq!(
+            text_only.check(Transfer::Incoming, "/tmp", true),
+            Err(String::from("Clipboard format File is not allowed"))
+        );
+        assert_eq!(
+            text_only.check(Transfer::Incoming, "texts", false),
+            Err(String::from(
+                "Clipboard content exceeds the size limit (5 > 4 bytes)"
+            ))
+        );
+    }
+
+    #[test]
+    fn deny_rules() {
+        let invalid = ClipboardPolicy {
+            deny: vec![String::from(r"^\d{16}$"), String::from("(")],
+            ..ClipboardPolicy::default()
+        };
+        assert!(ClipboardFilter::new(&invalid).is_err());
+
+        let deny = filter(ClipboardPolicy {
+            deny: vec![String::from(r"^\d{16}$")],
+            ..ClipboardPolicy::default()
+        });
+        assert_eq!(
+            deny.check(Transfer::Outgoing, "4111111111111111", false),
+            Err(String::from(
+                r"Clipboard content matches deny rule '^\d{16}$'"
+            ))
+        );
+        assert!(deny.check(Transfer::Outgoing, "4111 1111", false).is_ok());
+    }
+}
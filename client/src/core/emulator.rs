@@ -1,9 +1,9 @@
-use super::message_handler::ProcessingError;
 use super::Button;
 use super::Direction;
 use crate::emulation;
 #[cfg(target_os = "linux")]
 use crate::emulation::linux_k;
+use crate::error::EmulationError;
 #[cfg(target_os = "macos")]
 use core_graphics::event::{CGEvent, CGEventTapLocation, ScrollEventUnit};
 #[cfg(target_os = "macos")]
@@ -15,22 +15,43 @@ use enigo::{Enigo, MouseButton, MouseControllable};
 #[cfg(target_os = "linux")]
 use enigo::{Enigo, MouseButton, MouseControllable};
 use eyre::Result;
+use std::fmt;
 #[cfg(target_os = "linux")]
 use std::os::raw::c_int;
 #[cfg(target_os = "linux")]
 use std::ptr::null;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 #[cfg(target_os = "linux")]
 use x11::{xlib, xtest};
 
 pub trait Emulator {
-    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), ProcessingError>;
-    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), ProcessingError>;
-    fn get_cursor(&mut self) -> Result<(i32, i32), ProcessingError>;
-    fn mouse_up(&mut self, button: Button) -> Result<(), ProcessingError>;
-    fn mouse_down(&mut self, button: Button) -> Result<(), ProcessingError>;
-    fn mouse_wheel(&mut self, direction: Direction, amount: f32) -> Result<(), ProcessingError>;
-    fn key_down(&mut self, key: u32) -> Result<(), ProcessingError>;
-    fn key_up(&mut self, key: u32) -> Result<(), ProcessingError>;
+    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), EmulationError>;
+    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), EmulationError>;
+    fn get_cursor(&mut self) -> Result<(i32, i32), EmulationError>;
+    fn mouse_up(&mut self, button: Button) -> Result<(), EmulationError>;
+    fn mouse_down(&mut self, button: Button) -> Result<(), EmulationError>;
+    fn mouse_wheel(&mut self, direction: Direction, amount: f32) -> Result<(), EmulationError>;
+    fn key_down(&mut self, key: u32) -> Result<(), EmulationError>;
+    fn key_up(&mut self, key: u32) -> Result<(), EmulationError>;
+}
+
+#[cfg(target_os = "windows")]
+pub fn platform_emulator() -> Box<dyn Emulator> {
+    Box::new(WindowsImpl::new())
+}
+#[cfg(target_os = "linux")]
+pub fn platform_emulator() -> Box<dyn Emulator> {
+    Box::new(LinuxImpl::new())
+}
+#[cfg(target_os = "macos")]
+pub fn platform_emulator() -> Box<dyn Emulator> {
+    Box::new(MacImpl::new())
+}
+
+/// The key has no code on this platform.
+fn unsupported_key(key: u32) -> EmulationError {
+    EmulationError::Unsupported(format!("key {}", emulation::key_name(key)))
 }
 
 //======================================================
@@ -47,35 +68,174 @@ impl NoopEmulator {
         }
     }
 }
+impl Default for NoopEmulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Emulator for NoopEmulator {
-    fn mouse_move_to(&mut self, _x: i32, _y: i32) -> Result<(), ProcessingError> {
+    fn mouse_move_to(&mut self, _x: i32, _y: i32) -> Result<(), EmulationError> {
         self.cursor_pos = (_x, _y);
         Ok(())
     }
-    fn mouse_up(&mut self, _button: Button) -> Result<(), ProcessingError> {
+    fn mouse_up(&mut self, _button: Button) -> Result<(), EmulationError> {
         Ok(())
     }
-    fn mouse_down(&mut self, _button: Button) -> Result<(), ProcessingError> {
+    fn mouse_down(&mut self, _button: Button) -> Result<(), EmulationError> {
         Ok(())
     }
-    fn mouse_wheel(&mut self, _direction: Direction, _: f32) -> Result<(), ProcessingError> {
+    fn mouse_wheel(&mut self, _direction: Direction, _: f32) -> Result<(), EmulationError> {
         Ok(())
     }
-    fn key_down(&mut self, _key: u32) -> Result<(), ProcessingError> {
+    fn key_down(&mut self, _key: u32) -> Result<(), EmulationError> {
         Ok(())
     }
-    fn key_up(&mut self, _key: u32) -> Result<(), ProcessingError> {
+    fn key_up(&mut self, _key: u32) -> Result<(), EmulationError> {
         Ok(())
     }
-    fn mouse_move_rel(&mut self, _dx: i32, _dy: i32) -> Result<(), ProcessingError> {
-        self.cursor_pos.0 += _dx;
-        self.cursor_pos.1 += _dy;
+    fn mouse_move_rel(&mut self, _dx: i32, _dy: i32) -> Result<(), EmulationError> {
+        self.cursor_pos.0 = self.cursor_pos.0.saturating_add(_dx);
+        self.cursor_pos.1 = self.cursor_pos.1.saturating_add(_dy);
+        Ok(())
+    }
+
+    fn get_cursor(&mut self) -> Result<(i32, i32), EmulationError> {
+        Ok(self.cursor_pos)
+    }
+}
+
+//======================================================
+/// Emulator call logged by the `RecordingEmulator`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmulatorCall {
+    MouseMoveRel(i32, i32),
+    MouseMoveTo(i32, i32),
+    MouseUp(Button),
+    MouseDown(Button),
+    MouseWheel(Direction, f32),
+    KeyDown(u32),
+    KeyUp(u32),
+}
+
+impl fmt::Display for EmulatorCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let key = emulation::key_name;
+        match self {
+            EmulatorCall::MouseMoveRel(dx, dy) => write!(f, "mouse_move_rel({}, {})", dx, dy),
+            EmulatorCall::MouseMoveTo(x, y) => write!(f, "mouse_move_to({}, {})", x, y),
+            EmulatorCall::MouseUp(button) => write!(f, "mouse_up({:?})", button),
+            EmulatorCall::MouseDown(button) => write!(f, "mouse_down({:?})", button),
+            EmulatorCall::MouseWheel(direction, amount) => {
+                write!(f, "mouse_wheel({:?}, {})", direction, amount)
+            }
+            EmulatorCall::KeyDown(code) => write!(f, "key_down({})", key(*code)),
+            EmulatorCall::KeyUp(code) => write!(f, "key_up({})", key(*code)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedCall {
+    /// Time elapsed since the emulator was created
+    pub at: Duration,
+    pub call: EmulatorCall,
+}
+
+/// Calls of a `RecordingEmulator`, shared with the owner of the emulator.
+#[derive(Clone, Default)]
+pub struct CallLog(Arc<Mutex<Vec<RecordedCall>>>);
+
+impl CallLog {
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.lock().clone()
+    }
+
+    /// Returns the calls logged so far and clears the log.
+    pub fn take(&self) -> Vec<RecordedCall> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<RecordedCall>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Emulator logging every call with a timestamp, used to test the message handler.
+///
+/// The cursor follows the moves like the one of the `NoopEmulator`.
+pub struct RecordingEmulator {
+    epoch: Instant,
+    cursor_pos: (i32, i32),
+    log: CallLog,
+}
+
+impl RecordingEmulator {
+    pub fn new() -> Self {
+        Self::with_cursor(0, 0)
+    }
+
+    pub fn with_cursor(x: i32, y: i32) -> Self {
+        Self {
+            epoch: Instant::now(),
+            cursor_pos: (x, y),
+            log: CallLog::default(),
+        }
+    }
+
+    /// Logs into `log` instead, e.g. shared by the emulators of successive connections.
+    pub fn log_to(mut self, log: CallLog) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Keep it to read the calls once the emulator is handed over.
+    pub fn log(&self) -> CallLog {
+        self.log.clone()
+    }
+
+    fn record(&self, call: EmulatorCall) -> Result<(), EmulationError> {
+        let at = self.epoch.elapsed();
+        self.log.lock().push(RecordedCall { at, call });
         Ok(())
     }
+}
 
-    fn get_cursor(&mut self) -> Result<(i32, i32), ProcessingError> {
+impl Default for RecordingEmulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emulator for RecordingEmulator {
+    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), EmulationError> {
+        self.cursor_pos.0 = self.cursor_pos.0.saturating_add(dx);
+        self.cursor_pos.1 = self.cursor_pos.1.saturating_add(dy);
+        self.record(EmulatorCall::MouseMoveRel(dx, dy))
+    }
+    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), EmulationError> {
+        self.cursor_pos = (x, y);
+        self.record(EmulatorCall::MouseMoveTo(x, y))
+    }
+    fn get_cursor(&mut self) -> Result<(i32, i32), EmulationError> {
         Ok(self.cursor_pos)
     }
+    fn mouse_up(&mut self, button: Button) -> Result<(), EmulationError> {
+        self.record(EmulatorCall::MouseUp(button))
+    }
+    fn mouse_down(&mut self, button: Button) -> Result<(), EmulationError> {
+        self.record(EmulatorCall::MouseDown(button))
+    }
+    fn mouse_wheel(&mut self, direction: Direction, amount: f32) -> Result<(), EmulationError> {
+        self.record(EmulatorCall::MouseWheel(direction, amount))
+    }
+    fn key_down(&mut self, key: u32) -> Result<(), EmulationError> {
+        self.record(EmulatorCall::KeyDown(key))
+    }
+    fn key_up(&mut self, key: u32) -> Result<(), EmulationError> {
+        self.record(EmulatorCall::KeyUp(key))
+    }
 }
 
 ///NOTE: wheel acceleration is not implemented
@@ -97,53 +257,51 @@ impl WindowsImpl {
 }
 #[cfg(target_os = "windows")]
 impl Emulator for WindowsImpl {
-    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), ProcessingError> {
-        emulation::windows::raw_relative_move_px(&(dx), &(dy))
-            .map_err(|_| ProcessingError::FailedToProcess)
+    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), EmulationError> {
+        emulation::windows::raw_relative_move_px(&(dx), &(dy)).map_err(EmulationError::backend)
     }
-    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), ProcessingError> {
-        emulation::windows::mouse_move_primary(x as f64, y as f64)
-            .map_err(|_| ProcessingError::FailedToProcess)
+    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), EmulationError> {
+        emulation::windows::mouse_move_primary(x as f64, y as f64).map_err(EmulationError::backend)
     }
 
-    fn key_down(&mut self, key: u32) -> Result<(), ProcessingError> {
-        let win_vk = u8::try_from(key).map_err(|_e| ProcessingError::UnableToProcess)?;
+    fn key_down(&mut self, key: u32) -> Result<(), EmulationError> {
+        let win_vk = u8::try_from(key).map_err(|_| unsupported_key(key))?;
         self.keyboard_emu
             .key_emu_hybrid(win_vk, true)
-            .map_err(|_e| ProcessingError::UnableToProcess)
+            .map_err(EmulationError::backend)
     }
-    fn key_up(&mut self, key: u32) -> Result<(), ProcessingError> {
-        let win_vk = u8::try_from(key).unwrap();
+    fn key_up(&mut self, key: u32) -> Result<(), EmulationError> {
+        let win_vk = u8::try_from(key).map_err(|_| unsupported_key(key))?;
         self.keyboard_emu
             .key_emu_hybrid(win_vk, false)
-            .map_err(|_e| ProcessingError::UnableToProcess)
+            .map_err(EmulationError::backend)
     }
-    fn mouse_up(&mut self, button: Button) -> Result<(), ProcessingError> {
+    fn mouse_up(&mut self, button: Button) -> Result<(), EmulationError> {
         self.mouse_emu
             .release(button.into())
-            .map_err(|_| ProcessingError::UnableToProcess)
+            .map_err(EmulationError::backend)
     }
 
-    fn mouse_down(&mut self, button: Button) -> Result<(), ProcessingError> {
+    fn mouse_down(&mut self, button: Button) -> Result<(), EmulationError> {
         self.mouse_emu
             .press(button.into())
-            .map_err(|_| ProcessingError::UnableToProcess)
+            .map_err(EmulationError::backend)
         // xbutton: Option + [  ] ?
     }
 
-    fn mouse_wheel(&mut self, direction: Direction, am: f32) -> Result<(), ProcessingError> {
+    fn mouse_wheel(&mut self, direction: Direction, am: f32) -> Result<(), EmulationError> {
         match direction {
             Direction::SCROLL_UP => self.mouse_emu.wheel(am, false),
             Direction::SCROLL_DOWN => self.mouse_emu.wheel(am, false),
             Direction::SCROLL_LEFT => self.mouse_emu.wheel(am, true),
             Direction::SCROLL_RIGHT => self.mouse_emu.wheel(am, true),
         }
-        .map_err(|_| ProcessingError::UnableToProcess)
+        .map_err(EmulationError::backend)
     }
 
-    fn get_cursor(&mut self) -> Result<(i32, i32), ProcessingError> {
+    fn get_cursor(&mut self) -> Result<(i32, i32), EmulationError> {
         emulation::windows::get_cursor_pos()
-            .map_err(|_| ProcessingError::FailedToProcess)
+            .map_err(EmulationError::backend)
             .map(|p| (p.x, p.y))
     }
 }
@@ -172,67 +330,69 @@ impl MacImpl {
             Button::XBUTTON2 => None,
         }
     }
-    fn scroll_y(&self, amount: f32) -> Result<(), ProcessingError> {
+    fn scroll_y(&self, amount: f32) -> Result<(), EmulationError> {
         let amount = (amount / 3f32 + amount.signum() * 1f32) as i32;
         let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
-            .map_err(|_| ProcessingError::FailedToProcess)?;
+            .map_err(|_| EmulationError::backend("Event source not created"))?;
         let event =
             CGEvent::new_scroll_event(source.clone(), ScrollEventUnit::PIXEL, 1, amount, 0, 0)
-                .expect("Failed to create a scroll event"); // do not panic
+                .map_err(|_| EmulationError::backend("Scroll event not created"))?;
         event.post(CGEventTapLocation::HID);
         Ok(())
     }
-    fn scroll_x(&self, amount: f32) -> Result<(), ProcessingError> {
+    fn scroll_x(&self, amount: f32) -> Result<(), EmulationError> {
         let amount = (amount / 3f32 + amount.signum() * 1f32) as i32;
         let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
-            .map_err(|_| ProcessingError::FailedToProcess)?;
+            .map_err(|_| EmulationError::backend("Event source not created"))?;
         let event =
             CGEvent::new_scroll_event(source.clone(), ScrollEventUnit::PIXEL, 2, 0, amount, 0)
-                .expect("Failed to create a scroll event");
+                .map_err(|_| EmulationError::backend("Scroll event not created"))?;
         event.post(CGEventTapLocation::HID);
         Ok(())
     }
 }
 #[cfg(target_os = "macos")]
 impl Emulator for MacImpl {
-    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), ProcessingError> {
+    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), EmulationError> {
         self.enigo.mouse_move_relative(dx, dy);
         Ok(())
     }
 
-    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), ProcessingError> {
+    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), EmulationError> {
         self.enigo.mouse_move_to(x, y);
         Ok(())
     }
 
-    fn get_cursor(&mut self) -> Result<(i32, i32), ProcessingError> {
+    fn get_cursor(&mut self) -> Result<(i32, i32), EmulationError> {
         let loc = self.enigo.mouse_location();
         Ok(loc)
     }
 
-    fn mouse_up(&mut self, button: Button) -> Result<(), ProcessingError> {
+    fn mouse_up(&mut self, button: Button) -> Result<(), EmulationError> {
         if let Some(btn) = self.convert_btn(button) {
             self.enigo.mouse_up(btn);
             Ok(())
         } else {
-            Err(ProcessingError::UnableToProcessPlatformSpecific(
-                "Xbutton not suported on Mac".to_owned(),
-            ))
+            Err(EmulationError::Unsupported(format!(
+                "{:?} on macOS",
+                button
+            )))
         }
     }
 
-    fn mouse_down(&mut self, button: Button) -> Result<(), ProcessingError> {
+    fn mouse_down(&mut self, button: Button) -> Result<(), EmulationError> {
         if let Some(btn) = self.convert_btn(button) {
             self.enigo.mouse_down(btn);
             Ok(())
         } else {
-            Err(ProcessingError::UnableToProcessPlatformSpecific(
-                "Xbutton not suported on Mac".to_owned(),
-            ))
+            Err(EmulationError::Unsupported(format!(
+                "{:?} on macOS",
+                button
+            )))
         }
     }
 
-    fn mouse_wheel(&mut self, direction: Direction, amount: f32) -> Result<(), ProcessingError> {
+    fn mouse_wheel(&mut self, direction: Direction, amount: f32) -> Result<(), EmulationError> {
         match direction {
             Direction::SCROLL_UP | Direction::SCROLL_DOWN => {
                 self.scroll_y(amount)?;
@@ -246,33 +406,33 @@ impl Emulator for MacImpl {
 
     //     #[link(name = "Cocoa", kind = "framework")]
     // extern "C" {}
-    fn key_down(&mut self, key: u32) -> Result<(), ProcessingError> {
+    fn key_down(&mut self, key: u32) -> Result<(), EmulationError> {
         let key = key as u8;
         match emulation::mac_k::code_from_key(key.into()) {
             Some(code) => {
                 let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
-                    .map_err(|_| ProcessingError::FailedToProcess)?;
+                    .map_err(|_| EmulationError::backend("Event source not created"))?;
                 let cg_event = CGEvent::new_keyboard_event(source, code, true)
-                    .map_err(|_| ProcessingError::FailedToProcess)?;
+                    .map_err(|_| EmulationError::backend("Keyboard event not created"))?;
                 cg_event.post(CGEventTapLocation::HID);
                 Ok(())
             }
-            None => Err(ProcessingError::UnableToProcess),
+            None => Err(unsupported_key(key.into())),
         }
     }
 
-    fn key_up(&mut self, key: u32) -> Result<(), ProcessingError> {
+    fn key_up(&mut self, key: u32) -> Result<(), EmulationError> {
         let key = key as u8;
         match emulation::mac_k::code_from_key(key.into()) {
             Some(code) => {
                 let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
-                    .map_err(|_| ProcessingError::FailedToProcess)?;
+                    .map_err(|_| EmulationError::backend("Event source not created"))?;
                 let cg_event = CGEvent::new_keyboard_event(source, code, false)
-                    .map_err(|_| ProcessingError::FailedToProcess)?;
+                    .map_err(|_| EmulationError::backend("Keyboard event not created"))?;
                 cg_event.post(CGEventTapLocation::HID);
                 Ok(())
             }
-            None => Err(ProcessingError::UnableToProcess),
+            None => Err(unsupported_key(key.into())),
         }
 
         // let code = emulation::mac_k::code_from_key(*key)?;
@@ -295,6 +455,12 @@ pub struct LinuxImpl {
     wheel_y: i32,
 }
 #[cfg(target_os = "linux")]
+impl Default for LinuxImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[cfg(target_os = "linux")]
 impl LinuxImpl {
     pub fn new() -> Self {
         Self {
@@ -312,36 +478,31 @@ impl LinuxImpl {
             Button::XBUTTON2 => MouseButton::Back,
         }
     }
-    fn simulate(&mut self, event_type: EventType) -> Result<(), ProcessingError> {
+    fn simulate(&mut self, event_type: EventType) -> Result<(), EmulationError> {
         unsafe {
             let dpy = xlib::XOpenDisplay(null());
             if dpy.is_null() {
-                return Err(ProcessingError::FailedToProcess); // "Can't open X11 Display"
+                return Err(EmulationError::DisplayUnavailable);
             }
-            match self.send_native(event_type, dpy) {
-                Some(_) => {
-                    xlib::XFlush(dpy);
-                    xlib::XSync(dpy, 0);
-                    xlib::XCloseDisplay(dpy);
-                    Ok(())
-                }
-                None => {
-                    xlib::XCloseDisplay(dpy);
-                    Err(ProcessingError::FailedToProcess)
-                }
+            let result = self.send_native(event_type, dpy);
+            if result.is_ok() {
+                xlib::XFlush(dpy);
+                xlib::XSync(dpy, 0);
             }
+            xlib::XCloseDisplay(dpy);
+            result
         }
     }
     unsafe fn send_native(
         &mut self,
         event_type: EventType,
         display: *mut xlib::Display,
-    ) -> Option<()> {
+    ) -> Result<(), EmulationError> {
         let mut result: c_int = 1;
         match event_type {
             EventType::WheelY(amount) => {
-                self.wheel_y += amount as i32;
-                while self.wheel_y.abs() > 30 {
+                self.wheel_y = self.wheel_y.saturating_add(amount as i32);
+                while self.wheel_y.unsigned_abs() > 30 {
                     let step = 30 * self.wheel_y.signum();
                     let code = if self.wheel_y.signum() > 0 { 4 } else { 5 };
                     result &= xtest::XTestFakeButtonEvent(display, code, 1 as c_int, 0)
@@ -350,8 +511,8 @@ impl LinuxImpl {
                 }
             }
             EventType::WheelX(amount) => {
-                self.wheel_x += amount as i32;
-                while self.wheel_x.abs() > 30 {
+                self.wheel_x = self.wheel_x.saturating_add(amount as i32);
+                while self.wheel_x.unsigned_abs() > 30 {
                     let step = 30 * self.wheel_x.signum();
                     let code = if self.wheel_x.signum() > 0 { 7 } else { 6 };
                     result &= xtest::XTestFakeButtonEvent(display, code, 1 as c_int, 0)
@@ -361,52 +522,54 @@ impl LinuxImpl {
             }
             EventType::KeyDown(key) => {
                 let key = key as u8;
-                let code = linux_k::code_from_key(key.into())?;
+                let code = linux_k::code_from_key(key.into())
+                    .ok_or_else(|| unsupported_key(key.into()))?;
                 result &= xtest::XTestFakeKeyEvent(display, code, 1 as c_int, 0);
             }
             EventType::KeyUp(key) => {
                 let key = key as u8;
-                let code = linux_k::code_from_key(key.into())?;
+                let code = linux_k::code_from_key(key.into())
+                    .ok_or_else(|| unsupported_key(key.into()))?;
                 result &= xtest::XTestFakeKeyEvent(display, code, 0 as c_int, 0);
             }
         }
         if result == 1 {
-            Some(())
+            Ok(())
         } else {
-            None
+            Err(EmulationError::backend("The X server rejected the event"))
         }
     }
 }
 #[cfg(target_os = "linux")]
 impl Emulator for LinuxImpl {
-    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), ProcessingError> {
+    fn mouse_move_rel(&mut self, dx: i32, dy: i32) -> Result<(), EmulationError> {
         self.enigo.mouse_move_relative(dx, dy);
         Ok(())
     }
 
-    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), ProcessingError> {
+    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), EmulationError> {
         self.enigo.mouse_move_to(x, y);
         Ok(())
     }
 
-    fn get_cursor(&mut self) -> Result<(i32, i32), ProcessingError> {
+    fn get_cursor(&mut self) -> Result<(i32, i32), EmulationError> {
         let loc = self.enigo.mouse_location();
         Ok(loc)
     }
 
-    fn mouse_up(&mut self, button: Button) -> Result<(), ProcessingError> {
+    fn mouse_up(&mut self, button: Button) -> Result<(), EmulationError> {
         let btn = self.convert_btn(button);
         self.enigo.mouse_up(btn);
         Ok(())
     }
 
-    fn mouse_down(&mut self, button: Button) -> Result<(), ProcessingError> {
+    fn mouse_down(&mut self, button: Button) -> Result<(), EmulationError> {
         let btn = self.convert_btn(button);
         self.enigo.mouse_down(btn);
         Ok(())
     }
 
-    fn mouse_wheel(&mut self, direction: Direction, amount: f32) -> Result<(), ProcessingError> {
+    fn mouse_wheel(&mut self, direction: Direction, amount: f32) -> Result<(), EmulationError> {
         let event = match direction {
             Direction::SCROLL_UP | Direction::SCROLL_DOWN => EventType::WheelY(amount),
             Direction::SCROLL_LEFT | Direction::SCROLL_RIGHT => EventType::WheelX(amount),
@@ -414,11 +577,11 @@ impl Emulator for LinuxImpl {
         self.simulate(event)
     }
 
-    fn key_down(&mut self, key: u32) -> Result<(), ProcessingError> {
+    fn key_down(&mut self, key: u32) -> Result<(), EmulationError> {
         self.simulate(EventType::KeyDown(key))
     }
 
-    fn key_up(&mut self, key: u32) -> Result<(), ProcessingError> {
+    fn key_up(&mut self, key: u32) -> Result<(), EmulationError> {
         self.simulate(EventType::KeyUp(key))
     }
 }
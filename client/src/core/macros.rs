@@ -0,0 +1,396 @@
+use super::emulator::Emulator;
+use super::{Button, Direction, JerryMessage, State};
+use crate::error::EmulationError;
+use eyre::{eyre, Context, Result};
+use protobuf::ProtobufEnum;
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const MACRO_DIRECTORY: &str = "macros";
+/// Time to focus the target window before the replay starts
+pub(crate) const REPLAY_COUNTDOWN: Duration = Duration::from_secs(3);
+/// Replay is aborted as soon as the cursor is moved to this position (top left corner)
+const FAILSAFE_POSITION: (i32, i32) = (0, 0);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MacroAction {
+    Key { code: u32, pressed: bool },
+    Button { button: i32, pressed: bool },
+    Wheel { direction: i32, amount: i32 },
+    Move { dx: i32, dy: i32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct MacroEvent {
+    /// Time elapsed since the previous event
+    pub delay_ms: u64,
+    pub action: MacroAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Macro {
+    pub name: String,
+    pub events: Vec<MacroEvent>,
+}
+
+/// The name becomes a file name in the macro directory, it must not lead out of it.
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(String::from("Macro name must not be empty"));
+    }
+    if name.contains("..") || name.contains(['/', '\\']) {
+        return Err(format!(
+            "Macro name '{}' must not contain path separators or '..'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+impl Macro {
+    pub fn path(name: &str) -> Result<PathBuf> {
+        check_name(name).map_err(|e| eyre!(e))?;
+        Ok(PathBuf::from(MACRO_DIRECTORY).join(format!("{}.toml", name)))
+    }
+
+    pub fn load(name: &str) -> Result<Self> {
+        let path = Self::path(name)?;
+        let content = std::fs::read_to_string(&path)
+            .wrap_err(format!("Macro file {} cannot be read", path.display()))?;
+        toml::from_str(&content).wrap_err(format!("Macro file {} is not valid", path.display()))
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Self::path(&self.name)?;
+        std::fs::create_dir_all(MACRO_DIRECTORY)?;
+        std::fs::write(&path, toml::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+/// Captures the input part of the `JerryMessage` stream received during active sessions.
+pub struct MacroRecorder {
+    recording: Macro,
+    last_event: Instant,
+    active: bool,
+    relative_move: bool,
+}
+
+impl MacroRecorder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            recording: Macro {
+                name: String::from(name),
+                events: Vec::new(),
+            },
+            last_event: Instant::now(),
+            active: false,
+            relative_move: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.recording.name
+    }
+
+    pub fn len(&self) -> usize {
+        self.recording.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recording.events.is_empty()
+    }
+
+    pub fn record(&mut self, msg: &JerryMessage) {
+        let action = match msg {
+            JerryMessage::SessionBegin { relative_move } => {
+                self.active = true;
+                self.relative_move = *relative_move;
+                return;
+            }
+            JerryMessage::SessionEnd => {
+                self.active = false;
+                return;
+            }
+            _ if !self.active => return,
+            JerryMessage::Key(code, state) => MacroAction::Key {
+                code: *code,
+                pressed: *state == State::PRESSED,
+            },
+            JerryMessage::MouseClick(btn, state) => MacroAction::Button {
+                button: btn.value(),
+                pressed: *state == State::PRESSED,
+            },
+            JerryMessage::MouseWheel(dir, amount) => MacroAction::Wheel {
+                direction: dir.value(),
+                amount: *amount,
+            },
+            // absolute positions depend on the server layout, only relative moves are replayable
+            JerryMessage::MouseMove(dx, dy) if self.relative_move => {
+                MacroAction::Move { dx: *dx, dy: *dy }
+            }
+            _ => return,
+        };
+        let now = Instant::now();
+        let delay_ms = match self.recording.events.is_empty() {
+            true => 0,
+            false => now.duration_since(self.last_event).as_millis() as u64,
+        };
+        self.last_event = now;
+        self.recording.events.push(MacroEvent { delay_ms, action });
+    }
+
+    pub fn save(self) -> Result<PathBuf> {
+        self.recording.save()
+    }
+}
+
+/// Recording of a session, shared by the `ContextAwareMessageHandler`s of its connections,
+/// which record the messages as they arrive, and the view starting and stopping it.
+#[derive(Clone, Default)]
+pub struct MacroRecording(Arc<Mutex<Option<MacroRecorder>>>);
+
+impl MacroRecording {
+    /// Replaces the running recording, if any.
+    pub fn start(&self, name: &str) {
+        *self.lock() = Some(MacroRecorder::new(name));
+    }
+
+    /// Returns the recorder to save.
+    pub fn stop(&self) -> Option<MacroRecorder> {
+        self.lock().take()
+    }
+
+    pub fn record(&self, msg: &JerryMessage) {
+        if let Some(recorder) = self.lock().as_mut() {
+            recorder.record(msg);
+        }
+    }
+
+    /// Name and number of events of the running recording
+    pub fn progress(&self) -> Option<(String, usize)> {
+        self.lock()
+            .as_ref()
+            .map(|recorder| (recorder.name().to_owned(), recorder.len()))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<MacroRecorder>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Replays the macro through the emulator.
+///
+/// Keys and buttons still held when the replay ends or is aborted are released.
+pub fn play(recording: &Macro, emulator: &mut dyn Emulator, speed: f64) -> Result<()> {
+    if speed <= 0.0 {
+        return Err(eyre!("Replay speed must be positive"));
+    }
+    info!(
+        "Replaying macro '{}' ({} events) in {}s. Move the cursor to the top left corner to abort.",
+        recording.name,
+        recording.events.len(),
+        REPLAY_COUNTDOWN.as_secs()
+    );
+    std::thread::sleep(REPLAY_COUNTDOWN);
+    replay(recording, emulator, speed)
+}
+
+fn replay(recording: &Macro, emulator: &mut dyn Emulator, speed: f64) -> Result<()> {
+    let mut keys: Vec<u32> = Vec::new();
+    let mut buttons: Vec<Button> = Vec::new();
+    let mut result = Ok(());
+    for event in &recording.events {
+        std::thread::sleep(Duration::from_millis(event.delay_ms).div_f64(speed));
+        if emulator.get_cursor().ok() == Some(FAILSAFE_POSITION) {
+            result = Err(eyre!("Replay aborted by moving the cursor to the corner"));
+            break;
+        }
+        if let Err(e) = replay_action(event.action, emulator, &mut keys, &mut buttons) {
+            warn!("Macro event {:?} not emulated: {}", event.action, e);
+        }
+    }
+    keys.into_iter().for_each(|k| _ = emulator.key_up(k));
+    buttons.into_iter().for_each(|b| _ = emulator.mouse_up(b));
+    if result.is_ok() {
+        info!("Macro '{}' finished", recording.name);
+    }
+    result
+}
+
+fn replay_action(
+    action: MacroAction,
+    emulator: &mut dyn Emulator,
+    keys: &mut Vec<u32>,
+    buttons: &mut Vec<Button>,
+) -> Result<(), EmulationError> {
+    match action {
+        MacroAction::Key {
+            code,
+            pressed: true,
+        } => emulator.key_down(code).map(|_| keys.push(code)),
+        MacroAction::Key {
+            code,
+            pressed: false,
+        } => emulator.key_up(code).map(|_| keys.retain(|k| *k != code)),
+        MacroAction::Button { button, pressed } => {
+            let btn = Button::from_i32(button)
+                .ok_or_else(|| EmulationError::Unsupported(format!("button {}", button)))?;
+            match pressed {
+                true => emulator.mouse_down(btn).map(|_| buttons.push(btn)),
+                false => emulator
+                    .mouse_up(btn)
+                    .map(|_| buttons.retain(|b| *b != btn)),
+            }
+        }
+        MacroAction::Wheel { direction, amount } => {
+            let dir = Direction::from_i32(direction)
+                .ok_or_else(|| EmulationError::Unsupported(format!("direction {}", direction)))?;
+            emulator.mouse_wheel(dir, amount as f32)
+        }
+        MacroAction::Move { dx, dy } => emulator.mouse_move_rel(dx, dy),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::emulator::{EmulatorCall, RecordingEmulator};
+
+    fn key(code: u32, state: State) -> JerryMessage {
+        JerryMessage::Key(code, state)
+    }
+
+    fn event(delay_ms: u64, action: MacroAction) -> MacroEvent {
+        MacroEvent { delay_ms, action }
+    }
+
+    #[test]
+    fn only_input_of_active_sessions_is_recorded() {
+        let mut recorder = MacroRecorder::new("test");
+        recorder.record(&key(0x41, State::PRESSED));
+        recorder.record(&JerryMessage::SessionBegin {
+            relative_move: false,
+        });
+        recorder.record(&key(0x41, State::PRESSED));
+        // absolute moves are not replayable
+        recorder.record(&JerryMessage::MouseMove(100, 100));
+        recorder.record(&JerryMessage::Heartbeat);
+        recorder.record(&JerryMessage::SessionEnd);
+        recorder.record(&key(0x41, State::RELEASED));
+        recorder.record(&JerryMessage::SessionBegin {
+            relative_move: true,
+        });
+        recorder.record(&JerryMessage::MouseMove(-3, 4));
+        recorder.record(&JerryMessage::MouseWheel(Direction::SCROLL_DOWN, 120));
+
+        let actions: Vec<_> = recorder.recording.events.iter().map(|e| e.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                MacroAction::Key {
+                    code: 0x41,
+                    pressed: true
+                },
+                MacroAction::Move { dx: -3, dy: 4 },
+                MacroAction::Wheel {
+                    direction: Direction::SCROLL_DOWN.value(),
+                    amount: 120
+                },
+            ]
+        );
+        assert_eq!(recorder.recording.events[0].delay_ms, 0);
+    }
+
+    #[test]
+    fn delays_are_divided_by_the_speed() {
+        let recording = Macro {
+            name: String::from("test"),
+            events: vec![
+                event(0, MacroAction::Move { dx: 1, dy: 1 }),
+                event(200, MacroAction::Move { dx: 1, dy: 1 }),
+            ],
+        };
+        let mut emulator = RecordingEmulator::with_cursor(100, 100);
+        let log = emulator.log();
+        replay(&recording, &mut emulator, 4.0).unwrap();
+
+        let calls = log.calls();
+        assert_eq!(calls.len(), 2);
+        let delay = calls[1].at - calls[0].at;
+        assert!(delay >= Duration::from_millis(50), "{:?}", delay);
+        assert!(delay < Duration::from_millis(150), "{:?}", delay);
+        assert!(play(&recording, &mut emulator, 0.0).is_err());
+    }
+
+    #[test]
+    fn moving_to_the_corner_aborts_and_releases_the_keys() {
+        let recording = Macro {
+            name: String::from("test"),
+            events: vec![
+                event(
+                    0,
+                    MacroAction::Key {
+                        code: 0x41,
+                        pressed: true,
+                    },
+                ),
+                event(0, MacroAction::Move { dx: -5, dy: -5 }),
+                event(
+                    0,
+                    MacroAction::Key {
+                        code: 0x42,
+                        pressed: true,
+                    },
+                ),
+            ],
+        };
+        let mut emulator = RecordingEmulator::with_cursor(5, 5);
+        let log = emulator.log();
+        assert!(replay(&recording, &mut emulator, 1.0).is_err());
+
+        let calls: Vec<_> = log.calls().into_iter().map(|c| c.call).collect();
+        assert_eq!(
+            calls,
+            vec![
+                EmulatorCall::KeyDown(0x41),
+                EmulatorCall::MouseMoveRel(-5, -5),
+                EmulatorCall::KeyUp(0x41),
+            ]
+        );
+    }
+
+    #[test]
+    fn recording_is_shared() {
+        let recording = MacroRecording::default();
+        let handler_side = recording.clone();
+        handler_side.record(&JerryMessage::SessionBegin {
+            relative_move: false,
+        });
+        assert_eq!(recording.progress(), None);
+
+        recording.start("shared");
+        handler_side.record(&JerryMessage::SessionBegin {
+            relative_move: false,
+        });
+        handler_side.record(&key(0x41, State::PRESSED));
+        assert_eq!(recording.progress(), Some((String::from("shared"), 1)));
+        assert_eq!(recording.stop().map(|r| r.len()), Some(1));
+        assert_eq!(handler_side.progress(), None);
+    }
+
+    #[test]
+    fn names_stay_in_the_macro_directory() {
+        assert_eq!(
+            Macro::path("login").unwrap(),
+            PathBuf::from(MACRO_DIRECTORY).join("login.toml")
+        );
+        for name in ["", "../../x", "a/b", "a\\b", "..", "x..y"] {
+            assert!(Macro::path(name).is_err(), "{:?} accepted", name);
+        }
+    }
+}
@@ -1,30 +1,66 @@
+use super::clipboard::{self, PlatformClipboard, SystemClipboard, Transfer};
 use super::emulator::{Emulator, NoopEmulator};
+use super::macros::MacroRecording;
 use super::proto_in::{Button, Direction, HandshakeResult, State};
 use super::{JerryMessage, JerryResponse};
-use crate::configuration::{ScreenResolution, SessionParams};
+use crate::configuration::{ClipboardRestore, ScreenResolution, SessionParams};
 use crate::emulation::JKey;
+use crate::error::{EmulationError, Rejection};
 use crate::state::Command;
-use arboard::Clipboard;
 use enigo::MouseControllable;
-use std::sync::mpsc::Sender;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
-use tap::TapFallible;
-use tracing::{self, error, info, warn};
+use tracing::{self, info, warn};
+
+/// Buttons in the order of their protobuf values, the indices of `buttons`
+const BUTTONS: [Button; 5] = [
+    Button::LEFT,
+    Button::RIGHT,
+    Button::MIDDLE,
+    Button::XBUTTON1,
+    Button::XBUTTON2,
+];
+/// A wheel event scrolls at most 100 notches, a corrupted amount must not flood the system.
+const MAX_WHEEL_AMOUNT: i32 = 100 * 120;
+
+/// Pauses the emulation of the input of every connection of a session.
+/// While paused the keys and buttons held are still released.
+#[derive(Debug, Clone, Default)]
+pub struct EmulationPause(Arc<AtomicBool>);
+
+impl EmulationPause {
+    pub fn set(&self, paused: bool) {
+        self.0.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
 
 pub struct ContextAwareMessageHandler {
-    transmitter: Sender<Command>,
+    transmitter: SyncSender<Command>,
     session_info: SessionParams,
     cursor: (i32, i32),
     pressed: [bool; 256],
     buttons: [bool; 5],
     state: ClientState,
     emulator: Box<dyn Emulator>,
+    pause: EmulationPause,
+    recording: MacroRecording,
+    clipboard: Box<dyn SystemClipboard>,
     relative_move: bool,
     clipboard_client: Option<String>,
     clipboard_jerry: Option<String>,
     finished: bool,
     session: Instant,
+    /// Events the views were too busy to take
+    dropped: u64,
+    /// Outcome of the handshake, `Err` holds the reason of the rejection
+    handshake: Option<Result<(), Rejection>>,
 }
 #[derive(Clone, Copy, Debug)]
 enum ClientState {
@@ -34,16 +70,34 @@ enum ClientState {
 }
 //
 impl ContextAwareMessageHandler {
-    pub fn new(transmitter: Sender<Command>, session_info: SessionParams) -> Self {
-        let cursor = enigo::Enigo::new().mouse_location();
-        let pressed: [bool; 256] = [false; 256];
-        let buttons: [bool; 5] = [false; 5];
+    pub fn new(transmitter: SyncSender<Command>, session_info: SessionParams) -> Self {
         //=============================================
         let emulator: Box<dyn Emulator> = match session_info.emulate_events {
-            true => Box::new(Self::get_platform_emulator()),
+            true => super::emulator::platform_emulator(),
             false => Box::new(NoopEmulator::new()),
         };
         //=============================================
+        let cursor = enigo::Enigo::new().mouse_location();
+        Self::build(transmitter, session_info, emulator, cursor)
+    }
+    /// Input events are passed to `emulator` whatever `SessionParams::emulate_events` says,
+    /// the initial cursor position is taken from it as well.
+    pub fn with_emulator(
+        transmitter: SyncSender<Command>,
+        session_info: SessionParams,
+        mut emulator: Box<dyn Emulator>,
+    ) -> Self {
+        let cursor = emulator.get_cursor().unwrap_or_default();
+        Self::build(transmitter, session_info, emulator, cursor)
+    }
+    fn build(
+        transmitter: SyncSender<Command>,
+        session_info: SessionParams,
+        emulator: Box<dyn Emulator>,
+        cursor: (i32, i32),
+    ) -> Self {
+        let pressed: [bool; 256] = [false; 256];
+        let buttons: [bool; 5] = [false; 5];
         ContextAwareMessageHandler {
             transmitter,
             session_info,
@@ -51,39 +105,87 @@ impl ContextAwareMessageHandler {
             pressed,
             buttons,
             emulator,
+            pause: EmulationPause::default(),
+            recording: MacroRecording::default(),
+            clipboard: Box::new(PlatformClipboard),
             state: ClientState::None,
             clipboard_client: None,
             clipboard_jerry: None,
             relative_move: false,
             finished: false,
             session: Instant::now(),
+            dropped: 0,
+            handshake: None,
         }
     }
-    #[cfg(target_os = "windows")]
-    fn get_platform_emulator() -> impl Emulator {
-        super::emulator::WindowsImpl::new()
+
+    /// Shares the switch with the controller of the session.
+    pub fn with_pause(mut self, pause: EmulationPause) -> Self {
+        self.pause = pause;
+        self
     }
-    #[cfg(target_os = "linux")]
-    fn get_platform_emulator() -> impl Emulator {
-        super::emulator::LinuxImpl::new()
+
+    /// The received messages are recorded while `recording` runs.
+    pub fn with_recording(mut self, recording: MacroRecording) -> Self {
+        self.recording = recording;
+        self
     }
-    #[cfg(target_os = "macos")]
-    fn get_platform_emulator() -> impl Emulator {
-        super::emulator::MacImpl::new()
+
+    /// Replaces the system clipboard, e.g. by a `MemoryClipboard` in tests.
+    pub fn with_clipboard(mut self, clipboard: Box<dyn SystemClipboard>) -> Self {
+        self.clipboard = clipboard;
+        self
     }
-    fn try_get_clip(&self) -> Option<String> {
-        //thread::sleep(Duration::from_secs(1)); //DEBUGSERVER
-        let ctx = Clipboard::new().tap_err(|e| error!("Clipboard::new() failed {:?}", e));
 
-        match ctx {
-            Ok(mut clip) => clip.get_text().ok(),
-            Err(_) => None,
+    /// Sends an event only the views need, it is dropped while they are behind so that
+    /// a slow view never holds up the emulation. `false` once the views are gone.
+    fn notify(&mut self, command: Command) -> bool {
+        match self.transmitter.try_send(command) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
         }
     }
-    fn set_clipboard(&self, content: String) -> Result<(), ProcessingError> {
-        Clipboard::new()
-            .and_then(|mut a| a.set_text(content))
-            .map_err(|_| ProcessingError::FailedToProcess)
+
+    fn try_get_clip(&mut self) -> Option<String> {
+        //thread::sleep(Duration::from_secs(1)); //DEBUGSERVER
+        self.clipboard.get_text()
+    }
+    fn set_clipboard(&mut self, content: String) -> Result<(), ProcessingError> {
+        self.clipboard
+            .set_text(content)
+            .map_err(ProcessingError::Clipboard)
+    }
+    fn end_session(&mut self) {
+        self.state = ClientState::Inactive;
+        self.recover();
+        self.clipboard_jerry = None;
+        self.apply_clipboard_policy();
+    }
+    fn apply_clipboard_policy(&mut self) {
+        let Some(previous) = self.clipboard_client.take() else {
+            return;
+        };
+        let result = match self.session_info.clipboard_restore {
+            ClipboardRestore::Keep => Ok(()),
+            ClipboardRestore::Restore => self.set_clipboard(previous),
+            ClipboardRestore::Merge => {
+                let current = self.try_get_clip().unwrap_or_default();
+                for content in clipboard::merge(&previous, &current) {
+                    self.notify(Command::ClipboardKept(content));
+                }
+                self.set_clipboard(previous)
+            }
+        };
+        if result.is_err() {
+            warn!(
+                "Clipboard policy {:?} could not be applied",
+                self.session_info.clipboard_restore
+            );
+        }
     }
     fn clear_state(&mut self, relative: bool) -> bool {
         self.relative_move = relative;
@@ -104,19 +206,12 @@ impl ContextAwareMessageHandler {
 
         self.buttons
             .into_iter()
-            .enumerate()
-            .filter(|(_, v)| *v)
-            .for_each(|(i, _)| self.inject_release_button(self.get_mouse_button(i)));
-    }
-    fn get_mouse_button(&self, value: usize) -> Button {
-        match value {
-            0 => Button::LEFT,
-            1 => Button::RIGHT,
-            2 => Button::MIDDLE,
-            3 => Button::XBUTTON1,
-            4 => Button::XBUTTON2,
-            _ => panic!("Unknown usize value for proto_rs::Button"),
-        }
+            .zip(BUTTONS)
+            .filter(|(v, _)| *v)
+            .for_each(|(_, button)| self.inject_release_button(button));
+        // released once, a later disconnection must not release them again
+        self.pressed = [false; 256];
+        self.buttons = [false; 5];
     }
     fn inject_release(&mut self, code: u32) {
         let key = JKey::from(code as u8);
@@ -146,6 +241,7 @@ impl ContextAwareMessageHandler {
         thread::sleep(Duration::from_millis(1));
     }
     fn key_down(&mut self, key: u32) -> Result<(), ProcessingError> {
+        self.check_paused()?;
         match self.state {
             ClientState::Active => {
                 let key_u = key as usize;
@@ -153,6 +249,7 @@ impl ContextAwareMessageHandler {
                     return self
                         .emulator
                         .key_down(key)
+                        .map_err(ProcessingError::from)
                         .map(|_| self.pressed[key as usize] = true);
                 }
                 Err(ProcessingError::UnexpectedMessageDiscarded)
@@ -166,6 +263,7 @@ impl ContextAwareMessageHandler {
             Some(true) => self
                 .emulator
                 .key_up(key)
+                .map_err(ProcessingError::from)
                 .map(|_| self.pressed[key_u] = false),
             Some(false) => Err(ProcessingError::UnexpectedMessageDiscarded),
             None => Err(ProcessingError::UnexpectedMessageDiscarded),
@@ -173,10 +271,12 @@ impl ContextAwareMessageHandler {
     }
 
     fn mouse_down(&mut self, btn: Button) -> Result<(), ProcessingError> {
+        self.check_paused()?;
         match self.state {
             ClientState::Active => self
                 .emulator
                 .mouse_down(btn)
+                .map_err(ProcessingError::from)
                 .map(|_| self.buttons[btn as usize] = true),
             _ => Err(ProcessingError::UnexpectedMessageDiscarded),
         }
@@ -188,31 +288,45 @@ impl ContextAwareMessageHandler {
             Some(true) => self
                 .emulator
                 .mouse_up(btn)
+                .map_err(ProcessingError::from)
                 .map(|_| self.buttons[button] = false),
             Some(false) => Err(ProcessingError::UnexpectedMessageDiscarded),
             None => Err(ProcessingError::UnexpectedMessageDiscarded),
         }
     }
     fn mouse_move(&mut self, x: i32, y: i32) -> Result<(), ProcessingError> {
+        self.check_paused()?;
         match (self.state, self.relative_move) {
-            (ClientState::Active, true) => self.emulator.mouse_move_rel(x, y),
-            (ClientState::Active, false) => self.emulator.mouse_move_to(x, y),
+            (ClientState::Active, true) => Ok(self.emulator.mouse_move_rel(x, y)?),
+            (ClientState::Active, false) => Ok(self.emulator.mouse_move_to(x, y)?),
             (_, _) => Err(ProcessingError::UnexpectedMessageDiscarded),
         }
     }
 
     fn mouse_wheel(&mut self, direction: Direction, amount: i32) -> Result<(), ProcessingError> {
+        self.check_paused()?;
         match self.state {
-            ClientState::Active => self.emulator.mouse_wheel(direction, amount as f32),
+            ClientState::Active => {
+                let amount = amount.clamp(-MAX_WHEEL_AMOUNT, MAX_WHEEL_AMOUNT);
+                Ok(self.emulator.mouse_wheel(direction, amount as f32)?)
+            }
             _ => Err(ProcessingError::UnexpectedMessageDiscarded),
         }
     }
 
-    fn get_response(&self, request: &super::Request) -> Result<JerryResponse, ProcessingError> {
+    /// Only the releases of the keys and buttons held get through while paused.
+    fn check_paused(&self) -> Result<(), ProcessingError> {
+        match self.pause.is_paused() {
+            true => Err(ProcessingError::Paused),
+            false => Ok(()),
+        }
+    }
+
+    fn get_response(&mut self, request: &super::Request) -> Result<JerryResponse, ProcessingError> {
         match request {
             super::Request::INIT_INFO => {
                 //thread::sleep(Duration::from_secs(30)); //DEBUGSERVER
-                Ok(JerryResponse::InitInfo(self.session_info.clone()))
+                Ok(JerryResponse::InitInfo(Box::new(self.session_info.clone())))
             }
             super::Request::CLIPBOARD => {
                 if self.session.elapsed() < Duration::from_millis(500) {
@@ -220,19 +334,33 @@ impl ContextAwareMessageHandler {
                     return Ok(JerryResponse::NoResponse(String::from("")));
                 }
                 match self.try_get_clip() {
-                    Some(new_content) => {
-                        info!("Clipboard content: \t\tLength: {}", new_content.len());
-                        Ok(JerryResponse::Clipboard(new_content, false))
-                    }
+                    Some(new_content) => match self.session_info.clipboard_filter.check(
+                        Transfer::Outgoing,
+                        &new_content,
+                        false,
+                    ) {
+                        Ok(()) => {
+                            info!("Clipboard content: \t\tLength: {}", new_content.len());
+                            self.notify(Command::ClipboardTransfer(
+                                Transfer::Outgoing,
+                                new_content.clone(),
+                            ));
+                            Ok(JerryResponse::Clipboard(new_content, false))
+                        }
+                        Err(reason) => {
+                            warn!("Outgoing clipboard blocked: {}", reason);
+                            Ok(JerryResponse::NoResponse(reason))
+                        }
+                    },
                     None => Ok(JerryResponse::NoResponse(String::from(""))),
                 }
             }
             super::Request::MOUSE_POSITION => {
-                match self.session_info.monitor {
-                    ScreenResolution::Static(_) => {}
-                    ScreenResolution::Dynamic => todo!(),
-                }
-                Ok(JerryResponse::Cursor(self.cursor.0, self.cursor.1))
+                let (x, y) = match self.session_info.monitor {
+                    ScreenResolution::Static(_) => self.cursor,
+                    ScreenResolution::Dynamic => self.emulator.get_cursor().unwrap_or(self.cursor),
+                };
+                Ok(JerryResponse::Cursor(x, y))
             }
         }
     }
@@ -242,7 +370,25 @@ impl super::MessageConsumer for ContextAwareMessageHandler {
     fn finished(&self) -> bool {
         self.finished
     }
+    fn handshake(&self) -> Option<&Result<(), Rejection>> {
+        self.handshake.as_ref()
+    }
+    fn disconnected(&mut self) {
+        match self.state {
+            ClientState::Active => {
+                info!("Connection lost during an active session");
+                self.end_session();
+            }
+            _ => self.recover(),
+        }
+        self.state = ClientState::None;
+        if self.dropped > 0 {
+            info!("{} events not shown, the views were behind", self.dropped);
+            self.dropped = 0;
+        }
+    }
     fn consume(&mut self, msg: JerryMessage) -> Option<JerryResponse> {
+        self.recording.record(&msg);
         let (response, result) = match &msg {
             JerryMessage::MouseMove(x, y) => (None, self.mouse_move(*x, *y)),
             JerryMessage::Key(key, State::PRESSED) => (None, self.key_down(*key)),
@@ -266,27 +412,24 @@ impl super::MessageConsumer for ContextAwareMessageHandler {
             }
             JerryMessage::SessionEnd => match self.state {
                 ClientState::Active => {
-                    self.state = ClientState::Inactive;
-                    self.recover();
-                    self.clipboard_jerry = None;
-                    if let Some(text) = self.clipboard_client.clone() {
-                        let _res = self.set_clipboard(text);
-                    }
+                    self.end_session();
                     (None, Ok(()))
                 }
                 _ => (None, Err(ProcessingError::UnexpectedMessageDiscarded)),
             },
             JerryMessage::Handshake(res, mess) => {
                 if *res == HandshakeResult::Rejection {
+                    let rejection = Rejection::from_message(mess);
                     self.transmitter
                         .send(Command::ConnectionResult(
-                            crate::connection::ConnectionState::HandshakeFailed(mess.clone()),
+                            crate::connection::ConnectionState::HandshakeFailed(rejection.clone()),
                         ))
                         .unwrap_or_else(|_| self.recover());
-                    self.transmitter
-                        .send(Command::Halt)
-                        .unwrap_or_else(|_| self.recover());
+                    // the connection worker decides whether to retry
+                    self.handshake = Some(Err(rejection));
+                    self.finished = true;
                 } else {
+                    self.handshake = Some(Ok(()));
                     self.transmitter
                         .send(Command::ConnectionResult(
                             crate::connection::ConnectionState::HandshakeSuccess(mess.clone()),
@@ -296,38 +439,37 @@ impl super::MessageConsumer for ContextAwareMessageHandler {
                 (None, Ok(()))
             }
             JerryMessage::Clipboard(content, file) => {
-                if !file {
+                let allowed =
+                    self.session_info
+                        .clipboard_filter
+                        .check(Transfer::Incoming, content, *file);
+                if let Err(reason) = allowed {
+                    // the server reads a response only after a request of its own,
+                    // an answer here would be taken for the answer to the next one
+                    warn!("Incoming clipboard blocked: {}", reason);
+                    (None, Ok(()))
+                } else if !file {
                     self.clipboard_jerry = Some(content.clone());
-                    let a = Clipboard::new()
-                        .and_then(|mut a| a.set_text(content))
-                        .map_err(|_e| ProcessingError::FailedToProcess);
-                    (None, a)
+                    self.notify(Command::ClipboardTransfer(
+                        Transfer::Incoming,
+                        content.clone(),
+                    ));
+                    (None, self.set_clipboard(content.clone()))
                 } else {
                     (None, Err(ProcessingError::UnexpectedMessageDiscarded))
                 }
             }
             JerryMessage::Heartbeat => (None, Ok(())),
         };
-        if let Err(_ee) = self.transmitter.send(Command::Message(msg)) {
-            //.unwrap_or_else(|_| self.recover())
+        if !self.notify(Command::Message(msg)) {
             self.recover();
             self.finished = true;
         };
         if let Err(e) = result {
             match e {
-                ProcessingError::UnexpectedMessageDiscarded => {}
-                ProcessingError::UnableToProcess => warn!(
-                    "Emulation failure: Unable to emulate input based on provided data. {:?}",
-                    e
-                ),
-                ProcessingError::UnableToProcessPlatformSpecific(description) => warn!(
-                    "Emulation failure: Unable to emulate input based on provided data. {:?}",
-                    description
-                ),
-                ProcessingError::FailedToProcess => warn!(
-                    "Emulation process error: Action failed to execute emulation function. {:?}",
-                    e
-                ),
+                ProcessingError::UnexpectedMessageDiscarded | ProcessingError::Paused => {}
+                ProcessingError::Emulation(e) => warn!("Emulation failure: {}", e),
+                ProcessingError::Clipboard(e) => warn!("Clipboard not set: {}", e),
             }
         }
 
@@ -335,11 +477,18 @@ impl super::MessageConsumer for ContextAwareMessageHandler {
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum ProcessingError {
+    /// The message does not fit the state of the session
     UnexpectedMessageDiscarded,
-    UnableToProcess,
-    UnableToProcessPlatformSpecific(String),
-    FailedToProcess,
+    /// The emulation is paused by the controller of the session
+    Paused,
+    Emulation(EmulationError),
+    Clipboard(arboard::Error),
+}
+
+impl From<EmulationError> for ProcessingError {
+    fn from(e: EmulationError) -> Self {
+        ProcessingError::Emulation(e)
+    }
 }
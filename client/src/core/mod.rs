@@ -1,22 +1,42 @@
+pub mod clipboard;
 pub mod emulator;
+pub mod macros;
 
 pub mod message_handler;
+pub mod replay;
 pub use crate::state::Command;
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
 //========================
 //   CORE mod.rs
 //========================
 
+/// Handles the messages of one connection, created anew for each connection.
 pub trait MessageConsumer {
     fn consume(&mut self, msg: JerryMessage) -> Option<JerryResponse>;
     fn finished(&self) -> bool;
+    /// Called once the connection is lost, before the consumer is dropped.
+    fn disconnected(&mut self);
+    /// Outcome of the handshake, `Err` holds the reason of the rejection.
+    /// Drives the reconnection policy, `None` if no handshake was received.
+    fn handshake(&self) -> Option<&Result<(), Rejection>> {
+        None
+    }
 }
 
+/// Creates the consumer of a connection on the emulation thread,
+/// from the sender of the view events and the parameters of the server.
+pub type ConsumerFactory =
+    Arc<dyn Fn(SyncSender<Command>, SessionParams) -> Box<dyn MessageConsumer> + Send + Sync>;
+
 use crate::configuration::SessionParams;
+use crate::error::Rejection;
 pub use crate::proto_rs::request_master::Button;
 pub use crate::proto_rs::request_master::Direction;
 pub use crate::proto_rs::request_master::Echo;
 pub use crate::proto_rs::request_master::Request;
 pub use crate::proto_rs::request_master::State;
+pub use crate::proto_rs::response_slave::Goodbye_Cause as GoodbyeCause;
 
 pub use crate::proto_rs::*;
 
@@ -42,7 +62,8 @@ pub enum JerryMessage {
 #[derive(Debug)]
 pub enum JerryResponse {
     Cursor(i32, i32),
-    InitInfo(SessionParams),
+    InitInfo(Box<SessionParams>),
     Clipboard(String, bool),
     NoResponse(String),
+    Goodbye(GoodbyeCause, String),
 }
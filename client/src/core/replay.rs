@@ -0,0 +1,406 @@
+use super::emulator::{CallLog, RecordingEmulator};
+use super::message_handler::ContextAwareMessageHandler;
+use super::{Command, JerryMessage, MessageConsumer};
+use crate::configuration::{ClipboardRestore, ReconnectPolicy, ScreenResolution, SessionParams};
+use crate::connection::ServerAddress;
+use crate::emulation::key_name;
+use crate::proto_rs::proto_in::MasterMessage_oneof_action as MsgType;
+use crate::proto_rs::proto_out::SlaveMessage_oneof_response as Response;
+use crate::proto_rs::{ProtoInMsg, ProtoOutMsg};
+use crate::serialization::capture::{Entry, Record};
+use crate::serialization::compression::ClipboardCodec;
+use crate::DisplayMode;
+use eyre::{eyre, Result};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Longest sleep between two checks of the stop flag
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Kinds of the messages sent by the server, used to filter a replay.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Move,
+    Key,
+    Click,
+    Wheel,
+    Clipboard,
+    /// Session begin and end
+    Session,
+    Request,
+    Handshake,
+    /// Heartbeats, pings and pongs
+    Heartbeat,
+}
+
+impl MessageKind {
+    pub fn of(msg: &ProtoInMsg) -> Option<MessageKind> {
+        let kind = match msg.action.as_ref()? {
+            MsgType::mouse_position(_) => MessageKind::Move,
+            MsgType::keyboard(_) => MessageKind::Key,
+            MsgType::mouse_click(_) => MessageKind::Click,
+            MsgType::mouse_wheel(_) => MessageKind::Wheel,
+            MsgType::clipboard(_) => MessageKind::Clipboard,
+            MsgType::start_session(_) | MsgType::end_session(_) => MessageKind::Session,
+            MsgType::request(_) => MessageKind::Request,
+            MsgType::handshake(_) => MessageKind::Handshake,
+            MsgType::heartbeat(_) | MsgType::pong(_) => MessageKind::Heartbeat,
+        };
+        Some(kind)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Replay speed multiplier
+    pub speed: f64,
+    /// Print the messages and the emulator calls instead of emulating them
+    pub dry_run: bool,
+    /// Kinds of messages replayed, all of them if empty.
+    /// Sessions are always replayed, input outside of a session is discarded.
+    pub only: Vec<MessageKind>,
+}
+
+impl ReplayOptions {
+    fn selected(&self, kind: MessageKind) -> bool {
+        self.only.is_empty() || kind == MessageKind::Session || self.only.contains(&kind)
+    }
+
+    /// Requests are answered by the real server session only, the answer is in the
+    /// capture. A dry run leaves the clipboard alone.
+    fn forwarded(&self, kind: MessageKind) -> bool {
+        match kind {
+            MessageKind::Request | MessageKind::Heartbeat => false,
+            MessageKind::Clipboard => !self.dry_run,
+            _ => true,
+        }
+    }
+}
+
+/// Pushes the messages of a capture through the message handler and the emulator,
+/// one handler per captured connection, with the delays of the capture.
+///
+/// A dry run prints every message with the emulator calls it causes to `out` and
+/// does not wait. Keys and buttons still held at the end are released.
+pub fn replay(
+    records: impl Iterator<Item = Result<Record>>,
+    options: &ReplayOptions,
+    out: &mut dyn Write,
+    stop: &AtomicBool,
+) -> Result<()> {
+    if options.speed <= 0.0 {
+        return Err(eyre!("Replay speed must be positive"));
+    }
+    if !options.dry_run {
+        info!(
+            "Replaying the capture in {}s, press Ctrl+C to abort.",
+            super::macros::REPLAY_COUNTDOWN.as_secs()
+        );
+        std::thread::sleep(super::macros::REPLAY_COUNTDOWN);
+    }
+    let mut replay = Replay::new(options, out);
+    let mut result = Ok(());
+    for record in records {
+        if stop.load(Ordering::Relaxed) {
+            result = Err(eyre!("Replay aborted"));
+            break;
+        }
+        match record {
+            Ok(record) => replay.record(record, stop)?,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+    replay.disconnect()?;
+    info!("{} messages replayed", replay.replayed);
+    result
+}
+
+struct Replay<'a> {
+    options: &'a ReplayOptions,
+    out: &'a mut dyn Write,
+    handler: Option<Box<dyn MessageConsumer>>,
+    /// Calls of the emulator of a dry run
+    log: Option<CallLog>,
+    events: Option<Receiver<Command>>,
+    codec: ClipboardCodec,
+    /// Start of the replay and time of the first message in the capture
+    start: Option<(Instant, Duration)>,
+    replayed: usize,
+}
+
+impl<'a> Replay<'a> {
+    fn new(options: &'a ReplayOptions, out: &'a mut dyn Write) -> Self {
+        Self {
+            options,
+            out,
+            handler: None,
+            log: None,
+            events: None,
+            codec: ClipboardCodec::new(),
+            start: None,
+            replayed: 0,
+        }
+    }
+
+    fn record(&mut self, record: Record, stop: &AtomicBool) -> Result<()> {
+        match record.entry {
+            Entry::Connected => {
+                self.disconnect()?;
+                if self.options.dry_run {
+                    writeln!(self.out, "{} connected", timestamp(record.at))?;
+                }
+                self.connect();
+            }
+            Entry::Received(mut msg) => {
+                let Some(kind) = MessageKind::of(&msg) else {
+                    return Ok(());
+                };
+                if !self.options.selected(kind) {
+                    return Ok(());
+                }
+                if !self.options.dry_run {
+                    self.wait(record.at, stop);
+                }
+                if let Err(e) = self.codec.decode(&mut msg) {
+                    warn!("Message discarded: {}", e);
+                    return Ok(());
+                }
+                let msg = JerryMessage::from(msg);
+                if self.options.dry_run {
+                    writeln!(self.out, "{} > {}", timestamp(record.at), describe(&msg))?;
+                }
+                if self.options.forwarded(kind) {
+                    if self.handler.is_none() {
+                        // captures start with a connection, handmade ones may not
+                        self.connect();
+                    }
+                    if let Some(handler) = self.handler.as_mut() {
+                        handler.consume(msg);
+                    }
+                    self.replayed += 1;
+                    self.flush_calls()?;
+                }
+            }
+            Entry::Sent(msg) => {
+                let heartbeat = matches!(msg.response, Some(Response::ping(_) | Response::pong(_)));
+                let shown = !heartbeat || self.options.selected(MessageKind::Heartbeat);
+                if self.options.dry_run && shown {
+                    writeln!(
+                        self.out,
+                        "{} < {}",
+                        timestamp(record.at),
+                        describe_sent(&msg)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn connect(&mut self) {
+        let (transmitter, events) = mpsc::sync_channel(1024);
+        let params = replay_params(!self.options.dry_run);
+        let handler = match self.options.dry_run {
+            true => {
+                let emulator = RecordingEmulator::new();
+                self.log = Some(emulator.log());
+                ContextAwareMessageHandler::with_emulator(transmitter, params, Box::new(emulator))
+            }
+            false => ContextAwareMessageHandler::new(transmitter, params),
+        };
+        self.handler = Some(Box::new(handler));
+        self.events = Some(events);
+        self.codec = ClipboardCodec::new();
+    }
+
+    /// Releases the keys and buttons still held.
+    fn disconnect(&mut self) -> Result<()> {
+        if let Some(mut handler) = self.handler.take() {
+            handler.disconnected();
+            self.flush_calls()?;
+        }
+        Ok(())
+    }
+
+    fn flush_calls(&mut self) -> Result<()> {
+        // nobody displays the events, the handler stops once the channel is full
+        if let Some(events) = self.events.as_ref() {
+            while events.try_recv().is_ok() {}
+        }
+        if let Some(log) = self.log.as_ref() {
+            for recorded in log.take() {
+                writeln!(self.out, "{:>12} {}", "", recorded.call)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sleeps until the message is due, relative to the first one.
+    fn wait(&mut self, at: Duration, stop: &AtomicBool) {
+        let (start, first) = *self.start.get_or_insert((Instant::now(), at));
+        let due = start + at.saturating_sub(first).div_f64(self.options.speed);
+        while !stop.load(Ordering::Relaxed) {
+            let remaining = due.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            std::thread::sleep(remaining.min(STOP_CHECK_INTERVAL));
+        }
+    }
+}
+
+/// The clipboard is left as the replay found it, input is emulated only if `emulate`.
+fn replay_params(emulate: bool) -> SessionParams {
+    SessionParams {
+        client_name: String::from("replay"),
+        client_guid: String::new(),
+        server_password: String::new(),
+        monitor: ScreenResolution::Dynamic,
+        server_name: String::from("capture"),
+        address: ServerAddress {
+            host: String::from("127.0.0.1"),
+            port: crate::DEFAULT_PORT,
+        },
+        reverse: false,
+        display_mode: DisplayMode::Logging,
+        emulate_events: emulate,
+        clipboard_restore: ClipboardRestore::Keep,
+        clipboard_filter: Default::default(),
+        reconnect: ReconnectPolicy::default(),
+    }
+}
+
+fn timestamp(at: Duration) -> String {
+    format!("{:>11.3}s", at.as_secs_f64())
+}
+
+/// Keys are shown by name.
+pub fn describe(msg: &JerryMessage) -> String {
+    match msg {
+        JerryMessage::Key(code, state) => format!("Key({}, {:?})", key_name(*code), state),
+        JerryMessage::Clipboard(content, file) => {
+            format!(
+                "Clipboard({} chars, file: {})",
+                content.chars().count(),
+                file
+            )
+        }
+        msg => format!("{:?}", msg),
+    }
+}
+
+pub fn describe_sent(msg: &ProtoOutMsg) -> String {
+    match msg.response.as_ref() {
+        Some(Response::clipboard_session(clip)) => {
+            format!("clipboard_session({} bytes)", clip.message.len())
+        }
+        Some(response) => format!("{:?}", response),
+        None => String::from("empty"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Button, JerryResponse, State};
+
+    fn at(ms: u64, entry: Entry) -> Result<Record> {
+        Ok(Record {
+            at: Duration::from_millis(ms),
+            entry,
+        })
+    }
+
+    fn received(msg: JerryMessage) -> Entry {
+        Entry::Received(ProtoInMsg::from(msg))
+    }
+
+    fn capture() -> Vec<Result<Record>> {
+        vec![
+            at(0, Entry::Connected),
+            at(
+                1,
+                received(JerryMessage::Request(super::super::Request::MOUSE_POSITION)),
+            ),
+            at(
+                2,
+                Entry::Sent(ProtoOutMsg::from(JerryResponse::Cursor(5, 6))),
+            ),
+            at(
+                10,
+                received(JerryMessage::SessionBegin {
+                    relative_move: true,
+                }),
+            ),
+            at(11, received(JerryMessage::MouseMove(3, -1))),
+            at(12, received(JerryMessage::Key(0x41, State::PRESSED))),
+            at(
+                13,
+                received(JerryMessage::MouseClick(Button::LEFT, State::PRESSED)),
+            ),
+            // the connection was lost while the keys were held
+            at(20, Entry::Connected),
+            at(21, received(JerryMessage::Heartbeat)),
+        ]
+    }
+
+    fn dry_run(only: Vec<MessageKind>) -> String {
+        let options = ReplayOptions {
+            speed: 1.0,
+            dry_run: true,
+            only,
+        };
+        let mut out = Vec::new();
+        replay(
+            capture().into_iter(),
+            &options,
+            &mut out,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn dry_run_prints_messages_and_emulator_calls() {
+        let lines: Vec<String> = dry_run(Vec::new())
+            .lines()
+            .map(|line| line.trim().to_owned())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "0.000s connected",
+                "0.001s > Request(MOUSE_POSITION)",
+                "0.002s < cursor(x: 5 y: 6)",
+                "0.010s > SessionBegin { relative_move: true }",
+                "0.011s > MouseMove(3, -1)",
+                "mouse_move_rel(3, -1)",
+                "0.012s > Key(A, PRESSED)",
+                "key_down(A)",
+                "0.013s > MouseClick(LEFT, PRESSED)",
+                "mouse_down(LEFT)",
+                "key_up(A)",
+                "mouse_up(LEFT)",
+                "0.020s connected",
+                "0.021s > Heartbeat",
+            ]
+        );
+    }
+
+    #[test]
+    fn filtered_kinds_are_skipped() {
+        let output = dry_run(vec![MessageKind::Key]);
+        assert!(output.contains("key_down(A)"));
+        assert!(output.contains("SessionBegin"));
+        assert!(!output.contains("MouseMove"));
+        assert!(!output.contains("MouseClick"));
+        assert!(!output.contains("Heartbeat"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Running the client as a service, without a terminal (`--daemon`).
+//!
+//! The service manager is told when the client is ready through the `sd_notify`
+//! protocol, and the log written to the standard error carries the priority
+//! prefixes journald understands.
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+
+/// The client has started and connects to the server.
+pub const READY: &str = "READY=1";
+/// The client is closing the connection to the server.
+pub const STOPPING: &str = "STOPPING=1";
+
+/// Sends the state to the service manager that started the client with `Type=notify`.
+/// Returns `false` if the client was not started by one.
+pub fn notify(state: &str) -> io::Result<bool> {
+    match std::env::var_os("NOTIFY_SOCKET") {
+        Some(socket) => notify_socket(&socket, state).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// `socket` is a path or, if it starts with `@`, the name of an abstract socket.
+#[cfg(unix)]
+fn notify_socket(socket: &OsStr, state: &str) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+    let datagram = UnixDatagram::unbound()?;
+    match socket.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            datagram.send_to_addr(state.as_bytes(), &address)?;
+        }
+        _ => {
+            datagram.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn notify_socket(_socket: &OsStr, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "sd_notify needs Unix sockets",
+    ))
+}
+
+/// Log lines as `<priority>target: message`, the time is added by journald.
+pub struct JournalFormat;
+
+impl<S, N> FormatEvent<S, N> for JournalFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        write!(
+            writer,
+            "<{}>{}: ",
+            priority(metadata.level()),
+            metadata.target()
+        )?;
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+/// Syslog priority of the level
+fn priority(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn state_is_sent_to_the_notify_socket() {
+        let path = std::env::temp_dir().join(format!("jerry-notify-{}", std::process::id()));
+        _ = std::fs::remove_file(&path);
+        let manager = UnixDatagram::bind(&path).unwrap();
+
+        notify_socket(path.as_os_str(), READY).unwrap();
+        let mut buffer = [0; 64];
+        let received = manager.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..received], b"READY=1");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn levels_map_to_syslog_priorities() {
+        let priorities: Vec<_> = [Level::ERROR, Level::WARN, Level::INFO, Level::TRACE]
+            .iter()
+            .map(priority)
+            .collect();
+        assert_eq!(priorities, vec![3, 4, 6, 7]);
+    }
+}
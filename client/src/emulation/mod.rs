@@ -285,3 +285,11 @@ impl From<u8> for JKey {
         }
     }
 }
+
+/// Name of the key with the virtual key code sent by the server, codes out of range as they are.
+pub fn key_name(code: u32) -> String {
+    match u8::try_from(code) {
+        Ok(code) => format!("{:?}", JKey::from(code)),
+        Err(_) => code.to_string(),
+    }
+}
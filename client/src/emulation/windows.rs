@@ -152,9 +152,9 @@ pub fn raw_relative_move_px(dx: &i32, dy: &i32) -> Result<()> {
 }
 const ACC_INV: [i32; 16] = [0, 1, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9];
 fn reverse_acceleration_curve(px: i32) -> i32 {
-    let dist = match px.abs() {
+    let dist = match px.unsigned_abs() {
         i if i < 16 => ACC_INV[i as usize],
-        n => 6 + (n / 4),
+        n => 6 + (n / 4) as i32,
     };
     dist * px.signum()
 }
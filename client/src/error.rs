@@ -0,0 +1,334 @@
+//! Errors of the connection to a server and of the emulation.
+//!
+//! ```text
+//! ConnectionError
+//! ├── Transport   the TCP connection: connect, closed, timeout, read, write
+//! ├── Crypto      the key exchange, the proof of the peer of a reverse connection
+//! ├── Protocol    framing and protobuf decoding
+//! └── Handshake   the server rejected the client (`Rejection`)
+//! EmulationError  the input could not be emulated on this computer
+//! ```
+//!
+//! The errors keep their source, `std::error::Error::source` walks the chain.
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Boxed source of an error, shared by the clones of a `ConnectionState`.
+/// Two sources are equal if their messages are.
+#[derive(Debug, Clone)]
+pub struct ErrorSource(Arc<dyn Error + Send + Sync>);
+
+impl ErrorSource {
+    pub fn new(source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self(Arc::from(source.into()))
+    }
+
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+
+    /// Kind of the I/O error, `None` if the source is not one.
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        self.downcast_ref::<io::Error>().map(io::Error::kind)
+    }
+}
+
+impl fmt::Display for ErrorSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Error for ErrorSource {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl PartialEq for ErrorSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_string() == other.to_string()
+    }
+}
+
+impl From<io::Error> for ErrorSource {
+    fn from(e: io::Error) -> Self {
+        Self::new(e)
+    }
+}
+
+/// Failure of a connection, from the TCP connection to the end of the session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionError {
+    Transport(TransportError),
+    Crypto(CryptoError),
+    Protocol(ProtocolError),
+    Handshake(Rejection),
+    /// A task of the session panicked
+    Internal(ErrorSource),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportError {
+    /// No connection to the server could be established
+    Connect(ErrorSource),
+    /// The server closed the connection
+    Closed,
+    /// Nothing was received from the server for the duration
+    Timeout(Duration),
+    Read(ErrorSource),
+    Write(ErrorSource),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CryptoError {
+    /// The keys could not be agreed on, the connection was lost during the exchange
+    KeyExchange(ErrorSource),
+    /// The peer of a reverse connection did not prove that it knows the password
+    PeerNotAuthenticated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    /// The length prefix of a message exceeds the limit
+    MessageTooLarge(u64),
+    MalformedLength,
+    Decode(ErrorSource),
+    Encode(ErrorSource),
+}
+
+/// Why the server rejected the client, as reported in the handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    WrongPassword,
+    /// The client info was not sent in time
+    InitInfoMissing,
+    KeyExchangeFailed,
+    /// Any other reason, e.g. an unexpected resolution, with the message of the server
+    Other(String),
+}
+
+impl Rejection {
+    /// The server reports the reason as a message only.
+    pub fn from_message(message: &str) -> Self {
+        match message {
+            "Password rejected" => Rejection::WrongPassword,
+            "Init message not received within timeout" => Rejection::InitInfoMissing,
+            "Key exchange failed" => Rejection::KeyExchangeFailed,
+            other => Rejection::Other(other.to_owned()),
+        }
+    }
+}
+
+/// The input could not be emulated.
+#[derive(Debug)]
+pub enum EmulationError {
+    /// The key, button or direction has no equivalent on this platform
+    Unsupported(String),
+    /// The display server cannot be reached
+    DisplayUnavailable,
+    /// The input API of the platform failed
+    Backend(Box<dyn Error + Send + Sync>),
+}
+
+impl EmulationError {
+    pub fn backend(source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        EmulationError::Backend(source.into())
+    }
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionError::Transport(e) => e.fmt(f),
+            ConnectionError::Crypto(e) => e.fmt(f),
+            ConnectionError::Protocol(e) => write!(f, "Protocol error: {}", e),
+            ConnectionError::Handshake(rejection) => write!(f, "Handshake failed ({})", rejection),
+            ConnectionError::Internal(e) => write!(f, "Internal error: {}", e),
+        }
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransportError::Connect(e) => write!(f, "Connection failed: {}", e),
+            TransportError::Closed => write!(f, "Connection closed by the server"),
+            TransportError::Timeout(duration) => {
+                write!(f, "No message received for {:?}", duration)
+            }
+            TransportError::Read(e) => write!(f, "Read error: {}", e),
+            TransportError::Write(e) => write!(f, "Write error: {}", e),
+        }
+    }
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CryptoError::KeyExchange(e) => write!(f, "Key exchange failed: {}", e),
+            CryptoError::PeerNotAuthenticated => write!(f, "The peer does not know the password"),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::MessageTooLarge(length) => {
+                write!(f, "Message too large: {} bytes", length)
+            }
+            ProtocolError::MalformedLength => write!(f, "Malformed message length"),
+            ProtocolError::Decode(e) => write!(f, "Message not decoded: {}", e),
+            ProtocolError::Encode(e) => write!(f, "Message not encoded: {}", e),
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rejection::WrongPassword => write!(f, "Password rejected"),
+            Rejection::InitInfoMissing => write!(f, "Client info not received in time"),
+            Rejection::KeyExchangeFailed => write!(f, "Key exchange failed"),
+            Rejection::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl fmt::Display for EmulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmulationError::Unsupported(what) => write!(f, "Not supported: {}", what),
+            EmulationError::DisplayUnavailable => write!(f, "Display unavailable"),
+            EmulationError::Backend(e) => write!(f, "Emulation failed: {}", e),
+        }
+    }
+}
+
+impl Error for ConnectionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConnectionError::Transport(e) => Some(e),
+            ConnectionError::Crypto(e) => Some(e),
+            ConnectionError::Protocol(e) => Some(e),
+            ConnectionError::Handshake(e) => Some(e),
+            ConnectionError::Internal(e) => Some(e),
+        }
+    }
+}
+
+impl Error for TransportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TransportError::Connect(e) | TransportError::Read(e) | TransportError::Write(e) => {
+                Some(e)
+            }
+            TransportError::Closed | TransportError::Timeout(_) => None,
+        }
+    }
+}
+
+impl Error for CryptoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CryptoError::KeyExchange(e) => Some(e),
+            CryptoError::PeerNotAuthenticated => None,
+        }
+    }
+}
+
+impl Error for ProtocolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProtocolError::Decode(e) | ProtocolError::Encode(e) => Some(e),
+            ProtocolError::MessageTooLarge(_) | ProtocolError::MalformedLength => None,
+        }
+    }
+}
+
+impl Error for Rejection {}
+
+impl Error for EmulationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EmulationError::Backend(e) => Some(e.as_ref()),
+            EmulationError::Unsupported(_) | EmulationError::DisplayUnavailable => None,
+        }
+    }
+}
+
+impl From<TransportError> for ConnectionError {
+    fn from(e: TransportError) -> Self {
+        ConnectionError::Transport(e)
+    }
+}
+
+impl From<CryptoError> for ConnectionError {
+    fn from(e: CryptoError) -> Self {
+        ConnectionError::Crypto(e)
+    }
+}
+
+impl From<ProtocolError> for ConnectionError {
+    fn from(e: ProtocolError) -> Self {
+        ConnectionError::Protocol(e)
+    }
+}
+
+impl From<Rejection> for ConnectionError {
+    fn from(rejection: Rejection) -> Self {
+        ConnectionError::Handshake(rejection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::ConnectionState;
+
+    #[test]
+    fn source_chain_reaches_the_io_error() {
+        let io = io::Error::new(io::ErrorKind::ConnectionReset, "reset by peer");
+        let error = ConnectionError::from(TransportError::Read(io.into()));
+        assert_eq!(error.to_string(), "Read error: reset by peer");
+        assert_eq!(
+            ConnectionState::ReadError(error.clone()).to_string(),
+            "Connection lost (Read error: reset by peer)"
+        );
+
+        let transport = error.source().unwrap();
+        let source = transport.source().unwrap();
+        assert_eq!(source.to_string(), "reset by peer");
+        match &error {
+            ConnectionError::Transport(TransportError::Read(source)) => {
+                assert_eq!(source.io_kind(), Some(io::ErrorKind::ConnectionReset))
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejection_reasons_of_the_server() {
+        assert_eq!(
+            Rejection::from_message("Password rejected"),
+            Rejection::WrongPassword
+        );
+        assert_eq!(
+            Rejection::from_message("Init message not received within timeout"),
+            Rejection::InitInfoMissing
+        );
+        let other = Rejection::from_message("Unexpected resolution");
+        assert_eq!(
+            other,
+            Rejection::Other(String::from("Unexpected resolution"))
+        );
+        assert_eq!(
+            ConnectionError::from(other).to_string(),
+            "Handshake failed (Unexpected resolution)"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! User commands run on events of the session, configured in the `[hooks]` section
+//! of `jerry_client.toml`, e.g. to keep the screen unlocked during a session:
+//!
+//! ```toml
+//! [hooks]
+//! session_begin = "xset s off -dpms"
+//! session_end = "xset s on +dpms"
+//! disconnected = "notify-send Jerry \"$JERRY_DESCRIPTION\""
+//! ```
+//!
+//! The `HookRunner` follows the events of the session (`HookRunner::observe`) and
+//! queues the commands, a task of the runtime runs them one after the other so they
+//! keep the order of the events. The event is described by the environment variables:
+//!
+//! | variable                 | value                                            |
+//! |--------------------------|--------------------------------------------------|
+//! | `JERRY_EVENT`            | `HookEvent::name`, e.g. `session_begin`          |
+//! | `JERRY_SERVER`           | name and address of the server, if known         |
+//! | `JERRY_STATE`            | `ConnectionState::name` of the last state        |
+//! | `JERRY_DESCRIPTION`      | the last state as shown in the log               |
+//! | `JERRY_CLIPBOARD_LENGTH` | length of the content, for `clipboard_received`  |
+use crate::configuration::Hooks;
+use crate::connection::ConnectionState;
+use crate::core::clipboard::Transfer;
+use crate::core::JerryMessage;
+use crate::state::Command;
+use std::io;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Commands waiting for the previous ones to finish
+const HOOK_QUEUE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// The handshake succeeded
+    Connected,
+    HandshakeFailed,
+    SessionBegin,
+    /// `SessionEnd` received, or the connection was lost during a session
+    SessionEnd,
+    ClipboardReceived,
+    /// The connection was closed after the handshake succeeded
+    Disconnected,
+}
+
+impl HookEvent {
+    pub const ALL: [HookEvent; 6] = [
+        HookEvent::Connected,
+        HookEvent::HandshakeFailed,
+        HookEvent::SessionBegin,
+        HookEvent::SessionEnd,
+        HookEvent::ClipboardReceived,
+        HookEvent::Disconnected,
+    ];
+
+    /// The key in `[hooks]`
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::Connected => "connected",
+            HookEvent::HandshakeFailed => "handshake_failed",
+            HookEvent::SessionBegin => "session_begin",
+            HookEvent::SessionEnd => "session_end",
+            HookEvent::ClipboardReceived => "clipboard_received",
+            HookEvent::Disconnected => "disconnected",
+        }
+    }
+}
+
+/// A command with the environment describing its event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Invocation {
+    command: String,
+    env: Vec<(&'static str, String)>,
+}
+
+pub struct HookRunner {
+    hooks: Hooks,
+    queue: mpsc::Sender<Invocation>,
+    server: Option<String>,
+    /// `ConnectionState::name` and description of the last state
+    state: (&'static str, String),
+    connected: bool,
+    active: bool,
+}
+
+impl HookRunner {
+    /// Spawns the task running the commands on `runtime`. The task ends once the
+    /// runner is dropped and the queued commands have run.
+    pub fn start(hooks: Hooks, runtime: &Handle) -> (Self, JoinHandle<()>) {
+        let (queue, invocations) = mpsc::channel(HOOK_QUEUE);
+        let timeout = Duration::from_millis(hooks.timeout_ms);
+        let task = runtime.spawn(run_queued(invocations, timeout));
+        let runner = Self {
+            hooks,
+            queue,
+            server: None,
+            state: (
+                ConnectionState::None.name(),
+                ConnectionState::None.to_string(),
+            ),
+            connected: false,
+            active: false,
+        };
+        (runner, task)
+    }
+
+    /// Queues the commands of the hooks the event of the session triggers, never waits.
+    pub fn observe(&mut self, command: &Command) {
+        for event in self.events(command) {
+            let Some(hook) = self.hooks.command(event) else {
+                continue;
+            };
+            let invocation = Invocation {
+                command: hook.to_owned(),
+                env: self.env(event, command),
+            };
+            if self.queue.try_send(invocation).is_err() {
+                warn!("Hook '{}' skipped, too many hooks running", event.name());
+            }
+        }
+    }
+
+    fn events(&mut self, command: &Command) -> Vec<HookEvent> {
+        match command {
+            Command::ActiveServer(server) => {
+                self.server = Some(server.clone());
+                vec![]
+            }
+            Command::ConnectionResult(state) => {
+                self.state = (state.name(), state.to_string());
+                match state {
+                    ConnectionState::HandshakeSuccess(_) => {
+                        self.connected = true;
+                        vec![HookEvent::Connected]
+                    }
+                    ConnectionState::HandshakeFailed(_) => vec![HookEvent::HandshakeFailed],
+                    _ if self.connected => {
+                        self.connected = false;
+                        match std::mem::take(&mut self.active) {
+                            true => vec![HookEvent::SessionEnd, HookEvent::Disconnected],
+                            false => vec![HookEvent::Disconnected],
+                        }
+                    }
+                    _ => vec![],
+                }
+            }
+            Command::Message(JerryMessage::SessionBegin { .. }) => {
+                self.active = true;
+                vec![HookEvent::SessionBegin]
+            }
+            Command::Message(JerryMessage::SessionEnd) if self.active => {
+                self.active = false;
+                vec![HookEvent::SessionEnd]
+            }
+            Command::ClipboardTransfer(Transfer::Incoming, _) => {
+                vec![HookEvent::ClipboardReceived]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn env(&self, event: HookEvent, command: &Command) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("JERRY_EVENT", event.name().to_owned()),
+            ("JERRY_STATE", self.state.0.to_owned()),
+            ("JERRY_DESCRIPTION", self.state.1.clone()),
+        ];
+        if let Some(server) = &self.server {
+            env.push(("JERRY_SERVER", server.clone()));
+        }
+        if let Command::ClipboardTransfer(_, content) = command {
+            env.push(("JERRY_CLIPBOARD_LENGTH", content.len().to_string()));
+        }
+        env
+    }
+}
+
+async fn run_queued(mut invocations: mpsc::Receiver<Invocation>, timeout: Duration) {
+    while let Some(invocation) = invocations.recv().await {
+        match run(&invocation, timeout).await {
+            Ok(Some(status)) if status.success() => {
+                debug!("Hook '{}' finished", invocation.command)
+            }
+            Ok(Some(status)) => warn!("Hook '{}' failed: {}", invocation.command, status),
+            Ok(None) => warn!(
+                "Hook '{}' killed after {} ms",
+                invocation.command,
+                timeout.as_millis()
+            ),
+            Err(e) => warn!("Hook '{}' not started: {}", invocation.command, e),
+        }
+    }
+}
+
+/// Runs the command by the shell, `None` if it was killed after `timeout`.
+async fn run(invocation: &Invocation, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let mut child = shell(&invocation.command)
+        .envs(invocation.env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => status.map(Some),
+        Err(_) => {
+            child.kill().await?;
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Rejection;
+
+    fn runner(hooks: Hooks) -> (HookRunner, mpsc::Receiver<Invocation>) {
+        let (queue, invocations) = mpsc::channel(HOOK_QUEUE);
+        let runner = HookRunner {
+            hooks,
+            queue,
+            server: None,
+            state: (
+                ConnectionState::None.name(),
+                ConnectionState::None.to_string(),
+            ),
+            connected: false,
+            active: false,
+        };
+        (runner, invocations)
+    }
+
+    fn state(state: ConnectionState) -> Command {
+        Command::ConnectionResult(state)
+    }
+
+    #[test]
+    fn a_lost_connection_ends_the_session() {
+        let (mut runner, _invocations) = runner(Hooks::default());
+        let mut events = vec![];
+        for command in [
+            state(ConnectionState::HandshakeFailed(Rejection::WrongPassword)),
+            state(ConnectionState::Establishing),
+            state(ConnectionState::HandshakeSuccess(String::new())),
+            Command::Message(JerryMessage::SessionBegin {
+                relative_move: false,
+            }),
+            Command::ClipboardTransfer(Transfer::Outgoing, String::from("out")),
+            Command::ClipboardTransfer(Transfer::Incoming, String::from("in")),
+            state(ConnectionState::Disconnected),
+            state(ConnectionState::Reconnecting {
+                attempt: 1,
+                max_attempts: None,
+                remaining: Duration::from_secs(1),
+            }),
+        ] {
+            events.extend(runner.events(&command));
+        }
+        assert_eq!(
+            events,
+            vec![
+                HookEvent::HandshakeFailed,
+                HookEvent::Connected,
+                HookEvent::SessionBegin,
+                HookEvent::ClipboardReceived,
+                HookEvent::SessionEnd,
+                HookEvent::Disconnected,
+            ]
+        );
+    }
+
+    #[test]
+    fn only_configured_hooks_are_queued() {
+        let hooks = Hooks {
+            session_begin: Some(String::from("xset s off")),
+            ..Hooks::default()
+        };
+        let (mut runner, mut invocations) = runner(hooks);
+        runner.observe(&Command::ActiveServer(String::from(
+            "office (10.0.0.2:8888)",
+        )));
+        runner.observe(&state(ConnectionState::HandshakeSuccess(String::new())));
+        runner.observe(&Command::Message(JerryMessage::SessionBegin {
+            relative_move: false,
+        }));
+
+        let invocation = invocations.try_recv().unwrap();
+        assert_eq!(invocation.command, "xset s off");
+        assert!(invocation
+            .env
+            .contains(&("JERRY_EVENT", String::from("session_begin"))));
+        assert!(invocation
+            .env
+            .contains(&("JERRY_SERVER", String::from("office (10.0.0.2:8888)"))));
+        assert!(invocation
+            .env
+            .contains(&("JERRY_STATE", String::from("handshake_success"))));
+        assert!(invocations.try_recv().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn commands_get_the_environment_and_are_killed_on_timeout() {
+        let invocation = Invocation {
+            command: String::from("test \"$JERRY_EVENT\" = connected"),
+            env: vec![("JERRY_EVENT", String::from("connected"))],
+        };
+        let status = run(&invocation, Duration::from_secs(5)).await.unwrap();
+        assert!(status.unwrap().success());
+
+        let invocation = Invocation {
+            command: String::from("sleep 5"),
+            env: vec![],
+        };
+        let status = run(&invocation, Duration::from_millis(50)).await.unwrap();
+        assert_eq!(status, None);
+    }
+}
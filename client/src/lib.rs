@@ -0,0 +1,56 @@
+//! Jerry client: shares the mouse, the keyboard and the clipboard of the computer
+//! running the Jerry server with this one.
+//!
+//! The `jerry_client` binary is a thin consumer of this library, which can also be
+//! embedded in other tools:
+//!
+//! ```no_run
+//! use jerry::configuration::{get_session_info_localhost, args::LocalhostArgs};
+//! use jerry::Session;
+//!
+//! # fn run(args: &LocalhostArgs) {
+//! let runtime = tokio::runtime::Runtime::new().unwrap();
+//! let mut builder = Session::builder(get_session_info_localhost(args));
+//! let events = builder.subscribe(1024);
+//! runtime.spawn(builder.build().run());
+//! for event in events {
+//!     if let jerry::Command::ConnectionResult(state) = event {
+//!         println!("{}", state);
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! * [`Session`] connects to a group of servers and reconnects as configured,
+//! * [`MessageConsumer`] handles the messages of a connection,
+//! * [`Emulator`] emulates the input events on this computer,
+//! * [`Command`] is the event sent to the subscriber of the session.
+pub mod configuration;
+pub mod connection;
+pub mod control;
+pub mod core;
+pub mod daemon;
+pub mod emulation;
+pub mod error;
+pub mod hooks;
+pub mod proto_rs;
+pub mod security;
+pub mod serialization;
+mod session;
+pub mod state;
+
+pub use crate::core::emulator::Emulator;
+pub use crate::core::{JerryMessage, JerryResponse, MessageConsumer};
+pub use crate::state::Command;
+pub use session::{Session, SessionBuilder};
+
+pub const ENCRYPT: bool = true;
+pub const DEFAULT_PORT: u16 = 8888;
+pub const JERRY_CLIENT_ID: usize = 23889;
+pub const CONFIGURATION_FILE: &str = "jerry_client.toml";
+
+#[derive(Debug, Clone, Copy)]
+pub enum DisplayMode {
+    Logging,
+    CurrentState,
+}
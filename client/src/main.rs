@@ -1,73 +1,305 @@
-mod configuration;
-mod connection;
-mod core;
-mod emulation;
-mod proto_rs;
-mod security;
-mod serialization;
-mod state;
-
-use crate::core::Command;
 use clap::Parser;
-use configuration::{ScreenResolution, SessionParams};
-use connection::ConnectionWorker;
-use std::sync::mpsc::{self, Receiver, Sender};
+use jerry::configuration::{self, ScreenResolution, SessionParams};
+use jerry::connection::reconnect::UserDecision;
+use jerry::connection::ShutdownSignal;
+use jerry::control::{self, Controller};
+use jerry::core::macros::MacroRecording;
+use jerry::core::{self, Command, GoodbyeCause};
+use jerry::hooks::HookRunner;
+use jerry::serialization::capture::{Capture, CaptureReader};
+use jerry::{daemon, state, DisplayMode, Session, CONFIGURATION_FILE};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
 use std::thread::JoinHandle;
-use tracing::{error, info, trace, Level};
+use tokio::sync::mpsc::Sender as DecisionSender;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, trace, warn, Level};
 
-const ENCRYPT: bool = true;
-const DEFAULT_PORT: u16 = 8888;
-const JERRY_CLIENT_ID: usize = 23889;
-const CONFIGURATION_FILE: &str = "jerry_client.toml";
 const LOG_LEVEL_FILE: Level = Level::INFO;
 const LOG_LEVEL_STD: Level = Level::INFO;
+/// Commands waiting for the view
+const VIEW_QUEUE: usize = 1024;
+/// Answers to a reconnection prompt waiting for the connection worker
+const DECISION_QUEUE: usize = 8;
+/// Requests of the control socket waiting for the connection worker
+const CONTROL_QUEUE: usize = 8;
+
+/// Follows the events of the session, see `tap_events`
+type Observer = Box<dyn FnMut(&Command) + Send>;
 
-#[derive(Debug, Clone, Copy)]
-pub enum DisplayMode {
-    Logging,
-    CurrentState,
-}
 fn main() -> eyre::Result<()> {
     std::env::set_var("RUST_BACKTRACE", "1");
 
     use configuration::args::{Cli, Commands};
     let cli = Cli::parse();
-    let c_info = match &cli.command {
+    let mut group = match &cli.command {
         None => match configuration::get_session_info(cli.clone()) {
             Some(e) => e,
+            None if cli.daemon => {
+                return Err(eyre::eyre!(
+                    "No server to connect to, check {}",
+                    CONFIGURATION_FILE
+                ))
+            }
             None => return Ok(()), // Q/ESC key -> Exit
         },
         Some(Commands::Localhost(_args)) => configuration::get_session_info_localhost(_args),
+        Some(Commands::Play(args)) => return play_macro(args),
+        Some(Commands::Replay(args)) => return replay_capture(args),
+        Some(Commands::Decode(args)) => return decode_dump(args),
+        Some(Commands::Ctl(args)) => return control_request(args),
     };
 
-    let _guards = logger_init(c_info.display_mode, LOG_LEVEL_FILE, LOG_LEVEL_STD);
+    if cli.daemon {
+        // there is no terminal to draw the state in
+        for server in group.servers.iter_mut() {
+            server.display_mode = DisplayMode::Logging;
+        }
+    }
+
+    let c_info = group.primary().clone();
+    let _guards = match cli.daemon {
+        true => daemon_logger_init(cli.log_file.as_deref(), LOG_LEVEL_FILE)?,
+        false => logger_init(c_info.display_mode, LOG_LEVEL_FILE, LOG_LEVEL_STD),
+    };
     info!("Program start");
 
-    // type Channel = (Sender<Command>, Receiver<Command>);
-    let (tx, rx) = mpsc::channel();
-    let key_listener = start_exit_key_listener(tx.clone());
-    let ui_thread = start_state_visualization(tx.clone(), c_info.clone(), rx);
-    let conn_worker = start_connection_loop(tx, c_info);
+    let runtime = tokio::runtime::Runtime::new()?;
+    let (tx, rx) = mpsc::sync_channel(VIEW_QUEUE);
+    let (decision_tx, decision_rx) = tokio::sync::mpsc::channel(DECISION_QUEUE);
+    let shutdown = ShutdownSignal::default();
+    let servers = group
+        .servers
+        .iter()
+        .map(|s| s.server_name.clone())
+        .collect();
+    let hooks = group.hooks.clone();
+    let mut builder = Session::builder(group).shutdown(shutdown.clone());
+    let mut observers: Vec<Observer> = Vec::new();
+    if let Some(path) = &cli.control {
+        let path = path.clone().unwrap_or_else(control::default_socket_path);
+        let (requests, controls) = tokio::sync::mpsc::channel(CONTROL_QUEUE);
+        let controller = Arc::new(Controller::new(
+            servers,
+            requests,
+            builder.emulation_pause(),
+        ));
+        start_control_server(&runtime, &path, controller.clone(), &shutdown)?;
+        builder = builder.controls(controls);
+        observers.push(Box::new(move |command| controller.observe(command)));
+    }
+    let hooks_worker = match hooks.is_empty() {
+        true => None,
+        false => {
+            let (mut runner, worker) = HookRunner::start(hooks, runtime.handle());
+            observers.push(Box::new(move |command| runner.observe(command)));
+            Some(worker)
+        }
+    };
+    let key_listener = match cli.daemon {
+        true => runtime.spawn(daemon_signals(tx.clone(), shutdown.clone())),
+        false => {
+            set_signal_handler(tx.clone(), shutdown.clone());
+            runtime.spawn(exit_key_listener(tx.clone(), shutdown.token()))
+        }
+    };
+    let recording = builder.macro_recording();
+    if let Some(name) = &cli.record {
+        info!("Recording macro '{}'", name);
+        recording.start(name);
+    }
+    let ui_thread =
+        start_state_visualization(tx.clone(), c_info.clone(), rx, decision_tx, recording);
+    // only the session sends to the tap, it ends with the session
+    let (events, tap_thread) = match observers.is_empty() {
+        true => (tx, None),
+        false => {
+            let (events, thread) = tap_events(tx, observers);
+            (events, Some(thread))
+        }
+    };
+    builder = builder.events(events);
+    // without a terminal nobody answers, a prompt stops the reconnection
+    if !cli.daemon {
+        builder = builder.decisions(decision_rx);
+    }
+    if let Some(path) = &cli.capture {
+        builder = builder.capture(Capture::create(path)?);
+        info!("Capturing the traffic into {}", path.display());
+    }
+    let session = builder.build();
+    let conn_worker = runtime.spawn(session.run());
+    if cli.daemon {
+        notify_service(daemon::READY);
+    }
 
     if let Err(e) = ui_thread.join() {
         error!("View thread panicked: {:?}", e.downcast_ref::<&str>())
     }
-    trace!("Exiting the program: 1/4 |  View thread has finished execution.");
-    if let Err(e) = key_listener.join() {
-        error!("KeyListener panicked: {:?}", e.downcast_ref::<&str>())
+    // the connection is closed cleanly instead of timing out on the server
+    shutdown.request(GoodbyeCause::USER_EXIT);
+    if cli.daemon {
+        notify_service(daemon::STOPPING);
     }
-    trace!("Exiting the program: 2/4 |  KeyListener thread has finished execution.");
-    if let Err(e) = conn_worker.join() {
-        error!("Connection thread panicked: {:?}", e.downcast_ref::<&str>())
+    trace!("Exiting the program: 1/3 |  View thread has finished execution.");
+    runtime.block_on(async {
+        if let Err(e) = key_listener.await {
+            error!("KeyListener panicked: {:?}", e)
+        }
+        trace!("Exiting the program: 2/3 |  KeyListener task has finished execution.");
+        match conn_worker.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Connection task failed: {}", e),
+            Err(e) => error!("Connection task panicked: {:?}", e),
+        }
+    });
+    if let Some(tap_thread) = tap_thread {
+        if tap_thread.join().is_err() {
+            error!("Event tap thread panicked")
+        }
     }
-    trace!(
-        "Exiting the program: 3/4 |  Connection & MessageHandler thread has finished execution."
-    );
-    trace!("Exiting the program: 4/4 |  Main thread has finished execution. ");
-    std::thread::sleep(std::time::Duration::from_millis(300));
+    if let Some(worker) = hooks_worker {
+        // the hooks of the end of the session run before the client exits
+        if let Err(e) = runtime.block_on(worker) {
+            error!("Hook task panicked: {:?}", e)
+        }
+    }
+    trace!("Exiting the program: 3/3 |  Connection tasks have finished execution.");
     Ok(())
 }
 
+fn play_macro(args: &configuration::args::PlayArgs) -> eyre::Result<()> {
+    let _guards = logger_init(DisplayMode::Logging, LOG_LEVEL_FILE, LOG_LEVEL_STD);
+    let recording = core::macros::Macro::load(&args.name)?;
+    let mut emulator = core::emulator::platform_emulator();
+    core::macros::play(&recording, emulator.as_mut(), args.speed)
+}
+
+fn replay_capture(args: &configuration::args::ReplayArgs) -> eyre::Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    let _guards = logger_init(DisplayMode::Logging, LOG_LEVEL_FILE, LOG_LEVEL_STD);
+    let records = CaptureReader::open(&args.file)?;
+    let options = core::replay::ReplayOptions {
+        speed: args.speed,
+        dry_run: args.dry_run,
+        only: args.only.clone(),
+    };
+    // the keys held are released before exiting
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_handler = stop.clone();
+    if let Err(e) = ctrlc::set_handler(move || stop_handler.store(true, Ordering::Relaxed)) {
+        warn!("Signal handler not set: {}", e);
+    }
+    core::replay::replay(records, &options, &mut std::io::stdout().lock(), &stop)
+}
+
+fn decode_dump(args: &configuration::args::DecodeArgs) -> eyre::Result<()> {
+    use jerry::serialization::decode::{decode, DecodeOptions};
+    use std::io::Read;
+    let mut bytes = Vec::new();
+    match args.file.as_deref() {
+        Some(path) if path != std::path::Path::new("-") => {
+            bytes = std::fs::read(path)?;
+        }
+        _ => {
+            std::io::stdin().lock().read_to_end(&mut bytes)?;
+        }
+    }
+    let key = match (args.key, args.nonce) {
+        (Some(key), Some(nonce)) => Some(jerry::security::ChaChaKey { key, nonce }),
+        _ => None,
+    };
+    let options = DecodeOptions {
+        origin: args.origin,
+        key,
+        skip: args.skip,
+    };
+    let decoded = decode(&mut bytes, &options, &mut std::io::stdout().lock())?;
+    eprintln!("{} messages", decoded);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn control_request(args: &configuration::args::CtlArgs) -> eyre::Result<()> {
+    use eyre::WrapErr;
+    use serde_json::{json, Value};
+    use std::io::{BufRead, BufReader, Write};
+    let mut request = json!({"jsonrpc": "2.0", "id": 1, "method": args.method});
+    if let Some(params) = args.params.as_deref() {
+        request["params"] = serde_json::from_str(params).wrap_err("Parameters are not JSON")?;
+    }
+    let path = args
+        .socket
+        .clone()
+        .unwrap_or_else(control::default_socket_path);
+    let mut stream = std::os::unix::net::UnixStream::connect(&path)
+        .wrap_err_with(|| format!("Control socket {}", path.display()))?;
+    writeln!(stream, "{}", request)?;
+    // the events of a subscription follow the response until the client exits
+    let subscribe = args.method == "subscribe";
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        println!("{}", line);
+        let reply: Value = serde_json::from_str(&line)?;
+        if let Some(message) = reply["error"]["message"].as_str() {
+            return Err(eyre::eyre!("{}", message));
+        }
+        if !subscribe {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn control_request(_args: &configuration::args::CtlArgs) -> eyre::Result<()> {
+    Err(eyre::eyre!("The control socket needs Unix domain sockets"))
+}
+
+#[cfg(unix)]
+fn start_control_server(
+    runtime: &tokio::runtime::Runtime,
+    path: &Path,
+    controller: Arc<Controller>,
+    shutdown: &ShutdownSignal,
+) -> eyre::Result<()> {
+    use eyre::WrapErr;
+    let server = control::ControlServer::bind(path, controller)
+        .wrap_err_with(|| format!("Control socket {}", path.display()))?;
+    runtime.spawn(server.run(shutdown.token()));
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn start_control_server(
+    _runtime: &tokio::runtime::Runtime,
+    _path: &Path,
+    _controller: Arc<Controller>,
+    _shutdown: &ShutdownSignal,
+) -> eyre::Result<()> {
+    Err(eyre::eyre!("The control socket needs Unix domain sockets"))
+}
+
+/// The events pass through the observers on their way to the view. The observers
+/// still follow the end of the session after the view has stopped.
+fn tap_events(
+    view: SyncSender<Command>,
+    mut observers: Vec<Observer>,
+) -> (SyncSender<Command>, JoinHandle<()>) {
+    let (tap, events) = mpsc::sync_channel(VIEW_QUEUE);
+    let thread = std::thread::spawn(move || {
+        for command in events {
+            for observe in observers.iter_mut() {
+                observe(&command);
+            }
+            _ = view.send(command);
+        }
+    });
+    (tap, thread)
+}
+
 use tracing_appender::non_blocking::WorkerGuard;
 fn logger_init(strategy: DisplayMode, file_level: Level, out_level: Level) -> Vec<WorkerGuard> {
     use tracing::level_filters::LevelFilter;
@@ -107,60 +339,171 @@ fn logger_init(strategy: DisplayMode, file_level: Level, out_level: Level) -> Ve
     }
 }
 
+/// Log of the daemon: into the file if given, otherwise to the standard error for journald.
+fn daemon_logger_init(file: Option<&Path>, level: Level) -> eyre::Result<Vec<WorkerGuard>> {
+    use eyre::WrapErr;
+    use tracing::level_filters::LevelFilter;
+    use tracing_subscriber::{
+        fmt::{self},
+        prelude::*,
+        Registry,
+    };
+
+    let guard = match file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .wrap_err_with(|| format!("Log file {}", path.display()))?;
+            let (nb_file_appender, guard) = tracing_appender::non_blocking(file);
+            let layer_file = fmt::Layer::default()
+                .with_ansi(false)
+                .with_writer(nb_file_appender)
+                .with_filter(LevelFilter::from(level));
+            Registry::default().with(layer_file).init();
+            guard
+        }
+        None => {
+            let (nb_journal_appender, guard) = tracing_appender::non_blocking(std::io::stderr());
+            let layer_journal = fmt::Layer::default()
+                .with_ansi(false)
+                .event_format(daemon::JournalFormat)
+                .with_writer(nb_journal_appender)
+                .with_filter(LevelFilter::from(level));
+            Registry::default().with(layer_journal).init();
+            guard
+        }
+    };
+    Ok(vec![guard])
+}
+
+fn notify_service(state: &str) {
+    match daemon::notify(state) {
+        Ok(true) => trace!("Service manager notified: {}", state),
+        Ok(false) => {}
+        Err(e) => warn!("Service manager not notified ({}): {}", state, e),
+    }
+}
+
 fn start_state_visualization(
-    tx: Sender<Command>,
+    tx: SyncSender<Command>,
     info: SessionParams,
     rx: Receiver<Command>,
+    decisions: DecisionSender<UserDecision>,
+    recording: MacroRecording,
 ) -> JoinHandle<()> {
     std::thread::spawn(move || match info.display_mode {
-        DisplayMode::CurrentState => view_thread_job(info, tx, rx),
-        DisplayMode::Logging => log_thread_job(rx),
+        DisplayMode::CurrentState => view_thread_job(info, tx, rx, decisions, recording),
+        DisplayMode::Logging => log_thread_job(rx, decisions, recording),
     })
 }
 
-fn start_connection_loop(transmitter: Sender<Command>, cinfo: SessionParams) -> JoinHandle<()> {
-    std::thread::spawn(move || {
-        use thread_priority::*;
-        _ = set_current_thread_priority(ThreadPriority::Max);
-        let mut conw = ConnectionWorker::new(transmitter, cinfo);
-        conw.run();
-    })
+/// SIGINT, SIGTERM (Ctrl+C, Ctrl+Break on Windows)
+fn set_signal_handler(transmitter: SyncSender<Command>, shutdown: ShutdownSignal) {
+    let result = ctrlc::set_handler(move || {
+        shutdown.request(GoodbyeCause::TERMINATED);
+        _ = transmitter.send(Command::Halt);
+    });
+    if let Err(e) = result {
+        warn!("Signal handler not set: {}", e);
+    }
+}
+
+/// SIGTERM and SIGINT stop the daemon. SIGHUP is ignored, there is no terminal to hang up.
+#[cfg(unix)]
+async fn daemon_signals(transmitter: SyncSender<Command>, shutdown: ShutdownSignal) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let signals = (|| {
+        Ok::<_, std::io::Error>((
+            signal(SignalKind::terminate())?,
+            signal(SignalKind::interrupt())?,
+            signal(SignalKind::hangup())?,
+        ))
+    })();
+    let (mut terminate, mut interrupt, mut hangup) = match signals {
+        Ok(signals) => signals,
+        Err(e) => {
+            error!("Signal handlers not set: {}", e);
+            return;
+        }
+    };
+    let cancel = shutdown.token();
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = terminate.recv() => info!("SIGTERM received"),
+            _ = interrupt.recv() => info!("SIGINT received"),
+            _ = hangup.recv() => {
+                info!("SIGHUP ignored");
+                continue;
+            }
+        }
+        break;
+    }
+    shutdown.request(GoodbyeCause::TERMINATED);
+    halt(&transmitter);
 }
 
-fn start_exit_key_listener(exit_transmitter: Sender<Command>) -> JoinHandle<()> {
-    use crossterm::event::{self, Event, KeyCode};
-    std::thread::spawn(move || {
-        loop {
-            match event::poll(std::time::Duration::from_millis(1000)) {
-                Err(_) => {
-                    _ = exit_transmitter.send(Command::Halt);
+/// Ctrl+C and Ctrl+Break stop the daemon.
+#[cfg(not(unix))]
+async fn daemon_signals(transmitter: SyncSender<Command>, shutdown: ShutdownSignal) {
+    set_signal_handler(transmitter, shutdown.clone());
+    shutdown.token().cancelled().await;
+}
+
+/// Forwards the keys pressed in the terminal to the view until the shutdown is requested.
+async fn exit_key_listener(exit_transmitter: SyncSender<Command>, cancel: CancellationToken) {
+    use crossterm::event::{Event, EventStream, KeyCode};
+    use tokio_stream::StreamExt;
+    let mut events = EventStream::new();
+    loop {
+        let event = tokio::select! {
+            _ = cancel.cancelled() => break,
+            event = events.next() => event,
+        };
+        match event {
+            Some(Ok(Event::Key(key))) => {
+                if key.code == KeyCode::Char('q') || key.code == KeyCode::Esc {
+                    halt(&exit_transmitter);
                     break;
                 }
-                Ok(false) => {
-                    if exit_transmitter.send(Command::Draw).is_err() {
-                        break;
-                    }
-                }
-                Ok(true) => {
-                    //event is available
-                    if let Ok(key_ev) = event::read() {
-                        if let Event::Key(key) = key_ev {
-                            if key.code == KeyCode::Char('q') || key.code == KeyCode::Esc {
-                                _ = exit_transmitter.send(Command::Halt);
-                                break;
-                            }
-                        }
-                    } else {
-                        _ = exit_transmitter.send(Command::Halt);
-                        break;
-                    }
+                // keys typed while the view is busy are dropped
+                if let Err(mpsc::TrySendError::Disconnected(_)) =
+                    exit_transmitter.try_send(Command::Input(key.code))
+                {
+                    break;
                 }
             }
+            Some(Ok(_)) => {}
+            Some(Err(e)) => {
+                error!(
+                    "Terminal input not readable, run with --daemon without a terminal: {}",
+                    e
+                );
+                halt(&exit_transmitter);
+                break;
+            }
+            None => {
+                halt(&exit_transmitter);
+                break;
+            }
         }
-    })
+    }
 }
 
-fn view_thread_job(info: SessionParams, tx_clone: Sender<Command>, rx: Receiver<Command>) {
+/// Unlike the keys, `Halt` has to reach the view even when its queue is full.
+fn halt(transmitter: &SyncSender<Command>) {
+    _ = tokio::task::block_in_place(|| transmitter.send(Command::Halt));
+}
+
+fn view_thread_job(
+    info: SessionParams,
+    tx_clone: SyncSender<Command>,
+    rx: Receiver<Command>,
+    decisions: DecisionSender<UserDecision>,
+    recording: MacroRecording,
+) {
     use crossterm::execute;
     use crossterm::terminal::{
         disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
@@ -181,14 +524,25 @@ fn view_thread_job(info: SessionParams, tx_clone: Sender<Command>, rx: Receiver<
         }
     };
     let resolution = state::Coord { x: w, y: h };
-    let mut view = state::ui::WindowState::new(resolution, tx_clone, rx, &mut terminal);
+    let mut view = state::ui::WindowState::new(
+        resolution,
+        tx_clone,
+        rx,
+        decisions,
+        recording,
+        &mut terminal,
+    );
     view.run();
 
     disable_raw_mode().unwrap();
     execute!(terminal.backend_mut(), LeaveAlternateScreen).unwrap();
     terminal.show_cursor().unwrap();
 }
-fn log_thread_job(rx: Receiver<Command>) {
-    let mut logging_receiver = state::log::View::new(rx);
+fn log_thread_job(
+    rx: Receiver<Command>,
+    decisions: DecisionSender<UserDecision>,
+    recording: MacroRecording,
+) {
+    let mut logging_receiver = state::log::View::new(rx, decisions, recording);
     logging_receiver.run();
 }
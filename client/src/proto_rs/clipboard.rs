@@ -28,6 +28,8 @@ pub struct Clipboard {
     // message fields
     pub message: ::std::string::String,
     pub format: Clipboard_Format,
+    pub compression: Compression,
+    pub compressed: ::std::vec::Vec<u8>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -84,6 +86,47 @@ impl Clipboard {
     pub fn set_format(&mut self, v: Clipboard_Format) {
         self.format = v;
     }
+
+    // .common.Compression compression = 3;
+
+
+    pub fn get_compression(&self) -> Compression {
+        self.compression
+    }
+    pub fn clear_compression(&mut self) {
+        self.compression = Compression::NONE;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_compression(&mut self, v: Compression) {
+        self.compression = v;
+    }
+
+    // bytes compressed = 4;
+
+
+    pub fn get_compressed(&self) -> &[u8] {
+        &self.compressed
+    }
+    pub fn clear_compressed(&mut self) {
+        self.compressed.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_compressed(&mut self, v: ::std::vec::Vec<u8>) {
+        self.compressed = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_compressed(&mut self) -> &mut ::std::vec::Vec<u8> {
+        &mut self.compressed
+    }
+
+    // Take field
+    pub fn take_compressed(&mut self) -> ::std::vec::Vec<u8> {
+        ::std::mem::replace(&mut self.compressed, ::std::vec::Vec::new())
+    }
 }
 
 impl ::protobuf::Message for Clipboard {
@@ -101,6 +144,12 @@ impl ::protobuf::Message for Clipboard {
                 2 => {
                     ::protobuf::rt::read_proto3_enum_with_unknown_fields_into(wire_type, is, &mut self.format, 2, &mut self.unknown_fields)?
                 },
+                3 => {
+                    ::protobuf::rt::read_proto3_enum_with_unknown_fields_into(wire_type, is, &mut self.compression, 3, &mut self.unknown_fields)?
+                },
+                4 => {
+                    ::protobuf::rt::read_singular_proto3_bytes_into(wire_type, is, &mut self.compressed)?;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -119,6 +168,12 @@ impl ::protobuf::Message for Clipboard {
         if self.format != Clipboard_Format::TEXT {
             my_size += ::protobuf::rt::enum_size(2, self.format);
         }
+        if self.compression != Compression::NONE {
+            my_size += ::protobuf::rt::enum_size(3, self.compression);
+        }
+        if !self.compressed.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(4, &self.compressed);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -131,6 +186,12 @@ impl ::protobuf::Message for Clipboard {
         if self.format != Clipboard_Format::TEXT {
             os.write_enum(2, ::protobuf::ProtobufEnum::value(&self.format))?;
         }
+        if self.compression != Compression::NONE {
+            os.write_enum(3, ::protobuf::ProtobufEnum::value(&self.compression))?;
+        }
+        if !self.compressed.is_empty() {
+            os.write_bytes(4, &self.compressed)?;
+        }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -179,6 +240,16 @@ impl ::protobuf::Message for Clipboard {
                 |m: &Clipboard| { &m.format },
                 |m: &mut Clipboard| { &mut m.format },
             ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeEnum<Compression>>(
+                "compression",
+                |m: &Clipboard| { &m.compression },
+                |m: &mut Clipboard| { &mut m.compression },
+            ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBytes>(
+                "compressed",
+                |m: &Clipboard| { &m.compressed },
+                |m: &mut Clipboard| { &mut m.compressed },
+            ));
             ::protobuf::reflect::MessageDescriptor::new_pb_name::<Clipboard>(
                 "Clipboard",
                 fields,
@@ -197,6 +268,8 @@ impl ::protobuf::Clear for Clipboard {
     fn clear(&mut self) {
         self.message.clear();
         self.format = Clipboard_Format::TEXT;
+        self.compression = Compression::NONE;
+        self.compressed.clear();
         self.unknown_fields.clear();
     }
 }
@@ -263,11 +336,253 @@ impl ::protobuf::reflect::ProtobufValue for Clipboard_Format {
     }
 }
 
+#[derive(PartialEq,Clone,Default)]
+pub struct Ping {
+    // message fields
+    pub sequence: u32,
+    pub timestamp: u64,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl<'a> ::std::default::Default for &'a Ping {
+    fn default() -> &'a Ping {
+        <Ping as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl Ping {
+    pub fn new() -> Ping {
+        ::std::default::Default::default()
+    }
+
+    // uint32 sequence = 1;
+
+
+    pub fn get_sequence(&self) -> u32 {
+        self.sequence
+    }
+    pub fn clear_sequence(&mut self) {
+        self.sequence = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_sequence(&mut self, v: u32) {
+        self.sequence = v;
+    }
+
+    // uint64 timestamp = 2;
+
+
+    pub fn get_timestamp(&self) -> u64 {
+        self.timestamp
+    }
+    pub fn clear_timestamp(&mut self) {
+        self.timestamp = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_timestamp(&mut self, v: u64) {
+        self.timestamp = v;
+    }
+}
+
+impl ::protobuf::Message for Ping {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint32()?;
+                    self.sequence = tmp;
+                },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.timestamp = tmp;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if self.sequence != 0 {
+            my_size += ::protobuf::rt::value_size(1, self.sequence, ::protobuf::wire_format::WireTypeVarint);
+        }
+        if self.timestamp != 0 {
+            my_size += ::protobuf::rt::value_size(2, self.timestamp, ::protobuf::wire_format::WireTypeVarint);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if self.sequence != 0 {
+            os.write_uint32(1, self.sequence)?;
+        }
+        if self.timestamp != 0 {
+            os.write_uint64(2, self.timestamp)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: ::std::boxed::Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Ping {
+        Ping::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::LazyV2<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::LazyV2::INIT;
+        descriptor.get(|| {
+            let mut fields = ::std::vec::Vec::new();
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint32>(
+                "sequence",
+                |m: &Ping| { &m.sequence },
+                |m: &mut Ping| { &mut m.sequence },
+            ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                "timestamp",
+                |m: &Ping| { &m.timestamp },
+                |m: &mut Ping| { &mut m.timestamp },
+            ));
+            ::protobuf::reflect::MessageDescriptor::new_pb_name::<Ping>(
+                "Ping",
+                fields,
+                file_descriptor_proto()
+            )
+        })
+    }
+
+    fn default_instance() -> &'static Ping {
+        static instance: ::protobuf::rt::LazyV2<Ping> = ::protobuf::rt::LazyV2::INIT;
+        instance.get(Ping::new)
+    }
+}
+
+impl ::protobuf::Clear for Ping {
+    fn clear(&mut self) {
+        self.sequence = 0;
+        self.timestamp = 0;
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Ping {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Ping {
+    fn as_ref(&self) -> ::protobuf::reflect::ReflectValueRef {
+        ::protobuf::reflect::ReflectValueRef::Message(self)
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug,Hash)]
+pub enum Compression {
+    NONE = 0,
+    DEFLATE = 1,
+}
+
+impl ::protobuf::ProtobufEnum for Compression {
+    fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    fn from_i32(value: i32) -> ::std::option::Option<Compression> {
+        match value {
+            0 => ::std::option::Option::Some(Compression::NONE),
+            1 => ::std::option::Option::Some(Compression::DEFLATE),
+            _ => ::std::option::Option::None
+        }
+    }
+
+    fn values() -> &'static [Self] {
+        static values: &'static [Compression] = &[
+            Compression::NONE,
+            Compression::DEFLATE,
+        ];
+        values
+    }
+
+    fn enum_descriptor_static() -> &'static ::protobuf::reflect::EnumDescriptor {
+        static descriptor: ::protobuf::rt::LazyV2<::protobuf::reflect::EnumDescriptor> = ::protobuf::rt::LazyV2::INIT;
+        descriptor.get(|| {
+            ::protobuf::reflect::EnumDescriptor::new_pb_name::<Compression>("Compression", file_descriptor_proto())
+        })
+    }
+}
+
+impl ::std::marker::Copy for Compression {
+}
+
+impl ::std::default::Default for Compression {
+    fn default() -> Self {
+        Compression::NONE
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Compression {
+    fn as_ref(&self) -> ::protobuf::reflect::ReflectValueRef {
+        ::protobuf::reflect::ReflectValueRef::Enum(::protobuf::ProtobufEnum::descriptor(self))
+    }
+}
+
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x0fclipboard.proto\x12\x06common\"u\n\tClipboard\x12\x18\n\x07message\
-    \x18\x01\x20\x01(\tR\x07message\x120\n\x06format\x18\x02\x20\x01(\x0e2\
-    \x18.common.Clipboard.FormatR\x06format\"\x1c\n\x06Format\x12\x08\n\x04T\
-    EXT\x10\0\x12\x08\n\x04FILE\x10\x01b\x06proto3\
+    \n\x0fclipboard.proto\x12\x06common\"\xcc\x01\n\tClipboard\x12\x18\n\x07\
+    message\x18\x01\x20\x01(\tR\x07message\x120\n\x06format\x18\x02\x20\x01(\
+    \x0e2\x18.common.Clipboard.FormatR\x06format\x125\n\x0bcompression\x18\
+    \x03\x20\x01(\x0e2\x13.common.CompressionR\x0bcompression\x12\x1e\n\ncom\
+    pressed\x18\x04\x20\x01(\x0cR\ncompressed\"\x1c\n\x06Format\x12\x08\n\
+    \x04TEXT\x10\0\x12\x08\n\x04FILE\x10\x01\"@\n\x04Ping\x12\x1a\n\x08seque\
+    nce\x18\x01\x20\x01(\rR\x08sequence\x12\x1c\n\ttimestamp\x18\x02\x20\x01\
+    (\x04R\ttimestamp*$\n\x0bCompression\x12\x08\n\x04NONE\x10\0\x12\x0b\n\
+    \x07DEFLATE\x10\x01b\x06proto3\
 ";
 
 static file_descriptor_proto_lazy: ::protobuf::rt::LazyV2<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::rt::LazyV2::INIT;
@@ -53,6 +53,7 @@ pub enum MasterMessage_oneof_action {
     request(Request),
     handshake(Echo),
     heartbeat(Heartbeat),
+    pong(super::clipboard::Ping),
 }
 
 impl MasterMessage {
@@ -552,6 +553,55 @@ impl MasterMessage {
         }
     }
 
+    // .common.Ping pong = 13;
+
+
+    pub fn get_pong(&self) -> &super::clipboard::Ping {
+        match self.action {
+            ::std::option::Option::Some(MasterMessage_oneof_action::pong(ref v)) => v,
+            _ => <super::clipboard::Ping as ::protobuf::Message>::default_instance(),
+        }
+    }
+    pub fn clear_pong(&mut self) {
+        self.action = ::std::option::Option::None;
+    }
+
+    pub fn has_pong(&self) -> bool {
+        match self.action {
+            ::std::option::Option::Some(MasterMessage_oneof_action::pong(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_pong(&mut self, v: super::clipboard::Ping) {
+        self.action = ::std::option::Option::Some(MasterMessage_oneof_action::pong(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_pong(&mut self) -> &mut super::clipboard::Ping {
+        if let ::std::option::Option::Some(MasterMessage_oneof_action::pong(_)) = self.action {
+        } else {
+            self.action = ::std::option::Option::Some(MasterMessage_oneof_action::pong(super::clipboard::Ping::new()));
+        }
+        match self.action {
+            ::std::option::Option::Some(MasterMessage_oneof_action::pong(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_pong(&mut self) -> super::clipboard::Ping {
+        if self.has_pong() {
+            match self.action.take() {
+                ::std::option::Option::Some(MasterMessage_oneof_action::pong(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            super::clipboard::Ping::new()
+        }
+    }
+
     // string rndE = 12;
 
 
@@ -626,6 +676,11 @@ impl ::protobuf::Message for MasterMessage {
                 return false;
             }
         }
+        if let Some(MasterMessage_oneof_action::pong(ref v)) = self.action {
+            if !v.is_initialized() {
+                return false;
+            }
+        }
         true
     }
 
@@ -696,6 +751,12 @@ impl ::protobuf::Message for MasterMessage {
                     }
                     self.action = ::std::option::Option::Some(MasterMessage_oneof_action::heartbeat(is.read_message()?));
                 },
+                13 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    self.action = ::std::option::Option::Some(MasterMessage_oneof_action::pong(is.read_message()?));
+                },
                 12 => {
                     ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.rndE)?;
                 },
@@ -758,6 +819,10 @@ impl ::protobuf::Message for MasterMessage {
                     let len = v.compute_size();
                     my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
                 },
+                &MasterMessage_oneof_action::pong(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+                },
             };
         }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
@@ -822,6 +887,11 @@ impl ::protobuf::Message for MasterMessage {
                     os.write_raw_varint32(v.get_cached_size())?;
                     v.write_to_with_cached_sizes(os)?;
                 },
+                &MasterMessage_oneof_action::pong(ref v) => {
+                    os.write_tag(13, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+                    os.write_raw_varint32(v.get_cached_size())?;
+                    v.write_to_with_cached_sizes(os)?;
+                },
             };
         }
         os.write_unknown_fields(self.get_unknown_fields())?;
@@ -917,6 +987,11 @@ impl ::protobuf::Message for MasterMessage {
                 MasterMessage::has_heartbeat,
                 MasterMessage::get_heartbeat,
             ));
+            fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, super::clipboard::Ping>(
+                "pong",
+                MasterMessage::has_pong,
+                MasterMessage::get_pong,
+            ));
             fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
                 "rndE",
                 |m: &MasterMessage| { &m.rndE },
@@ -949,6 +1024,7 @@ impl ::protobuf::Clear for MasterMessage {
         self.action = ::std::option::Option::None;
         self.action = ::std::option::Option::None;
         self.action = ::std::option::Option::None;
+        self.action = ::std::option::Option::None;
         self.rndE.clear();
         self.unknown_fields.clear();
     }
@@ -971,6 +1047,8 @@ pub struct Echo {
     // message fields
     pub result: HandshakeResult,
     pub message: ::std::string::String,
+    pub compression: super::clipboard::Compression,
+    pub ping: bool,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -1027,6 +1105,36 @@ impl Echo {
     pub fn take_message(&mut self) -> ::std::string::String {
         ::std::mem::replace(&mut self.message, ::std::string::String::new())
     }
+
+    // .common.Compression compression = 3;
+
+
+    pub fn get_compression(&self) -> super::clipboard::Compression {
+        self.compression
+    }
+    pub fn clear_compression(&mut self) {
+        self.compression = super::clipboard::Compression::NONE;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_compression(&mut self, v: super::clipboard::Compression) {
+        self.compression = v;
+    }
+
+    // bool ping = 4;
+
+
+    pub fn get_ping(&self) -> bool {
+        self.ping
+    }
+    pub fn clear_ping(&mut self) {
+        self.ping = false;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ping(&mut self, v: bool) {
+        self.ping = v;
+    }
 }
 
 impl ::protobuf::Message for Echo {
@@ -1044,6 +1152,16 @@ impl ::protobuf::Message for Echo {
                 2 => {
                     ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.message)?;
                 },
+                3 => {
+                    ::protobuf::rt::read_proto3_enum_with_unknown_fields_into(wire_type, is, &mut self.compression, 3, &mut self.unknown_fields)?
+                },
+                4 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_bool()?;
+                    self.ping = tmp;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -1062,6 +1180,12 @@ impl ::protobuf::Message for Echo {
         if !self.message.is_empty() {
             my_size += ::protobuf::rt::string_size(2, &self.message);
         }
+        if self.compression != super::clipboard::Compression::NONE {
+            my_size += ::protobuf::rt::enum_size(3, self.compression);
+        }
+        if self.ping != false {
+            my_size += 2;
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -1074,6 +1198,12 @@ impl ::protobuf::Message for Echo {
         if !self.message.is_empty() {
             os.write_string(2, &self.message)?;
         }
+        if self.compression != super::clipboard::Compression::NONE {
+            os.write_enum(3, ::protobuf::ProtobufEnum::value(&self.compression))?;
+        }
+        if self.ping != false {
+            os.write_bool(4, self.ping)?;
+        }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -1122,6 +1252,16 @@ impl ::protobuf::Message for Echo {
                 |m: &Echo| { &m.message },
                 |m: &mut Echo| { &mut m.message },
             ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeEnum<super::clipboard::Compression>>(
+                "compression",
+                |m: &Echo| { &m.compression },
+                |m: &mut Echo| { &mut m.compression },
+            ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeBool>(
+                "ping",
+                |m: &Echo| { &m.ping },
+                |m: &mut Echo| { &mut m.ping },
+            ));
             ::protobuf::reflect::MessageDescriptor::new_pb_name::<Echo>(
                 "Echo",
                 fields,
@@ -1140,6 +1280,8 @@ impl ::protobuf::Clear for Echo {
     fn clear(&mut self) {
         self.result = HandshakeResult::Success;
         self.message.clear();
+        self.compression = super::clipboard::Compression::NONE;
+        self.ping = false;
         self.unknown_fields.clear();
     }
 }
@@ -1160,6 +1302,7 @@ impl ::protobuf::reflect::ProtobufValue for Echo {
 pub struct Heartbeat {
     // message fields
     pub one_way: bool,
+    pub timestamp: u64,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -1190,6 +1333,21 @@ impl Heartbeat {
     pub fn set_one_way(&mut self, v: bool) {
         self.one_way = v;
     }
+
+    // uint64 timestamp = 2;
+
+
+    pub fn get_timestamp(&self) -> u64 {
+        self.timestamp
+    }
+    pub fn clear_timestamp(&mut self) {
+        self.timestamp = 0;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_timestamp(&mut self, v: u64) {
+        self.timestamp = v;
+    }
 }
 
 impl ::protobuf::Message for Heartbeat {
@@ -1208,6 +1366,13 @@ impl ::protobuf::Message for Heartbeat {
                     let tmp = is.read_bool()?;
                     self.one_way = tmp;
                 },
+                2 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeVarint {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    let tmp = is.read_uint64()?;
+                    self.timestamp = tmp;
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -1223,6 +1388,9 @@ impl ::protobuf::Message for Heartbeat {
         if self.one_way != false {
             my_size += 2;
         }
+        if self.timestamp != 0 {
+            my_size += ::protobuf::rt::value_size(2, self.timestamp, ::protobuf::wire_format::WireTypeVarint);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -1232,6 +1400,9 @@ impl ::protobuf::Message for Heartbeat {
         if self.one_way != false {
             os.write_bool(1, self.one_way)?;
         }
+        if self.timestamp != 0 {
+            os.write_uint64(2, self.timestamp)?;
+        }
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -1275,6 +1446,11 @@ impl ::protobuf::Message for Heartbeat {
                 |m: &Heartbeat| { &m.one_way },
                 |m: &mut Heartbeat| { &mut m.one_way },
             ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeUint64>(
+                "timestamp",
+                |m: &Heartbeat| { &m.timestamp },
+                |m: &mut Heartbeat| { &mut m.timestamp },
+            ));
             ::protobuf::reflect::MessageDescriptor::new_pb_name::<Heartbeat>(
                 "Heartbeat",
                 fields,
@@ -1292,6 +1468,7 @@ impl ::protobuf::Message for Heartbeat {
 impl ::protobuf::Clear for Heartbeat {
     fn clear(&mut self) {
         self.one_way = false;
+        self.timestamp = 0;
         self.unknown_fields.clear();
     }
 }
@@ -2580,7 +2757,7 @@ impl ::protobuf::reflect::ProtobufValue for State {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x14request_master.proto\x12\x06master\x1a\x0fclipboard.proto\"\xd0\
+    \n\x14request_master.proto\x12\x06master\x1a\x0fclipboard.proto\"\xf4\
     \x04\n\rMasterMessage\x12\x12\n\x04rndB\x18\x01\x20\x01(\tR\x04rndB\x12:\
     \n\x0emouse_position\x18\x0b\x20\x01(\x0b2\x11.master.MouseMoveH\0R\rmou\
     sePosition\x12.\n\x08keyboard\x18\x02\x20\x01(\x0b2\x10.master.KeyboardH\
@@ -2593,28 +2770,31 @@ static file_descriptor_proto_data: &'static [u8] = b"\
     +\n\x07request\x18\x07\x20\x01(\x0e2\x0f.master.RequestH\0R\x07request\
     \x12,\n\thandshake\x18\t\x20\x01(\x0b2\x0c.master.EchoH\0R\thandshake\
     \x121\n\theartbeat\x18\n\x20\x01(\x0b2\x11.master.HeartbeatH\0R\theartbe\
-    at\x12\x12\n\x04rndE\x18\x0c\x20\x01(\tR\x04rndEB\x08\n\x06action\"Q\n\
+    at\x12\"\n\x04pong\x18\r\x20\x01(\x0b2\x0c.common.PingH\0R\x04pong\x12\
+    \x12\n\x04rndE\x18\x0c\x20\x01(\tR\x04rndEB\x08\n\x06action\"\x9c\x01\n\
     \x04Echo\x12/\n\x06result\x18\x01\x20\x01(\x0e2\x17.master.HandshakeResu\
-    ltR\x06result\x12\x18\n\x07message\x18\x02\x20\x01(\tR\x07message\"$\n\t\
-    Heartbeat\x12\x17\n\x07one_way\x18\x01\x20\x01(\x08R\x06oneWay\">\n\x0cS\
-    essionBegin\x12.\n\x13mouse_move_relative\x18\x01\x20\x01(\x08R\x11mouse\
-    MoveRelative\"\x0c\n\nSessionEnd\"J\n\x08Keyboard\x12\x10\n\x03key\x18\
-    \x01\x20\x01(\rR\x03key\x12,\n\nevent_type\x18\x02\x20\x01(\x0e2\r.maste\
-    r.StateR\teventType\"'\n\tMouseMove\x12\x0c\n\x01X\x18\x01\x20\x01(\x05R\
-    \x01X\x12\x0c\n\x01Y\x18\x02\x20\x01(\x05R\x01Y\"b\n\nMouseClick\x12&\n\
-    \x06button\x18\x01\x20\x01(\x0e2\x0e.master.ButtonR\x06button\x12,\n\nev\
-    ent_type\x18\x02\x20\x01(\x0e2\r.master.StateR\teventType\"b\n\nMouseWhe\
-    el\x12<\n\x10scroll_direction\x18\x01\x20\x01(\x0e2\x11.master.Direction\
-    R\x0fscrollDirection\x12\x16\n\x06amount\x18\x02\x20\x01(\x05R\x06amount\
-    *;\n\x07Request\x12\r\n\tINIT_INFO\x10\0\x12\x12\n\x0eMOUSE_POSITION\x10\
-    \x01\x12\r\n\tCLIPBOARD\x10\x02*A\n\x0fHandshakeResult\x12\x0b\n\x07Succ\
-    ess\x10\0\x12\x12\n\x0eSuccessWarning\x10\x01\x12\r\n\tRejection\x10\x02\
-    *N\n\tDirection\x12\r\n\tSCROLL_UP\x10\0\x12\x0f\n\x0bSCROLL_DOWN\x10\
-    \x01\x12\x0f\n\x0bSCROLL_LEFT\x10\x02\x12\x10\n\x0cSCROLL_RIGHT\x10\x03*\
-    E\n\x06Button\x12\x08\n\x04LEFT\x10\0\x12\t\n\x05RIGHT\x10\x01\x12\n\n\
-    \x06MIDDLE\x10\x02\x12\x0c\n\x08XBUTTON1\x10\x03\x12\x0c\n\x08XBUTTON2\
-    \x10\x04*\"\n\x05State\x12\x0b\n\x07PRESSED\x10\0\x12\x0c\n\x08RELEASED\
-    \x10\x01b\x06proto3\
+    ltR\x06result\x12\x18\n\x07message\x18\x02\x20\x01(\tR\x07message\x125\n\
+    \x0bcompression\x18\x03\x20\x01(\x0e2\x13.common.CompressionR\x0bcompres\
+    sion\x12\x12\n\x04ping\x18\x04\x20\x01(\x08R\x04ping\"B\n\tHeartbeat\x12\
+    \x17\n\x07one_way\x18\x01\x20\x01(\x08R\x06oneWay\x12\x1c\n\ttimestamp\
+    \x18\x02\x20\x01(\x04R\ttimestamp\">\n\x0cSessionBegin\x12.\n\x13mouse_m\
+    ove_relative\x18\x01\x20\x01(\x08R\x11mouseMoveRelative\"\x0c\n\nSession\
+    End\"J\n\x08Keyboard\x12\x10\n\x03key\x18\x01\x20\x01(\rR\x03key\x12,\n\
+    \nevent_type\x18\x02\x20\x01(\x0e2\r.master.StateR\teventType\"'\n\tMous\
+    eMove\x12\x0c\n\x01X\x18\x01\x20\x01(\x05R\x01X\x12\x0c\n\x01Y\x18\x02\
+    \x20\x01(\x05R\x01Y\"b\n\nMouseClick\x12&\n\x06button\x18\x01\x20\x01(\
+    \x0e2\x0e.master.ButtonR\x06button\x12,\n\nevent_type\x18\x02\x20\x01(\
+    \x0e2\r.master.StateR\teventType\"b\n\nMouseWheel\x12<\n\x10scroll_direc\
+    tion\x18\x01\x20\x01(\x0e2\x11.master.DirectionR\x0fscrollDirection\x12\
+    \x16\n\x06amount\x18\x02\x20\x01(\x05R\x06amount*;\n\x07Request\x12\r\n\
+    \tINIT_INFO\x10\0\x12\x12\n\x0eMOUSE_POSITION\x10\x01\x12\r\n\tCLIPBOARD\
+    \x10\x02*A\n\x0fHandshakeResult\x12\x0b\n\x07Success\x10\0\x12\x12\n\x0e\
+    SuccessWarning\x10\x01\x12\r\n\tRejection\x10\x02*N\n\tDirection\x12\r\n\
+    \tSCROLL_UP\x10\0\x12\x0f\n\x0bSCROLL_DOWN\x10\x01\x12\x0f\n\x0bSCROLL_L\
+    EFT\x10\x02\x12\x10\n\x0cSCROLL_RIGHT\x10\x03*E\n\x06Button\x12\x08\n\
+    \x04LEFT\x10\0\x12\t\n\x05RIGHT\x10\x01\x12\n\n\x06MIDDLE\x10\x02\x12\
+    \x0c\n\x08XBUTTON1\x10\x03\x12\x0c\n\x08XBUTTON2\x10\x04*\"\n\x05State\
+    \x12\x0b\n\x07PRESSED\x10\0\x12\x0c\n\x08RELEASED\x10\x01b\x06proto3\
 ";
 
 static file_descriptor_proto_lazy: ::protobuf::rt::LazyV2<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::rt::LazyV2::INIT;
@@ -44,6 +44,9 @@ pub enum SlaveMessage_oneof_response {
     cursor(Position),
     clipboard_session(super::clipboard::Clipboard),
     no_response(Failure),
+    goodbye(Goodbye),
+    ping(super::clipboard::Ping),
+    pong(super::clipboard::Ping),
 }
 
 impl SlaveMessage {
@@ -246,6 +249,153 @@ impl SlaveMessage {
             Failure::new()
         }
     }
+
+    // .slave.Goodbye goodbye = 5;
+
+
+    pub fn get_goodbye(&self) -> &Goodbye {
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::goodbye(ref v)) => v,
+            _ => <Goodbye as ::protobuf::Message>::default_instance(),
+        }
+    }
+    pub fn clear_goodbye(&mut self) {
+        self.response = ::std::option::Option::None;
+    }
+
+    pub fn has_goodbye(&self) -> bool {
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::goodbye(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_goodbye(&mut self, v: Goodbye) {
+        self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::goodbye(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_goodbye(&mut self) -> &mut Goodbye {
+        if let ::std::option::Option::Some(SlaveMessage_oneof_response::goodbye(_)) = self.response {
+        } else {
+            self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::goodbye(Goodbye::new()));
+        }
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::goodbye(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_goodbye(&mut self) -> Goodbye {
+        if self.has_goodbye() {
+            match self.response.take() {
+                ::std::option::Option::Some(SlaveMessage_oneof_response::goodbye(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            Goodbye::new()
+        }
+    }
+
+    // .common.Ping ping = 6;
+
+
+    pub fn get_ping(&self) -> &super::clipboard::Ping {
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::ping(ref v)) => v,
+            _ => <super::clipboard::Ping as ::protobuf::Message>::default_instance(),
+        }
+    }
+    pub fn clear_ping(&mut self) {
+        self.response = ::std::option::Option::None;
+    }
+
+    pub fn has_ping(&self) -> bool {
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::ping(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_ping(&mut self, v: super::clipboard::Ping) {
+        self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::ping(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_ping(&mut self) -> &mut super::clipboard::Ping {
+        if let ::std::option::Option::Some(SlaveMessage_oneof_response::ping(_)) = self.response {
+        } else {
+            self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::ping(super::clipboard::Ping::new()));
+        }
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::ping(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_ping(&mut self) -> super::clipboard::Ping {
+        if self.has_ping() {
+            match self.response.take() {
+                ::std::option::Option::Some(SlaveMessage_oneof_response::ping(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            super::clipboard::Ping::new()
+        }
+    }
+
+    // .common.Ping pong = 7;
+
+
+    pub fn get_pong(&self) -> &super::clipboard::Ping {
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::pong(ref v)) => v,
+            _ => <super::clipboard::Ping as ::protobuf::Message>::default_instance(),
+        }
+    }
+    pub fn clear_pong(&mut self) {
+        self.response = ::std::option::Option::None;
+    }
+
+    pub fn has_pong(&self) -> bool {
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::pong(..)) => true,
+            _ => false,
+        }
+    }
+
+    // Param is passed by value, moved
+    pub fn set_pong(&mut self, v: super::clipboard::Ping) {
+        self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::pong(v))
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_pong(&mut self) -> &mut super::clipboard::Ping {
+        if let ::std::option::Option::Some(SlaveMessage_oneof_response::pong(_)) = self.response {
+        } else {
+            self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::pong(super::clipboard::Ping::new()));
+        }
+        match self.response {
+            ::std::option::Option::Some(SlaveMessage_oneof_response::pong(ref mut v)) => v,
+            _ => panic!(),
+        }
+    }
+
+    // Take field
+    pub fn take_pong(&mut self) -> super::clipboard::Ping {
+        if self.has_pong() {
+            match self.response.take() {
+                ::std::option::Option::Some(SlaveMessage_oneof_response::pong(v)) => v,
+                _ => panic!(),
+            }
+        } else {
+            super::clipboard::Ping::new()
+        }
+    }
 }
 
 impl ::protobuf::Message for SlaveMessage {
@@ -270,6 +420,21 @@ impl ::protobuf::Message for SlaveMessage {
                 return false;
             }
         }
+        if let Some(SlaveMessage_oneof_response::goodbye(ref v)) = self.response {
+            if !v.is_initialized() {
+                return false;
+            }
+        }
+        if let Some(SlaveMessage_oneof_response::ping(ref v)) = self.response {
+            if !v.is_initialized() {
+                return false;
+            }
+        }
+        if let Some(SlaveMessage_oneof_response::pong(ref v)) = self.response {
+            if !v.is_initialized() {
+                return false;
+            }
+        }
         true
     }
 
@@ -301,6 +466,24 @@ impl ::protobuf::Message for SlaveMessage {
                     }
                     self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::no_response(is.read_message()?));
                 },
+                5 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::goodbye(is.read_message()?));
+                },
+                6 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::ping(is.read_message()?));
+                },
+                7 => {
+                    if wire_type != ::protobuf::wire_format::WireTypeLengthDelimited {
+                        return ::std::result::Result::Err(::protobuf::rt::unexpected_wire_type(wire_type));
+                    }
+                    self.response = ::std::option::Option::Some(SlaveMessage_oneof_response::pong(is.read_message()?));
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -331,6 +514,18 @@ impl ::protobuf::Message for SlaveMessage {
                     let len = v.compute_size();
                     my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
                 },
+                &SlaveMessage_oneof_response::goodbye(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+                },
+                &SlaveMessage_oneof_response::ping(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+                },
+                &SlaveMessage_oneof_response::pong(ref v) => {
+                    let len = v.compute_size();
+                    my_size += 1 + ::protobuf::rt::compute_raw_varint32_size(len) + len;
+                },
             };
         }
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
@@ -361,6 +556,21 @@ impl ::protobuf::Message for SlaveMessage {
                     os.write_raw_varint32(v.get_cached_size())?;
                     v.write_to_with_cached_sizes(os)?;
                 },
+                &SlaveMessage_oneof_response::goodbye(ref v) => {
+                    os.write_tag(5, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+                    os.write_raw_varint32(v.get_cached_size())?;
+                    v.write_to_with_cached_sizes(os)?;
+                },
+                &SlaveMessage_oneof_response::ping(ref v) => {
+                    os.write_tag(6, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+                    os.write_raw_varint32(v.get_cached_size())?;
+                    v.write_to_with_cached_sizes(os)?;
+                },
+                &SlaveMessage_oneof_response::pong(ref v) => {
+                    os.write_tag(7, ::protobuf::wire_format::WireTypeLengthDelimited)?;
+                    os.write_raw_varint32(v.get_cached_size())?;
+                    v.write_to_with_cached_sizes(os)?;
+                },
             };
         }
         os.write_unknown_fields(self.get_unknown_fields())?;
@@ -421,6 +631,21 @@ impl ::protobuf::Message for SlaveMessage {
                 SlaveMessage::has_no_response,
                 SlaveMessage::get_no_response,
             ));
+            fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, Goodbye>(
+                "goodbye",
+                SlaveMessage::has_goodbye,
+                SlaveMessage::get_goodbye,
+            ));
+            fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, super::clipboard::Ping>(
+                "ping",
+                SlaveMessage::has_ping,
+                SlaveMessage::get_ping,
+            ));
+            fields.push(::protobuf::reflect::accessor::make_singular_message_accessor::<_, super::clipboard::Ping>(
+                "pong",
+                SlaveMessage::has_pong,
+                SlaveMessage::get_pong,
+            ));
             ::protobuf::reflect::MessageDescriptor::new_pb_name::<SlaveMessage>(
                 "SlaveMessage",
                 fields,
@@ -437,6 +662,9 @@ impl ::protobuf::Message for SlaveMessage {
 
 impl ::protobuf::Clear for SlaveMessage {
     fn clear(&mut self) {
+        self.response = ::std::option::Option::None;
+        self.response = ::std::option::Option::None;
+        self.response = ::std::option::Option::None;
         self.response = ::std::option::Option::None;
         self.response = ::std::option::Option::None;
         self.response = ::std::option::Option::None;
@@ -616,6 +844,246 @@ impl ::protobuf::reflect::ProtobufValue for Failure {
     }
 }
 
+#[derive(PartialEq,Clone,Default)]
+pub struct Goodbye {
+    // message fields
+    pub Reason: Goodbye_Cause,
+    pub Description: ::std::string::String,
+    // special fields
+    pub unknown_fields: ::protobuf::UnknownFields,
+    pub cached_size: ::protobuf::CachedSize,
+}
+
+impl<'a> ::std::default::Default for &'a Goodbye {
+    fn default() -> &'a Goodbye {
+        <Goodbye as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl Goodbye {
+    pub fn new() -> Goodbye {
+        ::std::default::Default::default()
+    }
+
+    // .slave.Goodbye.Cause Reason = 1;
+
+
+    pub fn get_Reason(&self) -> Goodbye_Cause {
+        self.Reason
+    }
+    pub fn clear_Reason(&mut self) {
+        self.Reason = Goodbye_Cause::USER_EXIT;
+    }
+
+    // Param is passed by value, moved
+    pub fn set_Reason(&mut self, v: Goodbye_Cause) {
+        self.Reason = v;
+    }
+
+    // string Description = 2;
+
+
+    pub fn get_Description(&self) -> &str {
+        &self.Description
+    }
+    pub fn clear_Description(&mut self) {
+        self.Description.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_Description(&mut self, v: ::std::string::String) {
+        self.Description = v;
+    }
+
+    // Mutable pointer to the field.
+    // If field is not initialized, it is initialized with default value first.
+    pub fn mut_Description(&mut self) -> &mut ::std::string::String {
+        &mut self.Description
+    }
+
+    // Take field
+    pub fn take_Description(&mut self) -> ::std::string::String {
+        ::std::mem::replace(&mut self.Description, ::std::string::String::new())
+    }
+}
+
+impl ::protobuf::Message for Goodbye {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        while !is.eof()? {
+            let (field_number, wire_type) = is.read_tag_unpack()?;
+            match field_number {
+                1 => {
+                    ::protobuf::rt::read_proto3_enum_with_unknown_fields_into(wire_type, is, &mut self.Reason, 1, &mut self.unknown_fields)?
+                },
+                2 => {
+                    ::protobuf::rt::read_singular_proto3_string_into(wire_type, is, &mut self.Description)?;
+                },
+                _ => {
+                    ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u32 {
+        let mut my_size = 0;
+        if self.Reason != Goodbye_Cause::USER_EXIT {
+            my_size += ::protobuf::rt::enum_size(1, self.Reason);
+        }
+        if !self.Description.is_empty() {
+            my_size += ::protobuf::rt::string_size(2, &self.Description);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
+        self.cached_size.set(my_size);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::ProtobufResult<()> {
+        if self.Reason != Goodbye_Cause::USER_EXIT {
+            os.write_enum(1, ::protobuf::ProtobufEnum::value(&self.Reason))?;
+        }
+        if !self.Description.is_empty() {
+            os.write_string(2, &self.Description)?;
+        }
+        os.write_unknown_fields(self.get_unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn get_cached_size(&self) -> u32 {
+        self.cached_size.get()
+    }
+
+    fn get_unknown_fields(&self) -> &::protobuf::UnknownFields {
+        &self.unknown_fields
+    }
+
+    fn mut_unknown_fields(&mut self) -> &mut ::protobuf::UnknownFields {
+        &mut self.unknown_fields
+    }
+
+    fn as_any(&self) -> &dyn (::std::any::Any) {
+        self as &dyn (::std::any::Any)
+    }
+    fn as_any_mut(&mut self) -> &mut dyn (::std::any::Any) {
+        self as &mut dyn (::std::any::Any)
+    }
+    fn into_any(self: ::std::boxed::Box<Self>) -> ::std::boxed::Box<dyn (::std::any::Any)> {
+        self
+    }
+
+    fn descriptor(&self) -> &'static ::protobuf::reflect::MessageDescriptor {
+        Self::descriptor_static()
+    }
+
+    fn new() -> Goodbye {
+        Goodbye::new()
+    }
+
+    fn descriptor_static() -> &'static ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::LazyV2<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::LazyV2::INIT;
+        descriptor.get(|| {
+            let mut fields = ::std::vec::Vec::new();
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeEnum<Goodbye_Cause>>(
+                "Reason",
+                |m: &Goodbye| { &m.Reason },
+                |m: &mut Goodbye| { &mut m.Reason },
+            ));
+            fields.push(::protobuf::reflect::accessor::make_simple_field_accessor::<_, ::protobuf::types::ProtobufTypeString>(
+                "Description",
+                |m: &Goodbye| { &m.Description },
+                |m: &mut Goodbye| { &mut m.Description },
+            ));
+            ::protobuf::reflect::MessageDescriptor::new_pb_name::<Goodbye>(
+                "Goodbye",
+                fields,
+                file_descriptor_proto()
+            )
+        })
+    }
+
+    fn default_instance() -> &'static Goodbye {
+        static instance: ::protobuf::rt::LazyV2<Goodbye> = ::protobuf::rt::LazyV2::INIT;
+        instance.get(Goodbye::new)
+    }
+}
+
+impl ::protobuf::Clear for Goodbye {
+    fn clear(&mut self) {
+        self.Reason = Goodbye_Cause::USER_EXIT;
+        self.Description.clear();
+        self.unknown_fields.clear();
+    }
+}
+
+impl ::std::fmt::Debug for Goodbye {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Goodbye {
+    fn as_ref(&self) -> ::protobuf::reflect::ReflectValueRef {
+        ::protobuf::reflect::ReflectValueRef::Message(self)
+    }
+}
+
+#[derive(Clone,PartialEq,Eq,Debug,Hash)]
+pub enum Goodbye_Cause {
+    USER_EXIT = 0,
+    TERMINATED = 1,
+}
+
+impl ::protobuf::ProtobufEnum for Goodbye_Cause {
+    fn value(&self) -> i32 {
+        *self as i32
+    }
+
+    fn from_i32(value: i32) -> ::std::option::Option<Goodbye_Cause> {
+        match value {
+            0 => ::std::option::Option::Some(Goodbye_Cause::USER_EXIT),
+            1 => ::std::option::Option::Some(Goodbye_Cause::TERMINATED),
+            _ => ::std::option::Option::None
+        }
+    }
+
+    fn values() -> &'static [Self] {
+        static values: &'static [Goodbye_Cause] = &[
+            Goodbye_Cause::USER_EXIT,
+            Goodbye_Cause::TERMINATED,
+        ];
+        values
+    }
+
+    fn enum_descriptor_static() -> &'static ::protobuf::reflect::EnumDescriptor {
+        static descriptor: ::protobuf::rt::LazyV2<::protobuf::reflect::EnumDescriptor> = ::protobuf::rt::LazyV2::INIT;
+        descriptor.get(|| {
+            ::protobuf::reflect::EnumDescriptor::new_pb_name::<Goodbye_Cause>("Goodbye.Cause", file_descriptor_proto())
+        })
+    }
+}
+
+impl ::std::marker::Copy for Goodbye_Cause {
+}
+
+impl ::std::default::Default for Goodbye_Cause {
+    fn default() -> Self {
+        Goodbye_Cause::USER_EXIT
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for Goodbye_Cause {
+    fn as_ref(&self) -> ::protobuf::reflect::ReflectValueRef {
+        ::protobuf::reflect::ReflectValueRef::Enum(::protobuf::ProtobufEnum::descriptor(self))
+    }
+}
+
 #[derive(PartialEq,Clone,Default)]
 pub struct ClientInfo {
     // message fields
@@ -626,6 +1094,7 @@ pub struct ClientInfo {
     pub Guid: ::protobuf::SingularPtrField<ClientInfo_UUID>,
     pub Name: ::std::string::String,
     pub System: ClientInfo_OS,
+    pub Compression: ::std::vec::Vec<super::clipboard::Compression>,
     // special fields
     pub unknown_fields: ::protobuf::UnknownFields,
     pub cached_size: ::protobuf::CachedSize,
@@ -804,6 +1273,31 @@ impl ClientInfo {
     pub fn set_System(&mut self, v: ClientInfo_OS) {
         self.System = v;
     }
+
+    // repeated .common.Compression Compression = 8;
+
+
+    pub fn get_Compression(&self) -> &[super::clipboard::Compression] {
+        &self.Compression
+    }
+    pub fn clear_Compression(&mut self) {
+        self.Compression.clear();
+    }
+
+    // Param is passed by value, moved
+    pub fn set_Compression(&mut self, v: ::std::vec::Vec<super::clipboard::Compression>) {
+        self.Compression = v;
+    }
+
+    // Mutable pointer to the field.
+    pub fn mut_Compression(&mut self) -> &mut ::std::vec::Vec<super::clipboard::Compression> {
+        &mut self.Compression
+    }
+
+    // Take field
+    pub fn take_Compression(&mut self) -> ::std::vec::Vec<super::clipboard::Compression> {
+        ::std::mem::replace(&mut self.Compression, ::std::vec::Vec::new())
+    }
 }
 
 impl ::protobuf::Message for ClientInfo {
@@ -854,6 +1348,9 @@ impl ::protobuf::Message for ClientInfo {
                 4 => {
                     ::protobuf::rt::read_proto3_enum_with_unknown_fields_into(wire_type, is, &mut self.System, 4, &mut self.unknown_fields)?
                 },
+                8 => {
+                    ::protobuf::rt::read_repeated_enum_with_unknown_fields_into(wire_type, is, &mut self.Compression, 8, &mut self.unknown_fields)?
+                },
                 _ => {
                     ::protobuf::rt::read_unknown_or_skip_group(field_number, wire_type, is, self.mut_unknown_fields())?;
                 },
@@ -889,6 +1386,9 @@ impl ::protobuf::Message for ClientInfo {
         if self.System != ClientInfo_OS::WINDOWS {
             my_size += ::protobuf::rt::enum_size(4, self.System);
         }
+        for value in &self.Compression {
+            my_size += ::protobuf::rt::enum_size(8, *value);
+        };
         my_size += ::protobuf::rt::unknown_fields_size(self.get_unknown_fields());
         self.cached_size.set(my_size);
         my_size
@@ -920,6 +1420,9 @@ impl ::protobuf::Message for ClientInfo {
         if self.System != ClientInfo_OS::WINDOWS {
             os.write_enum(4, ::protobuf::ProtobufEnum::value(&self.System))?;
         }
+        for v in &self.Compression {
+            os.write_enum(8, ::protobuf::ProtobufEnum::value(v))?;
+        };
         os.write_unknown_fields(self.get_unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -993,6 +1496,11 @@ impl ::protobuf::Message for ClientInfo {
                 |m: &ClientInfo| { &m.System },
                 |m: &mut ClientInfo| { &mut m.System },
             ));
+            fields.push(::protobuf::reflect::accessor::make_vec_accessor::<_, ::protobuf::types::ProtobufTypeEnum<super::clipboard::Compression>>(
+                "Compression",
+                |m: &ClientInfo| { &m.Compression },
+                |m: &mut ClientInfo| { &mut m.Compression },
+            ));
             ::protobuf::reflect::MessageDescriptor::new_pb_name::<ClientInfo>(
                 "ClientInfo",
                 fields,
@@ -1016,6 +1524,7 @@ impl ::protobuf::Clear for ClientInfo {
         self.Guid.clear();
         self.Name.clear();
         self.System = ClientInfo_OS::WINDOWS;
+        self.Compression.clear();
         self.unknown_fields.clear();
     }
 }
@@ -1432,23 +1941,31 @@ impl ::protobuf::reflect::ProtobufValue for Position {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x14response_slave.proto\x12\x05slave\x1a\x0fclipboard.proto\"\xec\x01\
+    \n\x14response_slave.proto\x12\x05slave\x1a\x0fclipboard.proto\"\xe0\x02\
     \n\x0cSlaveMessage\x120\n\tinit_info\x18\x01\x20\x01(\x0b2\x11.slave.Cli\
     entInfoH\0R\x08initInfo\x12)\n\x06cursor\x18\x02\x20\x01(\x0b2\x0f.slave\
     .PositionH\0R\x06cursor\x12@\n\x11clipboard_session\x18\x03\x20\x01(\x0b\
     2\x11.common.ClipboardH\0R\x10clipboardSession\x121\n\x0bno_response\x18\
-    \x04\x20\x01(\x0b2\x0e.slave.FailureH\0R\nnoResponseB\n\n\x08response\"!\
-    \n\x07Failure\x12\x16\n\x06Reason\x18\x01\x20\x01(\tR\x06Reason\"\xb2\
-    \x02\n\nClientInfo\x12\x14\n\x05Width\x18\x01\x20\x01(\x05R\x05Width\x12\
-    \x16\n\x06Height\x18\x02\x20\x01(\x05R\x06Height\x12'\n\x06Cursor\x18\
-    \x06\x20\x01(\x0b2\x0f.slave.PositionR\x06Cursor\x12\x1a\n\x08Password\
-    \x18\x07\x20\x01(\tR\x08Password\x12*\n\x04Guid\x18\x05\x20\x01(\x0b2\
+    \x04\x20\x01(\x0b2\x0e.slave.FailureH\0R\nnoResponse\x12*\n\x07goodbye\
+    \x18\x05\x20\x01(\x0b2\x0e.slave.GoodbyeH\0R\x07goodbye\x12\"\n\x04ping\
+    \x18\x06\x20\x01(\x0b2\x0c.common.PingH\0R\x04ping\x12\"\n\x04pong\x18\
+    \x07\x20\x01(\x0b2\x0c.common.PingH\0R\x04pongB\n\n\x08response\"!\n\x07\
+    Failure\x12\x16\n\x06Reason\x18\x01\x20\x01(\tR\x06Reason\"\x81\x01\n\
+    \x07Goodbye\x12,\n\x06Reason\x18\x01\x20\x01(\x0e2\x14.slave.Goodbye.Cau\
+    seR\x06Reason\x12\x20\n\x0bDescription\x18\x02\x20\x01(\tR\x0bDescriptio\
+    n\"&\n\x05Cause\x12\r\n\tUSER_EXIT\x10\0\x12\x0e\n\nTERMINATED\x10\x01\"\
+    \xe9\x02\n\nClientInfo\x12\x14\n\x05Width\x18\x01\x20\x01(\x05R\x05Width\
+    \x12\x16\n\x06Height\x18\x02\x20\x01(\x05R\x06Height\x12'\n\x06Cursor\
+    \x18\x06\x20\x01(\x0b2\x0f.slave.PositionR\x06Cursor\x12\x1a\n\x08Passwo\
+    rd\x18\x07\x20\x01(\tR\x08Password\x12*\n\x04Guid\x18\x05\x20\x01(\x0b2\
     \x16.slave.ClientInfo.UUIDR\x04Guid\x12\x12\n\x04Name\x18\x03\x20\x01(\t\
     R\x04Name\x12,\n\x06System\x18\x04\x20\x01(\x0e2\x14.slave.ClientInfo.OS\
-    R\x06System\x1a\x1c\n\x04UUID\x12\x14\n\x05value\x18\x01\x20\x01(\tR\x05\
-    value\"%\n\x02OS\x12\x0b\n\x07WINDOWS\x10\0\x12\t\n\x05LINUX\x10\x01\x12\
-    \x07\n\x03MAC\x10\x02\"&\n\x08Position\x12\x0c\n\x01x\x18\x01\x20\x01(\
-    \x05R\x01x\x12\x0c\n\x01y\x18\x02\x20\x01(\x05R\x01yb\x06proto3\
+    R\x06System\x125\n\x0bCompression\x18\x08\x20\x03(\x0e2\x13.common.Compr\
+    essionR\x0bCompression\x1a\x1c\n\x04UUID\x12\x14\n\x05value\x18\x01\x20\
+    \x01(\tR\x05value\"%\n\x02OS\x12\x0b\n\x07WINDOWS\x10\0\x12\t\n\x05LINUX\
+    \x10\x01\x12\x07\n\x03MAC\x10\x02\"&\n\x08Position\x12\x0c\n\x01x\x18\
+    \x01\x20\x01(\x05R\x01x\x12\x0c\n\x01y\x18\x02\x20\x01(\x05R\x01yb\x06pr\
+    oto3\
 ";
 
 static file_descriptor_proto_lazy: ::protobuf::rt::LazyV2<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::rt::LazyV2::INIT;
@@ -1,40 +1,45 @@
 use super::KeyPair;
+use crate::error::CryptoError;
 use rand_core::OsRng;
-use std::io::{Error, Read, Write};
-use std::net::TcpStream;
+use std::io::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use x25519_dalek::{EphemeralSecret, PublicKey, SharedSecret as SharedSecret32};
 
-pub fn key_nonce_agreement(stream: &mut TcpStream) -> Result<KeyPair, Error> {
-    let key = match establish(stream) {
-        Ok(shared) => shared.as_bytes().to_owned(),
-        Err(e) => return Err(e),
-    };
-    let nonce = match establish(stream) {
-        Ok(shared) => shared.as_bytes().to_owned(),
-        Err(e) => return Err(e),
-    };
-    //info!("   SHARED key: {:?}", key);
-    //info!("   SHARED nonce: {:?}", nonce);
+/// Bytes each side writes during `get_secrets_chacha`: two key pairs of two public keys.
+pub const EXCHANGED_BYTES: usize = 4 * 32;
+
+pub async fn key_nonce_agreement<S>(stream: &mut S) -> Result<KeyPair, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let key = establish(stream).await?.as_bytes().to_owned();
+    let nonce = establish(stream).await?.as_bytes().to_owned();
     Ok(KeyPair { key, nonce })
 }
 
-pub fn establish(stream: &mut TcpStream) -> Result<SharedSecret32, Error> {
+pub async fn establish<S>(stream: &mut S) -> Result<SharedSecret32, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let my_secret = EphemeralSecret::new(OsRng);
     let my_public = PublicKey::from(&my_secret);
-    stream.write_all(my_public.as_bytes())?;
-    stream.flush()?;
+    stream.write_all(my_public.as_bytes()).await?;
+    stream.flush().await?;
     let mut bob_public: [u8; 32] = [0; 32];
-    stream.read_exact(&mut bob_public)?;
+    stream.read_exact(&mut bob_public).await?;
     Ok(my_secret.diffie_hellman(&PublicKey::from(bob_public)))
 }
 
-pub fn get_secrets_chacha(stream: &mut TcpStream) -> Option<(super::ChaChaKey, super::ChaChaKey)> {
-    let m_r = crate::security::key_exchange::key_nonce_agreement(stream);
-    let s_r = crate::security::key_exchange::key_nonce_agreement(stream);
-    if let (Ok(m), Ok(s)) = (m_r, s_r) {
-        let master = super::ChaChaKey::from(m);
-        let slave = super::ChaChaKey::from(s);
-        return Some((master, slave));
-    }
-    None
+pub async fn get_secrets_chacha<S>(
+    stream: &mut S,
+) -> Result<(super::ChaChaKey, super::ChaChaKey), CryptoError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let exchange = |e: Error| CryptoError::KeyExchange(e.into());
+    let m = key_nonce_agreement(stream).await.map_err(exchange)?;
+    let s = key_nonce_agreement(stream).await.map_err(exchange)?;
+    let master = super::ChaChaKey::from(m);
+    let slave = super::ChaChaKey::from(s);
+    Ok((master, slave))
 }
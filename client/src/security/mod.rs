@@ -1,4 +1,5 @@
 pub mod key_exchange;
+pub mod peer_auth;
 pub mod stream_cipher;
 pub use stream_cipher::Decryptor;
 pub use stream_cipher::Encryptor;
@@ -0,0 +1,107 @@
+//! Proof that the peer of a reverse connection knows the server password.
+//!
+//! Anyone can run the key exchange, so in reverse mode the client sends a random
+//! challenge right after it and waits for the HMAC-SHA256 of the challenge and the
+//! exchanged keys, keyed by the password. Only then is `INIT_INFO`, which carries
+//! the password, answered. The proof is bound to the keys of the connection and cannot
+//! be relayed to another one. The C# server does not dial out yet and has no side of it.
+use super::ChaChaKey;
+use crate::error::CryptoError;
+use hmac::{Hmac, Mac, NewMac};
+use rand::RngCore;
+use sha2::Sha256;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const CONTEXT: &[u8] = b"jerry reverse connection v1";
+const CHALLENGE_LEN: usize = 32;
+const PROOF_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Client side: fails with `CryptoError::PeerNotAuthenticated` on a wrong answer.
+pub async fn verify_peer<S>(
+    stream: &mut S,
+    password: &str,
+    keys: &(ChaChaKey, ChaChaKey),
+) -> Result<(), CryptoError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let exchange = |e: std::io::Error| CryptoError::KeyExchange(e.into());
+    let mut challenge = [0u8; CHALLENGE_LEN];
+    rand::thread_rng().fill_bytes(&mut challenge);
+    stream.write_all(&challenge).await.map_err(exchange)?;
+    stream.flush().await.map_err(exchange)?;
+    let mut proof = [0u8; PROOF_LEN];
+    stream.read_exact(&mut proof).await.map_err(exchange)?;
+    mac(password, &challenge, keys)
+        .verify(&proof)
+        .map_err(|_| CryptoError::PeerNotAuthenticated)
+}
+
+/// Server side of `verify_peer`.
+pub async fn answer_challenge<S>(
+    stream: &mut S,
+    password: &str,
+    keys: &(ChaChaKey, ChaChaKey),
+) -> Result<(), CryptoError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let exchange = |e: std::io::Error| CryptoError::KeyExchange(e.into());
+    let mut challenge = [0u8; CHALLENGE_LEN];
+    stream.read_exact(&mut challenge).await.map_err(exchange)?;
+    let proof = mac(password, &challenge, keys).finalize().into_bytes();
+    stream.write_all(&proof).await.map_err(exchange)?;
+    stream.flush().await.map_err(exchange)
+}
+
+fn mac(password: &str, challenge: &[u8], keys: &(ChaChaKey, ChaChaKey)) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(password.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(CONTEXT);
+    mac.update(challenge);
+    for key in [&keys.0, &keys.1] {
+        mac.update(&key.key);
+        mac.update(&key.nonce);
+    }
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(seed: u8) -> (ChaChaKey, ChaChaKey) {
+        let key = ChaChaKey {
+            key: [seed; 32],
+            nonce: [seed; 12],
+        };
+        (key, key)
+    }
+
+    async fn verify(client: (&str, u8), server: (&'static str, u8)) -> Result<(), CryptoError> {
+        let (mut client_side, mut server_side) = tokio::io::duplex(256);
+        let server_keys = keys(server.1);
+        let peer = tokio::spawn(async move {
+            _ = answer_challenge(&mut server_side, server.0, &server_keys).await;
+        });
+        let verified = verify_peer(&mut client_side, client.0, &keys(client.1)).await;
+        peer.await.unwrap();
+        verified
+    }
+
+    #[tokio::test]
+    async fn the_peer_proves_the_password() {
+        assert_eq!(verify(("2002", 1), ("2002", 1)).await, Ok(()));
+        assert_eq!(
+            verify(("2002", 1), ("guess", 1)).await,
+            Err(CryptoError::PeerNotAuthenticated)
+        );
+        // relayed from a connection with other keys
+        assert_eq!(
+            verify(("2002", 1), ("2002", 2)).await,
+            Err(CryptoError::PeerNotAuthenticated)
+        );
+    }
+}
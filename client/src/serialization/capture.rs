@@ -0,0 +1,268 @@
+use crate::proto_rs::{ProtoInMsg, ProtoOutMsg};
+use eyre::{eyre, Context, Result};
+use protobuf::Message;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+
+/// Start of a capture file, followed by the version
+const MAGIC: &[u8; 8] = b"JERRYCAP";
+const VERSION: u8 = 1;
+/// Upper bound of a captured message, as for the messages read from the server
+const MAX_RECORD_SIZE: usize = super::MAX_MESSAGE_SIZE;
+
+/// What a record of the capture holds.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    /// A connection to a server was established, the following messages belong to it
+    Connected,
+    /// Message of the server, decrypted
+    Received(ProtoInMsg),
+    /// Message of the client, before encryption
+    Sent(ProtoOutMsg),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Time elapsed since the capture was started
+    pub at: Duration,
+    pub entry: Entry,
+}
+
+const CONNECTED: u8 = 0;
+const RECEIVED: u8 = 1;
+const SENT: u8 = 2;
+
+/// Writes the traffic of every connection of a session into a file.
+///
+/// A record is a kind byte, the timestamp in microseconds (u64 LE), the length of
+/// the message (u32 LE) and the message itself. The file is written by a thread of
+/// its own and flushed whenever no record is waiting; clones share the file, which is
+/// complete once the last clone is dropped.
+#[derive(Clone)]
+pub struct Capture {
+    inner: Arc<CaptureInner>,
+}
+
+struct CaptureInner {
+    epoch: Instant,
+    records: Option<Sender<(u8, Duration, Vec<u8>)>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl Capture {
+    /// Truncates the file if it exists.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .wrap_err(format!("Capture file {} cannot be created", path.display()))?;
+        let mut file = BufWriter::new(file);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        let (sender, receiver) = mpsc::channel();
+        let writer = std::thread::spawn(move || {
+            if let Err(e) = write_records(file, receiver) {
+                error!("Capture stopped: {}", e);
+            }
+        });
+        Ok(Self {
+            inner: Arc::new(CaptureInner {
+                epoch: Instant::now(),
+                records: Some(sender),
+                writer: Some(writer),
+            }),
+        })
+    }
+
+    pub fn connected(&self) {
+        self.record(CONNECTED, Vec::new());
+    }
+
+    pub fn received(&self, msg: &ProtoInMsg) {
+        self.encode(RECEIVED, msg);
+    }
+
+    /// The password of the client info is left out, captures end up in bug reports.
+    pub fn sent(&self, msg: &ProtoOutMsg) {
+        if msg.has_init_info() {
+            let mut msg = msg.clone();
+            msg.mut_init_info().clear_Password();
+            return self.encode(SENT, &msg);
+        }
+        self.encode(SENT, msg);
+    }
+
+    fn encode<M: Message>(&self, kind: u8, msg: &M) {
+        match msg.write_to_bytes() {
+            Ok(bytes) => self.record(kind, bytes),
+            Err(e) => warn!("Message not captured: {}", e),
+        }
+    }
+
+    fn record(&self, kind: u8, bytes: Vec<u8>) {
+        let at = self.inner.epoch.elapsed();
+        if let Some(records) = self.inner.records.as_ref() {
+            _ = records.send((kind, at, bytes));
+        }
+    }
+}
+
+impl Drop for CaptureInner {
+    fn drop(&mut self) {
+        // the writer flushes and ends once the channel is closed
+        self.records.take();
+        if let Some(writer) = self.writer.take() {
+            _ = writer.join();
+        }
+    }
+}
+
+fn write_records(
+    mut file: BufWriter<File>,
+    records: Receiver<(u8, Duration, Vec<u8>)>,
+) -> std::io::Result<()> {
+    while let Ok(record) = records.recv() {
+        write_record(&mut file, record)?;
+        while let Ok(record) = records.try_recv() {
+            write_record(&mut file, record)?;
+        }
+        file.flush()?;
+    }
+    file.flush()
+}
+
+fn write_record(
+    file: &mut impl Write,
+    (kind, at, bytes): (u8, Duration, Vec<u8>),
+) -> std::io::Result<()> {
+    file.write_all(&[kind])?;
+    file.write_all(&(at.as_micros() as u64).to_le_bytes())?;
+    file.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    file.write_all(&bytes)
+}
+
+/// Reads the records of a capture file in order.
+pub struct CaptureReader<R> {
+    inner: R,
+    /// The end of the file or an error was reached
+    done: bool,
+}
+
+impl CaptureReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).wrap_err(format!("Capture file {} cannot be read", path.display()))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Fails unless the capture starts with the header of a supported version.
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut header = [0u8; MAGIC.len() + 1];
+        inner
+            .read_exact(&mut header)
+            .wrap_err("Not a capture file")?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(eyre!("Not a capture file"));
+        }
+        if header[MAGIC.len()] != VERSION {
+            return Err(eyre!(
+                "Capture version {} is not supported",
+                header[MAGIC.len()]
+            ));
+        }
+        Ok(Self { inner, done: false })
+    }
+
+    /// `None` at the end of the file. A record cut short, e.g. by a crash, ends the capture.
+    fn read_record(&mut self) -> Result<Option<Record>> {
+        let mut kind = [0u8; 1];
+        match self.inner.read_exact(&mut kind) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let mut header = [0u8; 12];
+        self.inner.read_exact(&mut header)?;
+        let at = Duration::from_micros(u64::from_le_bytes(header[..8].try_into()?));
+        let len = u32::from_le_bytes(header[8..].try_into()?) as usize;
+        if len > MAX_RECORD_SIZE {
+            return Err(eyre!("Record of {} bytes exceeds the limit", len));
+        }
+        let mut bytes = vec![0u8; len];
+        self.inner.read_exact(&mut bytes)?;
+        let entry = match kind[0] {
+            CONNECTED => Entry::Connected,
+            RECEIVED => Entry::Received(ProtoInMsg::parse_from_bytes(&bytes)?),
+            SENT => Entry::Sent(ProtoOutMsg::parse_from_bytes(&bytes)?),
+            other => return Err(eyre!("Unknown record kind {}", other)),
+        };
+        Ok(Some(Record { at, entry }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read_record().transpose();
+        self.done = !matches!(record, Some(Ok(_)));
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{JerryMessage, JerryResponse, State};
+
+    #[test]
+    fn records_are_read_back_in_order() {
+        let path = std::env::temp_dir().join(format!("jerry-capture-{}", std::process::id()));
+        let received = ProtoInMsg::from(JerryMessage::Key(0x41, State::PRESSED));
+        let sent = ProtoOutMsg::from(JerryResponse::Cursor(10, 20));
+        {
+            let capture = Capture::create(&path).unwrap();
+            let clone = capture.clone();
+            capture.connected();
+            capture.received(&received);
+            clone.sent(&sent);
+        }
+        let records = CaptureReader::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let entries: Vec<_> = records.iter().map(|r| r.entry.clone()).collect();
+        assert_eq!(
+            entries,
+            vec![
+                Entry::Connected,
+                Entry::Received(received),
+                Entry::Sent(sent)
+            ]
+        );
+        assert!(records.windows(2).all(|w| w[0].at <= w[1].at));
+    }
+
+    #[test]
+    fn truncated_capture_ends_with_an_error() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend([RECEIVED, 0, 0]);
+        let mut reader = CaptureReader::new(bytes.as_slice()).unwrap();
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+
+        assert!(CaptureReader::new(&b"JERRYCAP\x02"[..]).is_err());
+        assert!(CaptureReader::new(&b"not a capture"[..]).is_err());
+    }
+}
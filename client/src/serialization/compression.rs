@@ -0,0 +1,172 @@
+use crate::proto_rs::{proto_in::MasterMessage_oneof_action as MsgType, ProtoInMsg, ProtoOutMsg};
+use crate::proto_rs::{Clipboard, Compression};
+use eyre::{eyre, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Clipboard payloads shorter than this are always sent uncompressed.
+const COMPRESSION_THRESHOLD: usize = 1024;
+/// Upper bound for decompressed content, protects against decompression bombs.
+const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Compression of `common.Clipboard` payloads negotiated per session.
+///
+/// The client announces the supported algorithms in `ClientInfo`, the server selects one
+/// in the handshake `Echo`. Input events are never compressed.
+/// Clones share the negotiated algorithm, the reader decodes and the writer encodes.
+#[derive(Clone)]
+pub struct ClipboardCodec {
+    negotiated: Arc<Mutex<Compression>>,
+}
+
+impl ClipboardCodec {
+    pub fn new() -> Self {
+        Self {
+            negotiated: Arc::new(Mutex::new(Compression::NONE)),
+        }
+    }
+
+    fn negotiated(&self) -> Compression {
+        self.negotiated
+            .lock()
+            .map_or(Compression::NONE, |negotiated| *negotiated)
+    }
+
+    pub fn supported() -> Vec<Compression> {
+        vec![Compression::DEFLATE]
+    }
+
+    pub fn decode(&mut self, msg: &mut ProtoInMsg) -> Result<()> {
+        match msg.action.as_mut() {
+            Some(MsgType::handshake(echo)) => {
+                let negotiated = match Self::supported().contains(&echo.compression) {
+                    true => echo.compression,
+                    false => Compression::NONE,
+                };
+                if let Ok(mut current) = self.negotiated.lock() {
+                    *current = negotiated;
+                }
+                debug!("Clipboard compression: {:?}", negotiated);
+                Ok(())
+            }
+            Some(MsgType::clipboard(clip)) => decompress(clip),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn encode(&self, msg: &mut ProtoOutMsg) -> Result<()> {
+        let negotiated = self.negotiated();
+        if !msg.has_clipboard_session() || negotiated == Compression::NONE {
+            return Ok(());
+        }
+        compress(msg.mut_clipboard_session(), negotiated)
+    }
+}
+
+impl Default for ClipboardCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn compress(clip: &mut Clipboard, compression: Compression) -> Result<()> {
+    if clip.message.len() < COMPRESSION_THRESHOLD {
+        return Ok(());
+    }
+    let compressed = match compression {
+        Compression::NONE => return Ok(()),
+        Compression::DEFLATE => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+            encoder.write_all(clip.message.as_bytes())?;
+            encoder.finish()?
+        }
+    };
+    if compressed.len() < clip.message.len() {
+        debug!(
+            "Clipboard compressed: {} -> {} bytes",
+            clip.message.len(),
+            compressed.len()
+        );
+        clip.clear_message();
+        clip.set_compressed(compressed);
+        clip.set_compression(compression);
+    }
+    Ok(())
+}
+
+fn decompress(clip: &mut Clipboard) -> Result<()> {
+    let content = match clip.compression {
+        Compression::NONE => return Ok(()),
+        Compression::DEFLATE => inflate(&clip.take_compressed(), MAX_DECOMPRESSED_SIZE)?,
+    };
+    clip.set_message(content);
+    clip.set_compression(Compression::NONE);
+    Ok(())
+}
+
+/// Content longer than `limit` is an error, it is never truncated.
+fn inflate(compressed: &[u8], limit: u64) -> Result<String> {
+    let mut decoder = DeflateDecoder::new(compressed).take(limit + 1);
+    let mut content = String::new();
+    decoder
+        .read_to_string(&mut content)
+        .map_err(|e| eyre!("Clipboard decompression failed: {}", e))?;
+    if content.len() as u64 > limit {
+        return Err(eyre!(
+            "Clipboard decompression failed: content exceeds {} bytes",
+            limit
+        ));
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clipboard(message: &str) -> Clipboard {
+        let mut clip = Clipboard::new();
+        clip.set_message(message.to_owned());
+        clip
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "jerry ".repeat(1000);
+        let mut clip = clipboard(&text);
+        compress(&mut clip, Compression::DEFLATE).unwrap();
+        assert_eq!(clip.compression, Compression::DEFLATE);
+        assert!(clip.message.is_empty());
+        assert!(clip.compressed.len() < text.len());
+
+        decompress(&mut clip).unwrap();
+        assert_eq!(clip.compression, Compression::NONE);
+        assert_eq!(clip.message, text);
+    }
+
+    #[test]
+    fn short_content_is_not_compressed() {
+        let text = "a".repeat(COMPRESSION_THRESHOLD - 1);
+        let mut clip = clipboard(&text);
+        compress(&mut clip, Compression::DEFLATE).unwrap();
+        assert_eq!(clip.compression, Compression::NONE);
+        assert_eq!(clip.message, text);
+    }
+
+    #[test]
+    fn oversized_content_is_rejected() {
+        let mut clip = clipboard(&"a".repeat(4096));
+        compress(&mut clip, Compression::DEFLATE).unwrap();
+        let compressed = clip.take_compressed();
+
+        assert_eq!(inflate(&compressed, 4096).unwrap().len(), 4096);
+        let e = inflate(&compressed, 4095).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Clipboard decompression failed: content exceeds 4095 bytes"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use super::{decode_varint, MAX_MESSAGE_SIZE};
+use crate::core::replay::describe_sent;
+use crate::emulation::key_name;
+use crate::proto_rs::clipboard::Clipboard;
+use crate::proto_rs::proto_in::MasterMessage_oneof_action as MsgType;
+use crate::proto_rs::{ProtoInMsg, ProtoOutMsg};
+use crate::security::key_exchange::EXCHANGED_BYTES;
+use crate::security::{ChaChaKey, Decryptor};
+use eyre::{eyre, Result};
+use protobuf::{Message, UnknownFields};
+use std::io::Write;
+
+/// Side of the connection that wrote the dumped stream.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// `MasterMessage`s of the server
+    Server,
+    /// `SlaveMessage`s of the client
+    Client,
+}
+
+#[derive(Clone, Copy)]
+pub struct DecodeOptions {
+    pub origin: Origin,
+    /// Key of the stream, if it is encrypted
+    pub key: Option<ChaChaKey>,
+    /// Bytes at the start of the stream that are not part of a message.
+    /// By default the key exchange is skipped if the stream is encrypted.
+    pub skip: Option<usize>,
+}
+
+impl DecodeOptions {
+    fn skipped(&self) -> usize {
+        match (self.skip, self.key) {
+            (Some(skip), _) => skip,
+            (None, Some(_)) => EXCHANGED_BYTES,
+            (None, None) => 0,
+        }
+    }
+}
+
+/// Prints the length delimited messages of one direction of a connection, one per line
+/// with its offset in the stream, and returns how many were decoded.
+///
+/// A raw TCP payload is decrypted in place. Decoding stops at the first malformed
+/// message and fails if the stream ends in the middle of one.
+pub fn decode(bytes: &mut [u8], options: &DecodeOptions, out: &mut dyn Write) -> Result<usize> {
+    let skipped = options.skipped();
+    if bytes.len() < skipped {
+        return Err(eyre!(
+            "The stream is shorter than the {} bytes skipped",
+            skipped
+        ));
+    }
+    let stream = &mut bytes[skipped..];
+    if let Some(key) = options.key {
+        Decryptor::new(key).decrypt(stream);
+    }
+
+    let mut offset = 0;
+    let mut decoded = 0;
+    while offset < stream.len() {
+        let position = skipped + offset;
+        let (length, prefix) = decode_varint(&stream[offset..])?
+            .ok_or_else(|| eyre!("Incomplete length at offset {}", position))?;
+        let length = usize::try_from(length)
+            .ok()
+            .filter(|length| *length <= MAX_MESSAGE_SIZE)
+            .ok_or_else(|| eyre!("Message too large at offset {}: {} bytes", position, length))?;
+        let body = stream
+            .get(offset + prefix..offset + prefix + length)
+            .ok_or_else(|| {
+                eyre!(
+                    "Message at offset {} is cut short: {} of {} bytes",
+                    position,
+                    stream.len() - offset - prefix,
+                    length
+                )
+            })?;
+        let description = match options.origin {
+            Origin::Server => ProtoInMsg::parse_from_bytes(body).map(|msg| describe_received(&msg)),
+            Origin::Client => ProtoOutMsg::parse_from_bytes(body)
+                .map(|msg| with_unknown_fields(describe_sent(&msg), &msg.unknown_fields)),
+        }
+        .map_err(|e| eyre!("Malformed message at offset {}: {}", position, e))?;
+        writeln!(out, "{:>8} {:>6}  {}", position, length, description)?;
+        offset += prefix + length;
+        decoded += 1;
+    }
+    Ok(decoded)
+}
+
+/// Keys are shown by name, clipboards by size.
+fn describe_received(msg: &ProtoInMsg) -> String {
+    let description = match msg.action.as_ref() {
+        Some(MsgType::keyboard(key)) => {
+            format!("keyboard({}, {:?})", key_name(key.key), key.event_type)
+        }
+        Some(MsgType::clipboard(clip)) => describe_clipboard(clip),
+        Some(action) => format!("{:?}", action),
+        None => String::from("empty"),
+    };
+    with_unknown_fields(description, &msg.unknown_fields)
+}
+
+fn describe_clipboard(clip: &Clipboard) -> String {
+    format!(
+        "clipboard({:?}, {:?}, {} bytes, {} compressed)",
+        clip.format,
+        clip.compression,
+        clip.message.len(),
+        clip.compressed.len()
+    )
+}
+
+/// Fields the client does not know point at a protocol mismatch.
+fn with_unknown_fields(description: String, fields: &UnknownFields) -> String {
+    let unknown: Vec<_> = fields.iter().collect();
+    match unknown.is_empty() {
+        true => description,
+        false => format!("{} unknown fields: {:?}", description, unknown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{JerryMessage, JerryResponse, State};
+    use crate::security::Encryptor;
+
+    fn key() -> ChaChaKey {
+        ChaChaKey {
+            key: [9; 32],
+            nonce: [4; 12],
+        }
+    }
+
+    fn stream<M: Message>(messages: &[M]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for msg in messages {
+            msg.write_length_delimited_to_vec(&mut bytes).unwrap();
+        }
+        bytes
+    }
+
+    fn dump(mut bytes: Vec<u8>, options: DecodeOptions) -> Result<String> {
+        let mut out = Vec::new();
+        decode(&mut bytes, &options, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn encrypted_payload_after_the_key_exchange() {
+        let messages: Vec<ProtoInMsg> = vec![
+            JerryMessage::Key(0x41, State::PRESSED).into(),
+            JerryMessage::MouseMove(-3, 7).into(),
+        ];
+        let mut encrypted = stream(&messages);
+        Encryptor::new(key()).encrypt(&mut encrypted);
+        let mut payload = vec![0xee; EXCHANGED_BYTES];
+        payload.extend(encrypted);
+
+        let options = DecodeOptions {
+            origin: Origin::Server,
+            key: Some(key()),
+            skip: None,
+        };
+        let output = dump(payload, options).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("     128"), "{}", lines[0]);
+        assert!(lines[0].ends_with("keyboard(A, PRESSED)"), "{}", lines[0]);
+        assert!(lines[1].contains("mouse_position"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn client_messages_and_malformed_streams() {
+        let messages: Vec<ProtoOutMsg> = vec![JerryResponse::Cursor(1, 2).into()];
+        let plain = DecodeOptions {
+            origin: Origin::Client,
+            key: None,
+            skip: None,
+        };
+        let output = dump(stream(&messages), plain).unwrap();
+        assert!(output.contains("cursor"), "{}", output);
+
+        let mut truncated = stream(&messages);
+        truncated.pop();
+        assert!(dump(truncated, plain).is_err());
+        // a stream decoded with the wrong key is garbage
+        let mut wrong_key = plain;
+        wrong_key.key = Some(key());
+        wrong_key.skip = Some(0);
+        assert!(dump(stream(&messages), wrong_key).is_err());
+    }
+}
@@ -20,6 +20,8 @@ impl From<ProtoInMsg> for JerryMessage {
                 MsgType::end_session(_) => JerryMessage::SessionEnd,
                 MsgType::request(req) => JerryMessage::Request(req),
                 MsgType::heartbeat(_one_way) => JerryMessage::Heartbeat,
+                // consumed by pipeline::read_loop, never forwarded
+                MsgType::pong(_) => JerryMessage::Heartbeat,
             }
         } else {
             JerryMessage::Heartbeat
@@ -86,7 +88,7 @@ impl From<JerryResponse> for ProtoOutMsg {
         match params {
             JerryResponse::Cursor(x, y) => msg.set_cursor(response::create_position(x, y)),
             JerryResponse::InitInfo(params) => {
-                msg.set_init_info(response::create_init_info(params))
+                msg.set_init_info(response::create_init_info(*params))
             }
             JerryResponse::Clipboard(content, files) => {
                 msg.set_clipboard_session(response::create_clipboard(content, files))
@@ -94,6 +96,9 @@ impl From<JerryResponse> for ProtoOutMsg {
             JerryResponse::NoResponse(reason) => {
                 msg.set_no_response(response::create_failure(reason))
             }
+            JerryResponse::Goodbye(cause, description) => {
+                msg.set_goodbye(response::create_goodbye(cause, description))
+            }
         }
         msg
     }
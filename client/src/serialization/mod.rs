@@ -1,49 +1,325 @@
+pub mod capture;
+pub mod compression;
+pub mod decode;
 pub mod mapper;
+pub mod ping;
 pub mod proto_factory;
-use crate::core::JerryMessage;
-use crate::core::MessageConsumer;
-use crate::proto_rs::{ProtoInMsg, ProtoOutMsg};
+use crate::connection::stats::ByteCounter;
+use crate::error::{ConnectionError, ErrorSource, ProtocolError, TransportError};
+use crate::security::{Decryptor, Encryptor};
 
-use eyre::{eyre, Result};
-use protobuf::ProtobufResult;
-use std::io::{Read, Write};
+use protobuf::Message;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
-pub struct ProtoSerDe<'a> {
-    pub stream_in: protobuf::CodedInputStream<'a>,
-    pub stream_out: protobuf::CodedOutputStream<'a>,
+/// Upper bound for a single message, a corrupted length must not exhaust the memory.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+/// A varint encoded `u64` is at most 10 bytes long.
+const MAX_VARINT_SIZE: usize = 10;
+/// Initial capacity of the read and write buffers, enough for a burst of input events.
+const BUFFER_SIZE: usize = 16 * 1024;
+/// Buffers grown by a large clipboard are released once it has been handled.
+const MAX_RETAINED_BUFFER: usize = 1024 * 1024;
+
+/// Reads length delimited protobuf messages from the server.
+///
+/// Bytes are read into a single buffer that is reused for the whole session and
+/// decrypted in place, messages are parsed straight from the buffer.
+pub struct FrameReader<R> {
+    inner: R,
+    decryptor: Option<Decryptor>,
+    /// Decrypted bytes, `buffer[consumed..]` does not form a complete message yet
+    buffer: Vec<u8>,
+    consumed: usize,
+    counter: Option<ByteCounter>,
 }
 
-impl<'a> ProtoSerDe<'a> {
-    pub fn new(stream_in: &'a mut dyn Read, stream_out: &'a mut dyn Write) -> Self {
-        let stream_in = protobuf::CodedInputStream::new(stream_in);
-        let stream_out = protobuf::CodedOutputStream::new(stream_out);
-        ProtoSerDe {
-            stream_in,
-            stream_out,
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(inner: R, decryptor: Option<Decryptor>) -> Self {
+        Self {
+            inner,
+            decryptor,
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+            consumed: 0,
+            counter: None,
         }
     }
 
-    pub fn listen_loop(&mut self, mut consumer: Box<dyn MessageConsumer>) -> Result<()> {
+    pub fn with_counter(mut self, counter: ByteCounter) -> Self {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// Cancel safe, bytes received before the future is dropped are kept for the next call.
+    pub async fn read_message<M: Message>(&mut self) -> Result<M, ConnectionError> {
         loop {
-            if consumer.finished() {
-                return Ok(());
+            if let Some(msg) = self.parse()? {
+                return Ok(msg);
+            }
+            self.compact();
+            if self.buffer.len() == self.buffer.capacity() {
+                self.buffer.reserve(BUFFER_SIZE);
+            }
+            let start = self.buffer.len();
+            let n = self
+                .inner
+                .read_buf(&mut self.buffer)
+                .await
+                .map_err(|e| TransportError::Read(e.into()))?;
+            if n == 0 {
+                return Err(TransportError::Closed.into());
+            }
+            if let Some(counter) = self.counter.as_ref() {
+                counter.add(n);
+            }
+            if let Some(decryptor) = self.decryptor.as_mut() {
+                decryptor.decrypt(&mut self.buffer[start..]);
+            }
+        }
+    }
+
+    fn parse<M: Message>(&mut self) -> Result<Option<M>, ProtocolError> {
+        let pending = &self.buffer[self.consumed..];
+        let Some((length, prefix)) = decode_varint(pending)? else {
+            return Ok(None);
+        };
+        let length = usize::try_from(length)
+            .ok()
+            .filter(|length| *length <= MAX_MESSAGE_SIZE)
+            .ok_or(ProtocolError::MessageTooLarge(length))?;
+        let end = prefix + length;
+        if pending.len() < end {
+            // the rest of a large message is read without growing the buffer repeatedly
+            let missing = end - pending.len();
+            self.buffer.reserve(missing);
+            return Ok(None);
+        }
+        let msg = M::parse_from_bytes(&pending[prefix..end])
+            .map_err(|e| ProtocolError::Decode(ErrorSource::new(e)))?;
+        self.consumed += end;
+        if self.consumed == self.buffer.len() {
+            self.compact();
+        }
+        Ok(Some(msg))
+    }
+
+    /// Moves the incomplete message to the front of the buffer.
+    fn compact(&mut self) {
+        if self.consumed == 0 {
+            return;
+        }
+        self.buffer.copy_within(self.consumed.., 0);
+        self.buffer.truncate(self.buffer.len() - self.consumed);
+        self.consumed = 0;
+        if self.buffer.capacity() > MAX_RETAINED_BUFFER && self.buffer.len() < BUFFER_SIZE {
+            self.buffer.shrink_to(BUFFER_SIZE);
+        }
+    }
+}
+
+/// `None` while the length prefix is incomplete.
+fn decode_varint(buf: &[u8]) -> Result<Option<(u64, usize)>, ProtocolError> {
+    let mut value = 0u64;
+    for (i, byte) in buf.iter().take(MAX_VARINT_SIZE).enumerate() {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(Some((value, i + 1)));
+        }
+    }
+    match buf.len() >= MAX_VARINT_SIZE {
+        true => Err(ProtocolError::MalformedLength),
+        false => Ok(None),
+    }
+}
+
+/// Writes length delimited protobuf messages to the server.
+///
+/// Each message is serialized into a reused buffer, encrypted in place and
+/// written and flushed at once.
+pub struct FrameWriter<W> {
+    inner: W,
+    encryptor: Option<Encryptor>,
+    buffer: Vec<u8>,
+    counter: Option<ByteCounter>,
+}
+
+impl<W: AsyncWrite + Unpin> FrameWriter<W> {
+    pub fn new(inner: W, encryptor: Option<Encryptor>) -> Self {
+        Self {
+            inner,
+            encryptor,
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+            counter: None,
+        }
+    }
+
+    pub fn with_counter(mut self, counter: ByteCounter) -> Self {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// Not cancel safe, the keystream is advanced before the message is written.
+    pub async fn write_message<M: Message>(&mut self, msg: &M) -> Result<(), ConnectionError> {
+        self.buffer.clear();
+        msg.write_length_delimited_to_vec(&mut self.buffer)
+            .map_err(|e| ProtocolError::Encode(ErrorSource::new(e)))?;
+        if let Some(encryptor) = self.encryptor.as_mut() {
+            encryptor.encrypt(&mut self.buffer);
+        }
+        let write = |e: std::io::Error| TransportError::Write(e.into());
+        self.inner.write_all(&self.buffer).await.map_err(write)?;
+        self.inner.flush().await.map_err(write)?;
+        if let Some(counter) = self.counter.as_ref() {
+            counter.add(self.buffer.len());
+        }
+        if self.buffer.capacity() > MAX_RETAINED_BUFFER {
+            self.buffer = Vec::with_capacity(BUFFER_SIZE);
+        }
+        Ok(())
+    }
+
+    /// Closes the write half of the connection.
+    pub async fn shutdown(&mut self) -> Result<(), TransportError> {
+        self.inner
+            .shutdown()
+            .await
+            .map_err(|e| TransportError::Write(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto_rs::{proto_in, ProtoInMsg, ProtoOutMsg};
+    use crate::security::ChaChaKey;
+    use std::time::{Duration, Instant};
+
+    fn key() -> ChaChaKey {
+        ChaChaKey {
+            key: [7; 32],
+            nonce: [3; 12],
+        }
+    }
+
+    #[tokio::test]
+    async fn encrypted_messages_survive_fragmentation() {
+        let (client, server) = tokio::io::duplex(16);
+        let mut writer = FrameWriter::new(client, Some(Encryptor::new(key())));
+        let mut reader = FrameReader::new(server, Some(Decryptor::new(key())));
+
+        let mut messages = Vec::new();
+        for size in [0, 1, 300, 5_000] {
+            let mut msg = ProtoOutMsg::new();
+            msg.mut_clipboard_session().set_message("x".repeat(size));
+            messages.push(msg);
+        }
+        let expected = messages.clone();
+        let writing = tokio::spawn(async move {
+            for msg in messages.iter() {
+                writer.write_message(msg).await.unwrap();
             }
-            let _msg_in = self
-                .stream_in
-                .read_message::<ProtoInMsg>()
-                .map(JerryMessage::from)
-                .map_err(|e| eyre!("Read message error: {:?}", e))?;
-            consumer
-                .consume(_msg_in)
-                .map(ProtoOutMsg::from)
-                .map(|r| self.write_flush(r))
-                .unwrap_or(Ok(()))
-                .map_err(|e| eyre!("Write message error: {:?}", e))?;
-        }
-    }
-
-    fn write_flush(&mut self, msg: ProtoOutMsg) -> ProtobufResult<()> {
-        self.stream_out.write_message_no_tag(&msg)?;
-        self.stream_out.flush()
+        });
+        for msg in expected {
+            assert_eq!(reader.read_message::<ProtoOutMsg>().await.unwrap(), msg);
+        }
+        writing.await.unwrap();
+        assert!(reader.read_message::<ProtoOutMsg>().await.is_err());
+    }
+
+    #[test]
+    fn varint_length_prefix() {
+        assert_eq!(decode_varint(&[]).unwrap(), None);
+        assert_eq!(decode_varint(&[0x05, 0xff]).unwrap(), Some((5, 1)));
+        assert_eq!(decode_varint(&[0xac, 0x02]).unwrap(), Some((300, 2)));
+        assert_eq!(decode_varint(&[0x80, 0x80]).unwrap(), None);
+        assert!(decode_varint(&[0xff; MAX_VARINT_SIZE]).is_err());
+    }
+
+    fn mouse_move(x: i32, y: i32) -> ProtoInMsg {
+        let mut mouse_move = proto_in::MouseMove::new();
+        mouse_move.set_X(x);
+        mouse_move.set_Y(y);
+        let mut msg = ProtoInMsg::new();
+        msg.set_mouse_position(mouse_move);
+        msg
+    }
+
+    #[tokio::test]
+    async fn buffers_are_reused() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let mut writer = FrameWriter::new(client, Some(Encryptor::new(key())));
+        let mut reader = FrameReader::new(server, Some(Decryptor::new(key())));
+        for i in 0..10_000 {
+            writer.write_message(&mouse_move(i, -i)).await.unwrap();
+            let msg = reader.read_message::<ProtoInMsg>().await.unwrap();
+            assert_eq!(msg.get_mouse_position().X, i);
+        }
+        assert_eq!(reader.buffer.capacity(), BUFFER_SIZE);
+        assert_eq!(writer.buffer.capacity(), BUFFER_SIZE);
+
+        // a large clipboard grows the buffers only until it has been handled
+        let mut clipboard = ProtoOutMsg::new();
+        let content = "x".repeat(4 * MAX_RETAINED_BUFFER);
+        clipboard
+            .mut_clipboard_session()
+            .set_message(content.clone());
+        let writing = tokio::spawn(async move {
+            writer.write_message(&clipboard).await.unwrap();
+            writer.write_message(&ProtoOutMsg::new()).await.unwrap();
+            writer
+        });
+        let msg = reader.read_message::<ProtoOutMsg>().await.unwrap();
+        assert_eq!(msg.get_clipboard_session().message, content);
+        reader.read_message::<ProtoOutMsg>().await.unwrap();
+        let writer = writing.await.unwrap();
+        assert!(reader.buffer.capacity() <= MAX_RETAINED_BUFFER);
+        assert_eq!(writer.buffer.capacity(), BUFFER_SIZE);
+    }
+
+    /// `cargo test --release -- --ignored --nocapture frame_throughput`
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ignore = "benchmark"]
+    async fn frame_throughput() {
+        const MESSAGES: i32 = 500_000;
+        const LATENCY_SAMPLES: usize = 10_000;
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let mut writer = FrameWriter::new(client, Some(Encryptor::new(key())));
+        let mut reader = FrameReader::new(server, Some(Decryptor::new(key())));
+
+        let start = Instant::now();
+        let writing = tokio::spawn(async move {
+            for i in 0..MESSAGES {
+                writer.write_message(&mouse_move(i, i)).await.unwrap();
+            }
+            writer
+        });
+        for _ in 0..MESSAGES {
+            reader.read_message::<ProtoInMsg>().await.unwrap();
+        }
+        let elapsed = start.elapsed();
+        let mut writer = writing.await.unwrap();
+        println!(
+            "throughput: {:.0} messages/s ({} messages in {:?})",
+            MESSAGES as f64 / elapsed.as_secs_f64(),
+            MESSAGES,
+            elapsed
+        );
+
+        let mut latencies = Vec::with_capacity(LATENCY_SAMPLES);
+        for i in 0..LATENCY_SAMPLES {
+            let msg = mouse_move(i as i32, 0);
+            let sent = Instant::now();
+            writer.write_message(&msg).await.unwrap();
+            reader.read_message::<ProtoInMsg>().await.unwrap();
+            latencies.push(sent.elapsed());
+        }
+        latencies.sort();
+        let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+        let mean = latencies.iter().sum::<Duration>() / LATENCY_SAMPLES as u32;
+        println!(
+            "latency per message: mean {:?}, p50 {:?}, p99 {:?}",
+            mean,
+            percentile(50),
+            percentile(99)
+        );
     }
 }
@@ -0,0 +1,66 @@
+use crate::proto_rs::Ping;
+use std::time::{Duration, Instant};
+
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Client side of the Ping/Pong round-trip time measurement.
+///
+/// Pings are only sent once the server has announced support in the handshake `Echo`,
+/// the timestamp is relative to the creation of the `Pinger`. The C# server does not
+/// announce it yet, the RTT is then not measured.
+pub struct Pinger {
+    epoch: Instant,
+    enabled: bool,
+    sequence: u32,
+    last_ping: Option<Instant>,
+}
+
+impl Pinger {
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            enabled: false,
+            sequence: 0,
+            last_ping: None,
+        }
+    }
+
+    pub fn enable(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Returns the next ping once `PING_INTERVAL` has elapsed since the previous one.
+    pub fn due(&mut self) -> Option<Ping> {
+        if !self.enabled || self.last_ping.is_some_and(|t| t.elapsed() < PING_INTERVAL) {
+            return None;
+        }
+        let now = Instant::now();
+        self.last_ping = Some(now);
+        self.sequence = self.sequence.wrapping_add(1);
+        let mut ping = Ping::new();
+        ping.set_sequence(self.sequence);
+        ping.set_timestamp(now.duration_since(self.epoch).as_micros() as u64);
+        Some(ping)
+    }
+
+    /// Round-trip time of the echoed ping, `None` if the timestamp is not one of ours.
+    pub fn rtt(&self, pong: &Ping) -> Option<Duration> {
+        let sent = self
+            .epoch
+            .checked_add(Duration::from_micros(pong.timestamp))?;
+        Instant::now().checked_duration_since(sent)
+    }
+}
+
+impl Default for Pinger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Answer to a heartbeat that is not one way
+pub fn echo(timestamp: u64) -> Ping {
+    let mut pong = Ping::new();
+    pong.set_timestamp(timestamp);
+    pong
+}
@@ -12,6 +12,7 @@ pub fn create_clipboard(content: String, filelist: bool) -> Clipboard {
 //           proto_out::*
 //========================================
 pub mod response {
+    use crate::serialization::compression::ClipboardCodec;
     use crate::{
         configuration::{ScreenResolution, SessionParams},
         proto_rs::proto_out,
@@ -34,6 +35,16 @@ pub mod response {
         f
     }
 
+    pub fn create_goodbye(
+        cause: proto_out::Goodbye_Cause,
+        description: String,
+    ) -> proto_out::Goodbye {
+        let mut g = proto_out::Goodbye::new();
+        g.set_Reason(cause);
+        g.set_Description(description);
+        g
+    }
+
     pub fn create_init_info(session: SessionParams) -> proto_out::ClientInfo {
         tracing::debug!("{:?}", session);
         let (cx, cy): (i32, i32) = match session.monitor {
@@ -58,6 +69,7 @@ pub mod response {
         info.set_Guid(guid);
         info.set_Name(session.client_name);
         info.set_System(get_os());
+        info.set_Compression(ClipboardCodec::supported());
         info
     }
     fn get_os() -> proto_out::ClientInfo_OS {
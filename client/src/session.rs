@@ -0,0 +1,198 @@
+use crate::configuration::{ServerGroup, SessionParams};
+use crate::connection::reconnect::UserDecision;
+use crate::connection::{ConnectionWorker, ControlRequest, ShutdownSignal};
+use crate::core::emulator::Emulator;
+use crate::core::macros::MacroRecording;
+use crate::core::message_handler::{ContextAwareMessageHandler, EmulationPause};
+use crate::core::{Command, ConsumerFactory, MessageConsumer};
+use crate::serialization::capture::Capture;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Connection of the client to a group of servers.
+///
+/// The session connects to the servers in turn, runs the key exchange and the handshake,
+/// and passes the received messages to the `MessageConsumer`, by default a
+/// `ContextAwareMessageHandler` emulating the input on this computer. It reconnects
+/// as the `ReconnectPolicy` of the primary server says.
+///
+/// Everything that happens is reported as a `Command` to the event subscriber.
+pub struct Session {
+    worker: ConnectionWorker,
+    shutdown: ShutdownSignal,
+    /// Events nobody subscribed to
+    discarded: Option<Receiver<Command>>,
+}
+
+pub struct SessionBuilder {
+    group: ServerGroup,
+    events: Option<SyncSender<Command>>,
+    decisions: Option<tokio::sync::mpsc::Receiver<UserDecision>>,
+    controls: Option<tokio::sync::mpsc::Receiver<ControlRequest>>,
+    shutdown: ShutdownSignal,
+    pause: EmulationPause,
+    recording: MacroRecording,
+    consumer: Option<ConsumerFactory>,
+    capture: Option<Capture>,
+}
+
+impl Session {
+    /// The primary server of the group is tried first.
+    pub fn builder(group: ServerGroup) -> SessionBuilder {
+        SessionBuilder {
+            group,
+            events: None,
+            decisions: None,
+            controls: None,
+            shutdown: ShutdownSignal::default(),
+            pause: EmulationPause::default(),
+            recording: MacroRecording::default(),
+            consumer: None,
+            capture: None,
+        }
+    }
+
+    /// Ends the session from another task or thread, the connection is closed cleanly.
+    pub fn shutdown_signal(&self) -> ShutdownSignal {
+        self.shutdown.clone()
+    }
+
+    /// Runs until the reconnection stops, the event receiver is dropped or the
+    /// shutdown is requested. `Command::Halt` is the last event sent.
+    ///
+    /// Fails right away on a current-thread tokio runtime: the events are sent to
+    /// blocking receivers, waiting for room in their queue needs a multi-threaded one.
+    pub async fn run(self) -> eyre::Result<()> {
+        if Handle::current().runtime_flavor() == RuntimeFlavor::CurrentThread {
+            eyre::bail!("The session needs a multi-threaded tokio runtime");
+        }
+        if let Some(discarded) = self.discarded {
+            tokio::task::spawn_blocking(move || discarded.into_iter().for_each(drop));
+        }
+        self.worker.run().await;
+        Ok(())
+    }
+}
+
+impl SessionBuilder {
+    /// Events are sent to `sender`, the session ends as soon as its receiver is dropped.
+    ///
+    /// Senders cloned from it can be used to add events of your own, as the binary
+    /// does with the keys pressed in the terminal.
+    pub fn events(mut self, sender: SyncSender<Command>) -> Self {
+        self.events = Some(sender);
+        self
+    }
+
+    /// Creates the channel the events are sent to, at most `capacity` events are queued.
+    pub fn subscribe(&mut self, capacity: usize) -> Receiver<Command> {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        self.events = Some(sender);
+        receiver
+    }
+
+    /// Answers to `ConnectionState::AwaitingDecision`.
+    /// Without it, a `ReconnectAction::Prompt` stops the reconnection.
+    pub fn decisions(mut self, receiver: tokio::sync::mpsc::Receiver<UserDecision>) -> Self {
+        self.decisions = Some(receiver);
+        self
+    }
+
+    /// Requests to disconnect, reconnect or switch to another server of the group.
+    pub fn controls(mut self, receiver: tokio::sync::mpsc::Receiver<ControlRequest>) -> Self {
+        self.controls = Some(receiver);
+        self
+    }
+
+    /// Switch pausing the emulation of the `ContextAwareMessageHandler`s of the session.
+    pub fn emulation_pause(&self) -> EmulationPause {
+        self.pause.clone()
+    }
+
+    /// Macro recording of the `ContextAwareMessageHandler`s of the session.
+    pub fn macro_recording(&self) -> MacroRecording {
+        self.recording.clone()
+    }
+
+    /// Shares the signal, e.g. with a signal handler installed before the session is built.
+    pub fn shutdown(mut self, signal: ShutdownSignal) -> Self {
+        self.shutdown = signal;
+        self
+    }
+
+    /// Input events are passed to the emulator created for each connection
+    /// instead of the platform emulator.
+    pub fn emulator<F>(self, factory: F) -> Self
+    where
+        F: Fn(&SessionParams) -> Box<dyn Emulator> + Send + Sync + 'static,
+    {
+        let pause = self.pause.clone();
+        let recording = self.recording.clone();
+        self.consumer(move |transmitter, params| {
+            let emulator = factory(&params);
+            Box::new(
+                ContextAwareMessageHandler::with_emulator(transmitter, params, emulator)
+                    .with_pause(pause.clone())
+                    .with_recording(recording.clone()),
+            )
+        })
+    }
+
+    /// Messages are passed to the consumer created for each connection
+    /// instead of the `ContextAwareMessageHandler`.
+    pub fn consumer<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(SyncSender<Command>, SessionParams) -> Box<dyn MessageConsumer>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.consumer = Some(Arc::new(factory));
+        self
+    }
+
+    /// The decrypted traffic of every connection is written to `capture`.
+    pub fn capture(mut self, capture: Capture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    pub fn build(self) -> Session {
+        let (events, discarded) = match self.events {
+            Some(events) => (events, None),
+            None => {
+                let (sender, receiver) = mpsc::sync_channel(1);
+                (sender, Some(receiver))
+            }
+        };
+        let decisions = self.decisions.unwrap_or_else(|| {
+            // the sender is dropped, the prompt is answered with a stop
+            tokio::sync::mpsc::channel(1).1
+        });
+        let (pause, recording) = (self.pause, self.recording);
+        let consumer = self.consumer.unwrap_or_else(|| {
+            Arc::new(move |transmitter, params| {
+                Box::new(
+                    ContextAwareMessageHandler::new(transmitter, params)
+                        .with_pause(pause.clone())
+                        .with_recording(recording.clone()),
+                )
+            })
+        });
+        let worker = ConnectionWorker::new(
+            events,
+            self.group,
+            decisions,
+            self.controls,
+            self.shutdown.clone(),
+            consumer,
+            self.capture,
+        );
+        Session {
+            worker,
+            shutdown: self.shutdown,
+            discarded,
+        }
+    }
+}
@@ -1,23 +1,80 @@
 use super::super::Command;
+use crate::connection::reconnect::UserDecision;
 use crate::connection::ConnectionState;
+use crate::core::clipboard::Transfer;
+use crate::core::macros::MacroRecording;
 use crate::{core::JerryMessage, emulation::JKey};
 use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
 use tracing::{debug, error, info, trace, warn};
+
+const LINK_STATS_LOG_INTERVAL: Duration = Duration::from_secs(10);
 pub struct View {
     receiver: Receiver<Command>,
+    recording: MacroRecording,
+    decisions: Sender<UserDecision>,
+    awaiting_decision: bool,
+    link_stats_logged: Option<Instant>,
 }
 impl View {
-    pub fn new(rx: Receiver<Command>) -> Self {
-        Self { receiver: rx }
+    pub fn new(
+        rx: Receiver<Command>,
+        decisions: Sender<UserDecision>,
+        recording: MacroRecording,
+    ) -> Self {
+        Self {
+            receiver: rx,
+            recording,
+            decisions,
+            awaiting_decision: false,
+            link_stats_logged: None,
+        }
     }
     pub fn run(&mut self) {
+        self.receive();
+        if let Some(recorder) = self.recording.stop() {
+            let (name, len) = (recorder.name().to_owned(), recorder.len());
+            match recorder.save() {
+                Ok(path) => info!("Macro '{}' ({} events) saved to {:?}", name, len, path),
+                Err(e) => error!("Macro '{}' not saved: {}", name, e),
+            }
+        }
+    }
+    fn receive(&mut self) {
         loop {
             if let Ok(msg) = self.receiver.recv() {
                 match msg {
                     Command::Draw => {}
                     Command::Message(msg) => self.process(msg, false),
                     Command::MessageCorrective(msg) => self.process(msg, true),
-                    Command::ConnectionResult(st) => self.log(st),
+                    Command::ConnectionResult(st) => {
+                        self.awaiting_decision = matches!(st, ConnectionState::AwaitingDecision(_));
+                        self.log(st)
+                    }
+                    Command::ActiveServer(server) => info!("Active server: {}", server),
+                    Command::LinkStats(snapshot) => {
+                        let due = self
+                            .link_stats_logged
+                            .is_none_or(|t| t.elapsed() >= LINK_STATS_LOG_INTERVAL);
+                        if due {
+                            self.link_stats_logged = Some(Instant::now());
+                            info!("Connection quality: {}", snapshot);
+                        }
+                    }
+                    Command::Input(key) if self.awaiting_decision => {
+                        if let Some(decision) = super::decision_from_key(key) {
+                            self.awaiting_decision = false;
+                            _ = self.decisions.try_send(decision);
+                        }
+                    }
+                    Command::ClipboardTransfer(Transfer::Outgoing, content) => {
+                        debug!("Clipboard content sent: {} ", content);
+                        info!("Clipboard content sent, length: \t\t{} ", content.len())
+                    }
+                    Command::ClipboardTransfer(Transfer::Incoming, _)
+                    | Command::ClipboardKept(_)
+                    | Command::Input(_) => {}
                     Command::ExitWithError(error) => {
                         error!("Received a command to exit due to an error {:?}", error);
                         break;
@@ -77,16 +134,22 @@ impl View {
         match st {
             ConnectionState::None
             | ConnectionState::Establishing
+            | ConnectionState::Listening(_)
             | ConnectionState::Connected
             | ConnectionState::HandshakeSuccess(_)
             | ConnectionState::ConnectionError(_)
-            | ConnectionState::ConnectedSecured => {
+            | ConnectionState::ConnectedSecured
+            | ConnectionState::Disconnected => {
                 info!("Connection result: {}", st)
             }
             ConnectionState::KeyExchangeFailed(_) | ConnectionState::HandshakeFailed(_) => {
                 error!("Connection result: {}", st)
             }
-            ConnectionState::ReadError(_) => warn!("Connection result: {}", st),
+            ConnectionState::ReadError(_) | ConnectionState::AwaitingDecision(_) => {
+                warn!("Connection result: {}", st)
+            }
+            ConnectionState::Reconnecting { .. } => info!("{}", st),
+            ConnectionState::Stopped(_) => error!("{}", st),
         }
     }
 }
@@ -1,7 +1,11 @@
 pub mod log;
 pub mod ui;
+use crate::connection::reconnect::UserDecision;
+use crate::connection::stats::LinkSnapshot;
 use crate::connection::ConnectionState;
+use crate::core::clipboard::Transfer;
 use crate::core::JerryMessage;
+use crossterm::event::KeyCode;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Coord {
@@ -15,6 +19,22 @@ pub enum Command {
     Message(JerryMessage),
     MessageCorrective(JerryMessage),
     ConnectionResult(ConnectionState),
+    /// Name and address of the server the client connects to
+    ActiveServer(String),
+    LinkStats(LinkSnapshot),
+    ClipboardTransfer(Transfer, String),
+    /// Content for the clipboard history only, see `ClipboardRestore::Merge`
+    ClipboardKept(String),
+    Input(KeyCode),
     Halt,
     ExitWithError(String),
 }
+
+/// Answer to `ConnectionState::AwaitingDecision`.
+pub fn decision_from_key(key: KeyCode) -> Option<UserDecision> {
+    match key {
+        KeyCode::Char('y') | KeyCode::Char('Y') => Some(UserDecision::Retry),
+        KeyCode::Char('n') | KeyCode::Char('N') => Some(UserDecision::Stop),
+        _ => None,
+    }
+}
@@ -5,28 +5,38 @@ use ratatui::style::{Color, Style};
 use ratatui::text::Span;
 use ratatui::widgets::block::Title;
 use ratatui::widgets::canvas::Canvas;
-use ratatui::widgets::{Block, BorderType, Borders};
+use ratatui::widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph};
 use ratatui::{symbols, Terminal};
-use tracing::info;
+use tracing::{info, warn};
 
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, SyncSender};
 use std::time::Duration;
+use tokio::sync::mpsc::Sender;
 
 use super::JerryMessage;
 use super::{Command, Coord};
 
+use crate::connection::reconnect::UserDecision;
+use crate::connection::stats::LinkSnapshot;
 use crate::connection::ConnectionState;
+use crate::core::clipboard::{ClipboardHistory, Transfer};
+use crate::core::macros::MacroRecording;
 use crate::proto_rs::proto_in::State;
+use crossterm::event::KeyCode;
 
 const TICK_INTERVAL: Duration = Duration::from_millis(50);
+const CLIPBOARD_HISTORY_LEN: usize = 32;
 
 pub struct WindowState<'a, B: Backend> {
     receiver: Receiver<Command>,
-    transmitter: Sender<Command>,
+    transmitter: SyncSender<Command>,
+    decisions: Sender<UserDecision>,
 
     terminal: &'a mut Terminal<B>,
 
     connection_state: ConnectionState,
+    server: String,
+    link: Option<LinkSnapshot>,
     pub mon_size: Coord,
     pub cursor: Coord,
     active: bool,
@@ -34,32 +44,41 @@ pub struct WindowState<'a, B: Backend> {
     keys: Vec<u32>,
     wheel: char,
     mouse_btn: i16,
-    // _clip: String,
-    // _clip_len: usize,
+    clipboard: ClipboardHistory,
+    clipboard_selection: ListState,
+    status: String,
+    recording: MacroRecording,
     heart: u8,
     relative_move: bool,
 }
 impl<'a, B: Backend> WindowState<'a, B> {
     pub fn new(
         mon_size: Coord,
-        tx: Sender<Command>,
+        tx: SyncSender<Command>,
         rx: Receiver<Command>,
+        decisions: Sender<UserDecision>,
+        recording: MacroRecording,
         terminal: &'a mut Terminal<B>,
     ) -> WindowState<B> {
         WindowState {
             receiver: rx,
             transmitter: tx,
+            decisions,
             terminal,
             mon_size,
             connection_state: ConnectionState::None,
+            server: String::new(),
+            link: None,
             active: false,
             cursor: Coord { x: 0, y: 0 },
             rendering_pause_cycles: 0,
             keys: Vec::new(),
             wheel: ' ',
             mouse_btn: 0,
-            // _clip_len: 0,
-            // _clip: String::new(),
+            clipboard: ClipboardHistory::new(CLIPBOARD_HISTORY_LEN),
+            clipboard_selection: ListState::default(),
+            status: String::new(),
+            recording,
             heart: 0,
             relative_move: false,
         }
@@ -90,6 +109,17 @@ impl<'a, B: Backend> WindowState<'a, B> {
                         self.render();
                         self.pause_rendering(10);
                     }
+                    Command::ActiveServer(server) => self.server = server,
+                    Command::LinkStats(snapshot) => self.link = Some(snapshot),
+                    Command::ClipboardTransfer(transfer, content) => {
+                        let added = self.clipboard.push(Some(transfer), content);
+                        self.clipboard_added(added)
+                    }
+                    Command::ClipboardKept(content) => {
+                        let added = self.clipboard.keep(content);
+                        self.clipboard_added(added)
+                    }
+                    Command::Input(key) => self.on_key(key),
                     Command::Halt => break,
                     Command::ExitWithError(error) => {
                         tracing::error!("{}", error);
@@ -101,13 +131,36 @@ impl<'a, B: Backend> WindowState<'a, B> {
                 break;
             }
         }
+        self.stop_recording();
+    }
+
+    fn start_recording(&mut self, name: &str) {
+        info!("Recording macro '{}'", name);
+        self.recording.start(name);
+    }
+
+    fn stop_recording(&mut self) {
+        let Some(recorder) = self.recording.stop() else {
+            return;
+        };
+        let name = recorder.name().to_owned();
+        self.status = match recorder.save() {
+            Ok(path) => {
+                info!("Macro '{}' saved to {:?}", name, path);
+                format!("Macro '{}' saved to {}", name, path.display())
+            }
+            Err(e) => {
+                warn!("Macro '{}' not saved: {}", name, e);
+                format!("Macro '{}' not saved", name)
+            }
+        };
     }
     fn process(&mut self, msg: JerryMessage) {
         match msg {
             JerryMessage::MouseMove(x, y) => {
                 if self.relative_move {
-                    self.cursor.x = self.mon_size.x / 2 + x;
-                    self.cursor.y = self.mon_size.y / 2 + y;
+                    self.cursor.x = (self.mon_size.x / 2).saturating_add(x);
+                    self.cursor.y = (self.mon_size.y / 2).saturating_add(y);
                 } else {
                     self.cursor = super::Coord { x, y }
                 }
@@ -145,21 +198,88 @@ impl<'a, B: Backend> WindowState<'a, B> {
                 self.relative_move = relative;
             }
             JerryMessage::SessionEnd => self.active = false,
-            JerryMessage::Clipboard(content, _) => {
-                // self._clip_len = content.len();
-                // self._clip = content[..15].to_string();
-            }
+            JerryMessage::Clipboard(_, _) => {}
             JerryMessage::Request(_) => {}
             JerryMessage::Handshake(_echo, _) => {}
             JerryMessage::Heartbeat => self.heart = 3,
         }
     }
 
+    fn clipboard_added(&mut self, added: bool) {
+        if !added {
+            return;
+        }
+        // keep the selection on the same entry
+        let selected = match self.clipboard_selection.selected() {
+            None => 0,
+            Some(i) => std::cmp::min(i + 1, self.clipboard.len() - 1),
+        };
+        self.clipboard_selection.select(Some(selected));
+    }
+
+    fn on_key(&mut self, key: KeyCode) {
+        if let ConnectionState::AwaitingDecision(_) = self.connection_state {
+            if let Some(decision) = super::decision_from_key(key) {
+                _ = self.decisions.try_send(decision);
+                return;
+            }
+        }
+        if key == KeyCode::Char('r') {
+            match self.recording.progress().is_some() {
+                true => self.stop_recording(),
+                false => {
+                    let since_epoch = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default();
+                    self.start_recording(&format!("macro_{}", since_epoch.as_secs()));
+                }
+            }
+            return;
+        }
+        let len = self.clipboard.len();
+        if len == 0 {
+            return;
+        }
+        let selected = self.clipboard_selection.selected().unwrap_or(0);
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.clipboard_selection
+                    .select(Some(selected.saturating_sub(1)));
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.clipboard_selection
+                    .select(Some(std::cmp::min(selected + 1, len - 1)));
+            }
+            KeyCode::Enter => self.publish_clipboard_entry(selected),
+            _ => {}
+        }
+    }
+
+    fn publish_clipboard_entry(&mut self, index: usize) {
+        let Some(entry) = self.clipboard.get(index) else {
+            return;
+        };
+        let result = arboard::Clipboard::new().and_then(|mut c| c.set_text(entry.content.clone()));
+        self.status = match result {
+            Ok(_) => {
+                info!("Clipboard history entry #{} copied to the clipboard", index);
+                format!("Clipboard: entry #{} copied to the local clipboard", index)
+            }
+            Err(e) => {
+                warn!("Clipboard history entry #{} not copied: {}", index, e);
+                format!("Clipboard: failed to copy entry #{}", index)
+            }
+        };
+    }
+
     fn get_color(&self) -> Color {
         match (&self.connection_state, self.active) {
             (_, true) => Color::Green,
             (ConnectionState::HandshakeSuccess(_), _) => Color::LightBlue,
             (ConnectionState::ReadError(_), _) => Color::Red,
+            (ConnectionState::AwaitingDecision(_) | ConnectionState::Stopped(_), _) => {
+                Color::Yellow
+            }
             (_, _) => Color::DarkGray,
         }
     }
@@ -198,11 +318,61 @@ impl<'a, B: Backend> WindowState<'a, B> {
                 .direction(Direction::Vertical)
                 .constraints([Constraint::Min(8), Constraint::Length(5)].as_ref())
                 .split(f.size());
+            let top = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+                .split(chunks[0]);
+            let side = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(6)].as_ref())
+                .split(top[1]);
 
+            let preview_len = (side[0].width as usize).saturating_sub(14);
+            let clipboard_items: Vec<ListItem> = self
+                .clipboard
+                .iter()
+                .map(|entry| {
+                    let direction = match entry.transfer {
+                        Some(Transfer::Incoming) => "<-",
+                        Some(Transfer::Outgoing) => "->",
+                        None => "==",
+                    };
+                    ListItem::new(format!(
+                        "{} {:>4} {}",
+                        direction,
+                        format_age(entry.time.elapsed()),
+                        entry.preview(preview_len)
+                    ))
+                })
+                .collect();
+            let clipboard_list = List::new(clipboard_items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Plain)
+                        .title(Title::from("Clipboard [Up/Down, Enter: copy]"))
+                        .style(Style::default().fg(active_color)),
+                )
+                .highlight_style(Style::default().fg(Color::Black).bg(active_color))
+                .highlight_symbol(">");
+
+            let link_panel = Paragraph::new(link_lines(self.link.as_ref())).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Plain)
+                    .title(Title::from("Link"))
+                    .style(Style::default().fg(active_color)),
+            );
+
+            let recording_title = match self.recording.progress() {
+                Some((name, len)) => format!("REC {} ({} events) [r: stop]", name, len),
+                None => String::from("[r: record macro]"),
+            };
             let canvas_monitor = Canvas::default()
                 .marker(symbols::Marker::Braille)
                 .block(
                     Block::default()
+                        .title(Title::from(recording_title))
                         .borders(Borders::RIGHT | Borders::LEFT)
                         .border_type(BorderType::Thick)
                         .border_style(Style::default())
@@ -211,7 +381,7 @@ impl<'a, B: Backend> WindowState<'a, B> {
                 .paint(|ctx| {
                     ctx.print(
                         self.cursor.x as f64,
-                        (-self.cursor.y) as f64,
+                        -(self.cursor.y as f64),
                         Span::styled("O", Style::default().fg(active_color)),
                     );
                 })
@@ -233,6 +403,7 @@ impl<'a, B: Backend> WindowState<'a, B> {
                 .marker(symbols::Marker::Braille)
                 .block(
                     Block::default()
+                        .title(Title::from(format!("Server: {}", self.server)))
                         .borders(Borders::ALL)
                         .border_type(BorderType::Double)
                         .border_style(Style::default())
@@ -274,6 +445,14 @@ impl<'a, B: Backend> WindowState<'a, B> {
                         ),
                     );
 
+                    if !self.status.is_empty() {
+                        ctx.print(
+                            0.0,
+                            2.0,
+                            Span::styled(self.status.clone(), Style::default().fg(active_color)),
+                        );
+                    }
+
                     let stre = format!(
                         "Keys pressed: {}",
                         self.keys
@@ -292,10 +471,36 @@ impl<'a, B: Backend> WindowState<'a, B> {
                 .x_bounds([0.0, 70.0])
                 .y_bounds([0.0, 5.0]);
 
-            f.render_widget(canvas_monitor, chunks[0]);
+            f.render_widget(canvas_monitor, top[0]);
+            f.render_stateful_widget(clipboard_list, side[0], &mut self.clipboard_selection);
+            f.render_widget(link_panel, side[1]);
             f.render_widget(canvas_btn, chunks[1]);
 
             //f.render_stateful_widget(widget, area, state)
         });
     }
 }
+
+fn link_lines(link: Option<&LinkSnapshot>) -> String {
+    let Some(link) = link else {
+        return String::from("No data");
+    };
+    format!(
+        "{}\n{:.0} msg/s\nin {:.1} kB/s  out {:.1} kB/s\nqueue {} (peak {})  merged {}",
+        link.latency(),
+        link.messages_per_sec,
+        link.bytes_in_per_sec / 1000.0,
+        link.bytes_out_per_sec / 1000.0,
+        link.queue.depth,
+        link.queue.peak,
+        link.queue.merged
+    )
+}
+
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s => format!("{}h", s / 3600),
+    }
+}
@@ -0,0 +1,388 @@
+//! Golden tests of the emulation: message sequences are fed through the
+//! `ContextAwareMessageHandler` and the emulator calls are compared with `tests/golden/`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the files after an intended change.
+use jerry::configuration::args::LocalhostArgs;
+use jerry::configuration::{
+    get_session_info_localhost, ClipboardDirection, ClipboardPolicy, ClipboardRestore,
+    SessionParams,
+};
+use jerry::core::clipboard::{ClipboardFilter, MemoryClipboard, SystemClipboard};
+use jerry::core::emulator::RecordingEmulator;
+use jerry::core::macros::MacroRecording;
+use jerry::core::message_handler::{ContextAwareMessageHandler, EmulationPause};
+use jerry::core::{Button, Direction, JerryMessage, Request, State};
+use jerry::{Command, MessageConsumer};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+enum Input {
+    Message(JerryMessage),
+    /// The connection is lost
+    Disconnected,
+    /// The emulation is paused or resumed by the controller
+    Pause(bool),
+}
+
+use Input::*;
+
+fn params() -> SessionParams {
+    let args = LocalhostArgs {
+        width: 1920,
+        height: 1080,
+        guid: uuid::Uuid::nil(),
+        name: String::from("Test"),
+        port: jerry::DEFAULT_PORT,
+        password: String::from("2002"),
+        reverse: false,
+    };
+    let mut params = get_session_info_localhost(&args).servers.remove(0);
+    // the system clipboard is left alone
+    params.clipboard_restore = ClipboardRestore::Keep;
+    params
+}
+
+/// Each input is followed by the emulator calls and the response it caused.
+fn run(params: SessionParams, inputs: Vec<Input>) -> String {
+    let emulator = RecordingEmulator::with_cursor(400, 300);
+    let log = emulator.log();
+    let (transmitter, events) = mpsc::sync_channel(1024);
+    let pause = EmulationPause::default();
+    let mut handler =
+        ContextAwareMessageHandler::with_emulator(transmitter, params, Box::new(emulator))
+            .with_pause(pause.clone())
+            .with_clipboard(Box::new(MemoryClipboard::default()));
+
+    let mut transcript = String::new();
+    let mut previous = None;
+    for input in inputs {
+        let response = match input {
+            Message(msg) => {
+                writeln!(transcript, "> {:?}", msg).unwrap();
+                handler.consume(msg)
+            }
+            Disconnected => {
+                writeln!(transcript, "> disconnected").unwrap();
+                handler.disconnected();
+                None
+            }
+            Pause(paused) => {
+                writeln!(transcript, "> paused: {}", paused).unwrap();
+                pause.set(paused);
+                None
+            }
+        };
+        for recorded in log.take() {
+            assert!(previous <= Some(recorded.at), "Timestamps out of order");
+            previous = Some(recorded.at);
+            writeln!(transcript, "  {}", recorded.call).unwrap();
+        }
+        if let Some(response) = response {
+            writeln!(transcript, "< {:?}", response).unwrap();
+        }
+        // the views would stop the handler if the events were dropped
+        while events.try_recv().is_ok() {}
+    }
+    transcript
+}
+
+fn check(name: &str, inputs: Vec<Input>) {
+    check_with(name, params(), inputs)
+}
+
+fn check_with(name: &str, params: SessionParams, inputs: Vec<Input>) {
+    let actual = run(params, inputs);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.golden", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1)", path.display(), e));
+    assert!(
+        expected == actual,
+        "{} differs, actual:\n{}",
+        path.display(),
+        actual
+    );
+}
+
+fn key(code: u32, state: State) -> Input {
+    Message(JerryMessage::Key(code, state))
+}
+
+fn click(button: Button, state: State) -> Input {
+    Message(JerryMessage::MouseClick(button, state))
+}
+
+fn begin(relative_move: bool) -> Input {
+    Message(JerryMessage::SessionBegin { relative_move })
+}
+
+const SHIFT_LEFT: u32 = 0xA0;
+const CONTROL_LEFT: u32 = 0xA2;
+const KEY_A: u32 = 0x41;
+const KEY_C: u32 = 0x43;
+
+#[test]
+fn session_transitions() {
+    check(
+        "session_transitions",
+        vec![
+            // nothing is emulated outside of a session
+            key(KEY_A, State::PRESSED),
+            Message(JerryMessage::MouseMove(5, 5)),
+            begin(false),
+            Message(JerryMessage::MouseMove(100, 200)),
+            // a session is already active, the mode does not change
+            begin(true),
+            Message(JerryMessage::MouseMove(1, 1)),
+            Message(JerryMessage::Request(Request::MOUSE_POSITION)),
+            Message(JerryMessage::SessionEnd),
+            Message(JerryMessage::MouseMove(3, 3)),
+            Message(JerryMessage::SessionEnd),
+            begin(true),
+            Message(JerryMessage::MouseMove(-4, 2)),
+            Message(JerryMessage::MouseMove(0, -1)),
+            Message(JerryMessage::SessionEnd),
+            Disconnected,
+        ],
+    );
+}
+
+#[test]
+fn stuck_keys_released_on_session_end() {
+    check(
+        "stuck_keys_session_end",
+        vec![
+            begin(false),
+            key(SHIFT_LEFT, State::PRESSED),
+            key(KEY_A, State::PRESSED),
+            click(Button::LEFT, State::PRESSED),
+            key(KEY_A, State::RELEASED),
+            Message(JerryMessage::SessionEnd),
+            // released once only
+            Disconnected,
+        ],
+    );
+}
+
+#[test]
+fn stuck_keys_released_on_disconnect() {
+    check(
+        "stuck_keys_disconnect",
+        vec![
+            begin(true),
+            key(KEY_C, State::PRESSED),
+            key(CONTROL_LEFT, State::PRESSED),
+            click(Button::XBUTTON2, State::PRESSED),
+            click(Button::RIGHT, State::PRESSED),
+            Disconnected,
+            // the next connection starts without pressed keys
+            begin(true),
+            Message(JerryMessage::SessionEnd),
+        ],
+    );
+}
+
+#[test]
+fn held_keys_are_released_while_paused() {
+    check(
+        "paused",
+        vec![
+            begin(false),
+            key(SHIFT_LEFT, State::PRESSED),
+            click(Button::LEFT, State::PRESSED),
+            Pause(true),
+            key(KEY_A, State::PRESSED),
+            Message(JerryMessage::MouseMove(10, 10)),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_DOWN, 120)),
+            key(SHIFT_LEFT, State::RELEASED),
+            click(Button::LEFT, State::RELEASED),
+            key(KEY_A, State::RELEASED),
+            Pause(false),
+            key(KEY_C, State::PRESSED),
+            Message(JerryMessage::SessionEnd),
+        ],
+    );
+}
+
+#[test]
+fn unexpected_events_are_discarded() {
+    check(
+        "unexpected_events",
+        vec![
+            begin(false),
+            key(KEY_A, State::RELEASED),
+            click(Button::MIDDLE, State::RELEASED),
+            key(300, State::PRESSED),
+            // key repeat
+            key(KEY_A, State::PRESSED),
+            key(KEY_A, State::PRESSED),
+            key(KEY_A, State::RELEASED),
+            key(KEY_A, State::RELEASED),
+            Message(JerryMessage::SessionEnd),
+        ],
+    );
+}
+
+#[test]
+fn wheel_amounts_are_passed_through() {
+    check(
+        "wheel",
+        vec![
+            begin(false),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_DOWN, 120)),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_DOWN, 40)),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_UP, 360)),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_LEFT, 120)),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_RIGHT, 60)),
+            Message(JerryMessage::SessionEnd),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_DOWN, 120)),
+        ],
+    );
+}
+
+#[test]
+fn extreme_values_do_not_panic() {
+    check(
+        "extreme_values",
+        vec![
+            begin(true),
+            Message(JerryMessage::MouseMove(i32::MAX, i32::MIN)),
+            Message(JerryMessage::MouseMove(i32::MAX, i32::MIN)),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_DOWN, i32::MAX)),
+            Message(JerryMessage::MouseWheel(Direction::SCROLL_UP, i32::MIN)),
+            key(u32::MAX, State::PRESSED),
+            key(256, State::RELEASED),
+            key(0xFF, State::PRESSED),
+            Message(JerryMessage::Request(Request::MOUSE_POSITION)),
+            Disconnected,
+        ],
+    );
+}
+
+#[test]
+fn blocked_incoming_clipboard_is_not_answered() {
+    let mut params = params();
+    params.clipboard_filter = ClipboardFilter::new(&ClipboardPolicy {
+        direction: ClipboardDirection::Out,
+        ..ClipboardPolicy::default()
+    })
+    .unwrap();
+    check_with(
+        "blocked_clipboard",
+        params,
+        vec![
+            begin(false),
+            Message(JerryMessage::Clipboard(String::from("secret"), false)),
+            Message(JerryMessage::SessionEnd),
+        ],
+    );
+}
+
+#[test]
+fn a_busy_view_does_not_hold_up_the_emulation() {
+    let emulator = RecordingEmulator::new();
+    let log = emulator.log();
+    // the events are never read
+    let (transmitter, _events) = mpsc::sync_channel(1);
+    let mut handler =
+        ContextAwareMessageHandler::with_emulator(transmitter, params(), Box::new(emulator));
+    handler.consume(JerryMessage::SessionBegin {
+        relative_move: true,
+    });
+    for _ in 0..10 {
+        handler.consume(JerryMessage::MouseMove(1, 1));
+    }
+    let moves = log
+        .calls()
+        .iter()
+        .filter(|recorded| recorded.call.to_string().starts_with("mouse_move"))
+        .count();
+    assert_eq!(moves, 10);
+    assert!(!handler.finished());
+}
+
+#[test]
+fn received_input_is_recorded_by_the_handler() {
+    let (transmitter, _events) = mpsc::sync_channel(1024);
+    let recording = MacroRecording::default();
+    recording.start("handler");
+    let mut handler = ContextAwareMessageHandler::with_emulator(
+        transmitter,
+        params(),
+        Box::new(RecordingEmulator::new()),
+    )
+    .with_recording(recording.clone());
+    for msg in [
+        JerryMessage::SessionBegin {
+            relative_move: true,
+        },
+        JerryMessage::Key(KEY_A, State::PRESSED),
+        JerryMessage::MouseMove(3, 3),
+        JerryMessage::Key(KEY_A, State::RELEASED),
+        JerryMessage::SessionEnd,
+    ] {
+        handler.consume(msg);
+    }
+    assert_eq!(recording.progress(), Some((String::from("handler"), 3)));
+}
+
+/// Content of the clipboard after a session during which the server sent its clipboard,
+/// and the contents kept in the clipboard history.
+fn clipboard_after_session(restore: ClipboardRestore, end: Input) -> (Option<String>, Vec<String>) {
+    let mut clipboard = MemoryClipboard::default();
+    clipboard.set_text(String::from("before")).unwrap();
+    let mut params = params();
+    params.clipboard_restore = restore;
+    let (transmitter, events) = mpsc::sync_channel(1024);
+    let mut handler = ContextAwareMessageHandler::with_emulator(
+        transmitter,
+        params,
+        Box::new(RecordingEmulator::new()),
+    )
+    .with_clipboard(Box::new(clipboard.clone()));
+
+    handler.consume(JerryMessage::SessionBegin {
+        relative_move: false,
+    });
+    handler.consume(JerryMessage::Clipboard(String::from("server"), false));
+    assert_eq!(clipboard.content().as_deref(), Some("server"));
+    match end {
+        Disconnected => handler.disconnected(),
+        Message(msg) => _ = handler.consume(msg),
+        Pause(_) => unreachable!(),
+    }
+    let kept = events
+        .try_iter()
+        .filter_map(|command| match command {
+            Command::ClipboardKept(content) => Some(content),
+            _ => None,
+        })
+        .collect();
+    (clipboard.content(), kept)
+}
+
+#[test]
+fn clipboard_policy_is_applied_on_session_end_and_disconnect() {
+    for end in [|| Message(JerryMessage::SessionEnd), || Disconnected] {
+        let content = |restore| clipboard_after_session(restore, end());
+        let kept = |content: &[&str]| content.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            content(ClipboardRestore::Keep),
+            (Some(String::from("server")), vec![])
+        );
+        assert_eq!(
+            content(ClipboardRestore::Restore),
+            (Some(String::from("before")), vec![])
+        );
+        assert_eq!(
+            content(ClipboardRestore::Merge),
+            (Some(String::from("before")), kept(&["before", "server"]))
+        );
+    }
+}
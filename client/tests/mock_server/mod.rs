@@ -0,0 +1,321 @@
+//! In-process Jerry server for the end-to-end tests.
+//!
+//! Does what the C# server does on a connection: the key exchange, the ChaCha20 framing,
+//! the `INIT_INFO` request answered by the `Echo` with the handshake result, the
+//! heartbeats and the pongs. After a successful handshake it plays a script of messages
+//! and records everything the client sends.
+#![allow(dead_code)]
+
+use eyre::{bail, eyre, Result};
+use jerry::core::{JerryMessage, Request};
+use jerry::proto_rs::proto_in::{Echo, HandshakeResult, Heartbeat};
+use jerry::proto_rs::proto_out::{ClientInfo, Goodbye, SlaveMessage_oneof_response as Response};
+use jerry::proto_rs::{ProtoInMsg, ProtoOutMsg};
+use jerry::security::{key_exchange, Decryptor, Encryptor};
+use jerry::serialization::{FrameReader, FrameWriter};
+use std::time::{Duration, Instant};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Interval of the heartbeats, well below the timeout of the client
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
+const ACCEPT_TIMEOUT: Duration = Duration::from_secs(10);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One step of the script played once the handshake succeeded.
+pub enum Step {
+    /// Sends the message to the client
+    Send(JerryMessage),
+    /// Sends the request and waits for the response
+    Request(Request),
+    Pause(Duration),
+    /// Closes the connection without waiting for the client
+    Disconnect,
+    /// Stops sending, heartbeats included, but keeps the connection open
+    /// like a server that went away without closing it
+    Hang,
+}
+
+/// Everything the client sent on the connection.
+#[derive(Debug, Default)]
+pub struct Transcript {
+    /// Answer to the `INIT_INFO` request of the handshake
+    pub client_info: Option<ClientInfo>,
+    /// Messages other than pings, pongs and the goodbye, in the order received
+    pub responses: Vec<ProtoOutMsg>,
+    /// Answers to the heartbeats that are not one way
+    pub pongs: usize,
+    pub pings: usize,
+    pub goodbye: Option<Goodbye>,
+}
+
+pub struct MockServerBuilder {
+    password: String,
+    ping: bool,
+    one_way: bool,
+    script: Vec<Step>,
+}
+
+/// Serves a single connection on a port of the loopback interface.
+pub struct MockServer {
+    port: u16,
+    script_done: Option<oneshot::Receiver<()>>,
+    task: JoinHandle<Result<Transcript>>,
+}
+
+impl MockServer {
+    /// The server expects the default password of `LocalhostArgs`.
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder {
+            password: String::from("2002"),
+            ping: false,
+            one_way: true,
+            script: Vec::new(),
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Waits until the last step of the script was played.
+    pub async fn script_done(&mut self) -> Result<()> {
+        let done = self
+            .script_done
+            .take()
+            .ok_or_else(|| eyre!("Already waited for the script"))?;
+        done.await
+            .map_err(|_| eyre!("The script was not played to the end"))
+    }
+
+    /// Waits until the client closes the connection (or the script disconnects it).
+    pub async fn transcript(self) -> Result<Transcript> {
+        self.task.await?
+    }
+}
+
+impl MockServerBuilder {
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_owned();
+        self
+    }
+
+    /// Announces in the handshake that the pings of the client are answered.
+    pub fn ping(mut self, ping: bool) -> Self {
+        self.ping = ping;
+        self
+    }
+
+    /// Heartbeats that are not one way have to be answered with a pong.
+    pub fn one_way_heartbeats(mut self, one_way: bool) -> Self {
+        self.one_way = one_way;
+        self
+    }
+
+    pub fn step(mut self, step: Step) -> Self {
+        self.script.push(step);
+        self
+    }
+
+    /// Each message is sent in a step of its own.
+    pub fn send(mut self, messages: impl IntoIterator<Item = JerryMessage>) -> Self {
+        self.script.extend(messages.into_iter().map(Step::Send));
+        self
+    }
+
+    /// Listens on a free port right away, the client can connect as soon as this returns.
+    pub async fn start(self) -> Result<MockServer> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let port = listener.local_addr()?.port();
+        let (done_tx, done_rx) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let (stream, _) = tokio::time::timeout(ACCEPT_TIMEOUT, listener.accept())
+                .await
+                .map_err(|_| eyre!("The client did not connect"))??;
+            drop(listener);
+            self.serve(stream, done_tx).await
+        });
+        Ok(MockServer {
+            port,
+            script_done: Some(done_rx),
+            task,
+        })
+    }
+
+    async fn serve(
+        self,
+        mut stream: TcpStream,
+        script_done: oneshot::Sender<()>,
+    ) -> Result<Transcript> {
+        stream.set_nodelay(true)?;
+        // the first key encrypts what the server sends, as in the Gatekeeper of the server
+        let (output_key, input_key) = key_exchange::get_secrets_chacha(&mut stream).await?;
+        let (read_half, write_half) = stream.into_split();
+        let reader = FrameReader::new(read_half, Some(Decryptor::new(input_key)));
+        let writer = FrameWriter::new(write_half, Some(Encryptor::new(output_key)));
+
+        let mut transcript = Transcript::default();
+        let (output, output_rx) = mpsc::unbounded_channel();
+        let (responses_tx, mut responses) = mpsc::unbounded_channel();
+        let close = CancellationToken::new();
+        let hang = CancellationToken::new();
+        let writing = tokio::spawn(write_loop(
+            writer,
+            output_rx,
+            self.one_way,
+            hang.clone(),
+            close.clone(),
+        ));
+        let reading = tokio::spawn(read_loop(reader, output.clone(), responses_tx));
+
+        output.send(JerryMessage::Request(Request::INIT_INFO).into())?;
+        let info = match next_response(&mut responses).await?.response {
+            Some(Response::init_info(info)) => info,
+            other => bail!("Expected the client info, received {:?}", other),
+        };
+        let accepted = info.Password == self.password;
+        transcript.client_info = Some(info);
+        let mut echo = Echo::new();
+        echo.set_ping(self.ping);
+        match accepted {
+            true => echo.set_result(HandshakeResult::Success),
+            false => {
+                echo.set_result(HandshakeResult::Rejection);
+                echo.set_message(String::from("Password rejected"));
+            }
+        }
+        let mut handshake = ProtoInMsg::new();
+        handshake.set_handshake(echo);
+        output.send(handshake)?;
+
+        if accepted {
+            for step in self.script {
+                match step {
+                    Step::Send(msg) => output.send(msg.into())?,
+                    Step::Request(request) => {
+                        output.send(JerryMessage::Request(request).into())?;
+                        let response = next_response(&mut responses).await?;
+                        transcript.responses.push(response);
+                    }
+                    Step::Pause(duration) => tokio::time::sleep(duration).await,
+                    Step::Disconnect => {
+                        // the client closes its side once it notices
+                        close.cancel();
+                        break;
+                    }
+                    Step::Hang => {
+                        hang.cancel();
+                        break;
+                    }
+                }
+            }
+        }
+        _ = script_done.send(());
+        // the connection stays open until the client closes it
+        let counts = reading.await?;
+        close.cancel();
+        writing.await??;
+        Ok(counts.into_transcript(transcript, responses))
+    }
+}
+
+async fn next_response(
+    responses: &mut mpsc::UnboundedReceiver<ProtoOutMsg>,
+) -> Result<ProtoOutMsg> {
+    tokio::time::timeout(RESPONSE_TIMEOUT, responses.recv())
+        .await
+        .map_err(|_| eyre!("No response within {:?}", RESPONSE_TIMEOUT))?
+        .ok_or_else(|| eyre!("Connection closed by the client"))
+}
+
+/// What the reader handled on its own
+#[derive(Default)]
+struct Counts {
+    pongs: usize,
+    pings: usize,
+    goodbye: Option<Goodbye>,
+}
+
+impl Counts {
+    fn into_transcript(
+        self,
+        mut transcript: Transcript,
+        mut responses: mpsc::UnboundedReceiver<ProtoOutMsg>,
+    ) -> Transcript {
+        while let Ok(response) = responses.try_recv() {
+            transcript.responses.push(response);
+        }
+        transcript.pongs = self.pongs;
+        transcript.pings = self.pings;
+        transcript.goodbye = self.goodbye;
+        transcript
+    }
+}
+
+/// Answers the pings and passes the other messages on until the client closes the connection.
+async fn read_loop(
+    mut reader: FrameReader<OwnedReadHalf>,
+    output: mpsc::UnboundedSender<ProtoInMsg>,
+    responses: mpsc::UnboundedSender<ProtoOutMsg>,
+) -> Counts {
+    let mut counts = Counts::default();
+    while let Ok(mut msg) = reader.read_message::<ProtoOutMsg>().await {
+        match msg.response.take() {
+            Some(Response::ping(ping)) => {
+                counts.pings += 1;
+                let mut pong = ProtoInMsg::new();
+                pong.set_pong(ping);
+                _ = output.send(pong);
+            }
+            Some(Response::pong(_)) => counts.pongs += 1,
+            Some(Response::goodbye(goodbye)) => counts.goodbye = Some(goodbye),
+            response => {
+                msg.response = response;
+                _ = responses.send(msg);
+            }
+        }
+    }
+    counts
+}
+
+/// Sends the queued messages and a heartbeat every `HEARTBEAT_INTERVAL`
+/// until `close` is cancelled, nothing once `hang` is.
+async fn write_loop(
+    mut writer: FrameWriter<OwnedWriteHalf>,
+    mut output: mpsc::UnboundedReceiver<ProtoInMsg>,
+    one_way: bool,
+    hang: CancellationToken,
+    close: CancellationToken,
+) -> Result<()> {
+    let epoch = Instant::now();
+    let mut heartbeats = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        let msg = tokio::select! {
+            // the script is sent before the connection is closed
+            biased;
+            Some(msg) = output.recv() => msg,
+            _ = close.cancelled() => break,
+            _ = hang.cancelled() => {
+                // dropping the writer would close the connection
+                close.cancelled().await;
+                break;
+            }
+            _ = heartbeats.tick() => {
+                let mut heartbeat = Heartbeat::new();
+                heartbeat.set_one_way(one_way);
+                heartbeat.set_timestamp(epoch.elapsed().as_micros() as u64);
+                let mut msg = ProtoInMsg::new();
+                msg.set_heartbeat(heartbeat);
+                msg
+            }
+        };
+        // the client may already be gone
+        if writer.write_message(&msg).await.is_err() {
+            return Ok(());
+        }
+    }
+    Ok(writer.shutdown().await?)
+}
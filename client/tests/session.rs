@@ -0,0 +1,326 @@
+//! End-to-end tests of a `Session` against the in-process mock server.
+mod mock_server;
+
+use jerry::configuration::args::LocalhostArgs;
+use jerry::configuration::get_session_info_localhost;
+use jerry::connection::{ConnectionState, ControlRequest, ShutdownSignal};
+use jerry::core::emulator::{CallLog, RecordingEmulator};
+use jerry::core::{Button, Direction, GoodbyeCause, JerryMessage, Request, State};
+use jerry::error::{ConnectionError, Rejection, TransportError};
+use jerry::proto_rs::proto_out::SlaveMessage_oneof_response as Response;
+use jerry::{Command, Session};
+use mock_server::{MockServer, Step};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const CURSOR: (i32, i32) = (960, 540);
+
+/// Session connected to the mock server, emulating into a `RecordingEmulator`.
+struct Client {
+    calls: CallLog,
+    shutdown: ShutdownSignal,
+    controls: mpsc::Sender<ControlRequest>,
+    states: mpsc::UnboundedReceiver<ConnectionState>,
+    /// States received so far
+    seen: Vec<ConnectionState>,
+    session: JoinHandle<eyre::Result<()>>,
+}
+
+impl Client {
+    fn connect(port: u16, password: &str) -> Client {
+        let args = LocalhostArgs {
+            width: 1920,
+            height: 1080,
+            guid: uuid::Uuid::from_u128(0x6f1b2c1e_0000_4000_8000_000000000001),
+            name: String::from("Test"),
+            port,
+            password: password.to_owned(),
+            reverse: false,
+        };
+        let calls = CallLog::default();
+        let emulated = calls.clone();
+        let mut builder = Session::builder(get_session_info_localhost(&args)).emulator(move |_| {
+            Box::new(RecordingEmulator::with_cursor(CURSOR.0, CURSOR.1).log_to(emulated.clone()))
+        });
+        let events = builder.subscribe(1024);
+        let (controls, requests) = mpsc::channel(8);
+        let session = builder.controls(requests).build();
+        let shutdown = session.shutdown_signal();
+
+        // the events are dropped, the connection states are kept
+        let (states_tx, states) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            for event in events {
+                match event {
+                    Command::ConnectionResult(state) => _ = states_tx.send(state),
+                    Command::Halt => break,
+                    _ => {}
+                }
+            }
+        });
+        Client {
+            calls,
+            shutdown,
+            controls,
+            states,
+            seen: Vec::new(),
+            session: tokio::spawn(session.run()),
+        }
+    }
+
+    fn calls(&self) -> Vec<String> {
+        let calls = self.calls.calls();
+        calls
+            .iter()
+            .map(|recorded| recorded.call.to_string())
+            .collect()
+    }
+
+    /// Waits until the session reports a matching state.
+    async fn wait_for(&mut self, expected: impl Fn(&ConnectionState) -> bool) {
+        let wait = async {
+            while let Some(state) = self.states.recv().await {
+                let found = expected(&state);
+                self.seen.push(state);
+                if found {
+                    return;
+                }
+            }
+            panic!("Session ended, states: {:?}", self.seen);
+        };
+        tokio::time::timeout(TIMEOUT, wait)
+            .await
+            .unwrap_or_else(|_| panic!("Timed out, states: {:?}", self.seen));
+    }
+
+    /// Says goodbye to the server and returns every state reported.
+    async fn stop(self) -> Vec<ConnectionState> {
+        self.shutdown.request(GoodbyeCause::USER_EXIT);
+        self.finished().await
+    }
+
+    /// Waits until the session ends on its own.
+    async fn finished(mut self) -> Vec<ConnectionState> {
+        tokio::time::timeout(TIMEOUT, self.session)
+            .await
+            .expect("Session did not end")
+            .unwrap()
+            .unwrap();
+        while let Some(state) = self.states.recv().await {
+            self.seen.push(state);
+        }
+        self.seen
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn scripted_input_reaches_the_emulator() {
+    let mut server = MockServer::builder()
+        .send([
+            JerryMessage::SessionBegin {
+                relative_move: false,
+            },
+            JerryMessage::MouseMove(10, 20),
+            JerryMessage::Key(0x41, State::PRESSED),
+            JerryMessage::Key(0x41, State::RELEASED),
+            JerryMessage::MouseClick(Button::LEFT, State::PRESSED),
+            JerryMessage::MouseClick(Button::LEFT, State::RELEASED),
+            JerryMessage::MouseWheel(Direction::SCROLL_DOWN, 120),
+            JerryMessage::SessionEnd,
+        ])
+        .step(Step::Request(Request::MOUSE_POSITION))
+        .start()
+        .await
+        .unwrap();
+    let client = Client::connect(server.port(), "2002");
+    server.script_done().await.unwrap();
+    let calls = client.calls();
+    let states = client.stop().await;
+    let transcript = server.transcript().await.unwrap();
+
+    assert_eq!(
+        calls,
+        vec![
+            "mouse_move_to(10, 20)",
+            "key_down(A)",
+            "key_up(A)",
+            "mouse_down(LEFT)",
+            "mouse_up(LEFT)",
+            "mouse_wheel(SCROLL_DOWN, 120)",
+        ]
+    );
+    assert!(states.contains(&ConnectionState::ConnectedSecured));
+    assert!(states.contains(&ConnectionState::HandshakeSuccess(String::new())));
+
+    let info = transcript.client_info.unwrap();
+    assert_eq!((info.Width, info.Height), (1920, 1080));
+    assert_eq!(info.Name, "Test");
+    match transcript.responses.as_slice() {
+        [response] => match &response.response {
+            Some(Response::cursor(cursor)) => assert_eq!((cursor.x, cursor.y), CURSOR),
+            other => panic!("Unexpected response {:?}", other),
+        },
+        other => panic!("Unexpected responses {:?}", other),
+    }
+    let goodbye = transcript.goodbye.expect("No goodbye received");
+    assert_eq!(goodbye.Reason, GoodbyeCause::USER_EXIT);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disconnect_request_waits_for_reconnect() {
+    let server = MockServer::builder().start().await.unwrap();
+    let mut client = Client::connect(server.port(), "2002");
+    client
+        .wait_for(|state| matches!(state, ConnectionState::HandshakeSuccess(_)))
+        .await;
+
+    client
+        .controls
+        .send(ControlRequest::Disconnect)
+        .await
+        .unwrap();
+    client
+        .wait_for(|state| *state == ConnectionState::Disconnected)
+        .await;
+    let transcript = server.transcript().await.unwrap();
+    let goodbye = transcript.goodbye.expect("No goodbye received");
+    assert_eq!(goodbye.Reason, GoodbyeCause::USER_EXIT);
+    assert_eq!(goodbye.Description, "The client was asked to disconnect");
+    // nothing happens until the reconnection is requested
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(client.states.try_recv().is_err());
+
+    client
+        .controls
+        .send(ControlRequest::Reconnect)
+        .await
+        .unwrap();
+    client
+        .wait_for(|state| *state == ConnectionState::Establishing)
+        .await;
+    let states = client.stop().await;
+    let attempts = states
+        .iter()
+        .filter(|state| **state == ConnectionState::Establishing)
+        .count();
+    assert_eq!(attempts, 2, "{:?}", states);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejected_password_ends_the_session() {
+    let server = MockServer::builder()
+        .password("secret")
+        .send([JerryMessage::SessionBegin {
+            relative_move: false,
+        }])
+        .start()
+        .await
+        .unwrap();
+    let client = Client::connect(server.port(), "2002");
+    // without an answer to the prompt the reconnection stops
+    let states = client.finished().await;
+    let transcript = server.transcript().await.unwrap();
+
+    assert!(states.contains(&ConnectionState::HandshakeFailed(Rejection::WrongPassword)));
+    assert!(matches!(
+        states.last(),
+        Some(ConnectionState::AwaitingDecision(_))
+    ));
+    assert_eq!(transcript.client_info.unwrap().Password, "2002");
+    assert!(transcript.goodbye.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pressed_keys_are_released_when_the_connection_drops() {
+    let server = MockServer::builder()
+        .send([
+            JerryMessage::SessionBegin {
+                relative_move: true,
+            },
+            JerryMessage::Key(0x43, State::PRESSED),
+            JerryMessage::MouseClick(Button::RIGHT, State::PRESSED),
+            JerryMessage::MouseMove(5, -5),
+        ])
+        .step(Step::Disconnect)
+        .start()
+        .await
+        .unwrap();
+    let mut client = Client::connect(server.port(), "2002");
+    client
+        .wait_for(|state| matches!(state, ConnectionState::ReadError(_)))
+        .await;
+    let calls = client.calls();
+    client.stop().await;
+    let transcript = server.transcript().await.unwrap();
+
+    assert_eq!(
+        calls,
+        vec![
+            "key_down(C)",
+            "mouse_down(RIGHT)",
+            "mouse_move_rel(5, -5)",
+            "key_up(C)",
+            "mouse_up(RIGHT)",
+        ]
+    );
+    // the connection was already gone
+    assert!(transcript.goodbye.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn heartbeats_and_pings_are_answered() {
+    let mut server = MockServer::builder()
+        .ping(true)
+        .one_way_heartbeats(false)
+        .step(Step::Pause(Duration::from_millis(1_500)))
+        .start()
+        .await
+        .unwrap();
+    let client = Client::connect(server.port(), "2002");
+    server.script_done().await.unwrap();
+    client.stop().await;
+    let transcript = server.transcript().await.unwrap();
+
+    assert!(transcript.pongs >= 2, "{} pongs", transcript.pongs);
+    assert!(transcript.pings >= 1, "{} pings", transcript.pings);
+    assert!(transcript.responses.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_silent_server_is_detected() {
+    let server = MockServer::builder()
+        .send([JerryMessage::SessionBegin {
+            relative_move: true,
+        }])
+        .step(Step::Hang)
+        .start()
+        .await
+        .unwrap();
+    let mut client = Client::connect(server.port(), "2002");
+    client
+        .wait_for(|state| {
+            matches!(
+                state,
+                ConnectionState::ReadError(ConnectionError::Transport(TransportError::Timeout(_)))
+            )
+        })
+        .await;
+    client.stop().await;
+}
+
+#[tokio::test]
+async fn a_current_thread_runtime_is_refused() {
+    let args = LocalhostArgs {
+        width: 1920,
+        height: 1080,
+        guid: uuid::Uuid::from_u128(0x6f1b2c1e_0000_4000_8000_000000000001),
+        name: String::from("Test"),
+        port: 1,
+        password: String::from("2002"),
+        reverse: false,
+    };
+    let session = Session::builder(get_session_info_localhost(&args)).build();
+    assert!(session.run().await.is_err());
+}